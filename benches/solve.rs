@@ -0,0 +1,111 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::hint::black_box;
+use sudoku_solver::batch;
+use sudoku_solver::solver::{Heuristic, SolveOptions, Solver};
+use sudoku_solver::state::State;
+
+const PUZZLES: [(&str, &str); 3] = [
+    (
+        "easy",
+        "301086504046521070500000001400800002080347900009050038004090200008734090007208103",
+    ),
+    (
+        "hard",
+        "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+    ),
+    (
+        "17-clue",
+        "000000010400000000020000000000050407008000300001090000300400200050100000000806000",
+    ),
+];
+
+fn solve(c: &mut Criterion) {
+    let mut group = c.benchmark_group("solve");
+
+    for (name, puzzle) in PUZZLES {
+        let state = State::from(puzzle);
+
+        group.bench_with_input(BenchmarkId::new("solve", name), &state, |b, state| {
+            let solver = Solver::new();
+            b.iter(|| {
+                let mut state = state.clone();
+                solver.solve(&mut state).unwrap();
+                black_box(state)
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("in_place", name), &state, |b, state| {
+            let mut solver = Solver::new();
+            let mut scratch = state.clone();
+            b.iter(|| {
+                scratch.clone_from(state);
+                solver.solve_in_place(&mut scratch).unwrap();
+                black_box(&scratch);
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn heuristics(c: &mut Criterion) {
+    let mut group = c.benchmark_group("heuristic");
+
+    for (name, puzzle) in &PUZZLES[1..] {
+        let state = State::from(*puzzle);
+
+        for heuristic in [
+            Heuristic::Ascending,
+            Heuristic::LeastConstraining,
+            Heuristic::Random,
+        ] {
+            let options = SolveOptions::default().heuristic(heuristic).seed(1);
+            let solver = Solver::new().with_options(options);
+            group.bench_with_input(
+                BenchmarkId::new(heuristic.to_string(), name),
+                &state,
+                |b, state| {
+                    b.iter(|| {
+                        let mut state = state.clone();
+                        solver.solve(&mut state).unwrap();
+                        black_box(state)
+                    })
+                },
+            );
+        }
+    }
+
+    group.finish();
+}
+
+fn throughput(c: &mut Criterion) {
+    let puzzles: Vec<State> = PUZZLES
+        .iter()
+        .cycle()
+        .take(30)
+        .map(|(_, p)| State::from(*p))
+        .collect();
+
+    let mut group = c.benchmark_group("batch");
+    group.throughput(Throughput::Elements(puzzles.len() as u64));
+
+    group.bench_function("solve_batch", |b| {
+        b.iter(|| batch::solve_batch(puzzles.clone()).count())
+    });
+
+    group.bench_function("in_place", |b| {
+        let mut solver = Solver::new();
+        let mut scratch = puzzles[0].clone();
+        b.iter(|| {
+            for puzzle in &puzzles {
+                scratch.clone_from(puzzle);
+                solver.solve_in_place(&mut scratch).unwrap();
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, solve, heuristics, throughput);
+criterion_main!(benches);
@@ -0,0 +1,6 @@
+// Re-exports the bindings, which the linker then keeps as the exports of the
+// shared library.
+#[cfg(feature = "ffi")]
+pub use sudoku_solver::ffi::*;
+#[cfg(feature = "wasm")]
+pub use sudoku_solver::wasm::*;
@@ -0,0 +1,30 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sudoku_solver::io::{self, FileFormat};
+use sudoku_solver::killer;
+use sudoku_solver::shapes;
+use sudoku_solver::state::State;
+
+// Feeds arbitrary text to every parser. None may panic, and a puzzle that
+// parses must read back the same from its own output.
+fuzz_target!(|data: &[u8]| {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    if let Ok(state) = input.parse::<State>() {
+        let reparsed: State = state.to_string().parse().expect("output parses");
+        assert!(reparsed.iter_cells().eq(state.iter_cells()));
+
+        let _ = state.clone().with_regions(input);
+    }
+    for size in [4, 9] {
+        let _ = State::parse_with_size(input, size);
+        let _ = killer::parse_cages(input, size);
+        let _ = shapes::parse_inequalities(input, size);
+    }
+    for format in [FileFormat::Sdk, FileFormat::Sdm, FileFormat::Ss] {
+        let _ = io::read_puzzles(data, format);
+    }
+});
@@ -0,0 +1,47 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::time::Duration;
+use sudoku_solver::solver::{Backend, SolveOptions, Solver};
+use sudoku_solver::state::{SolveError, State};
+
+// Solves grids of 81 digits made from the input, with the propagation and
+// dlx backends. Neither may panic, every solution must keep the givens and
+// break no rule, and the two must agree on whether there is a solution.
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 81 {
+        return;
+    }
+    let puzzle: String = data[..81]
+        .iter()
+        .map(|b| char::from(b'0' + b % 10))
+        .collect();
+    let Ok(state) = puzzle.parse::<State>() else {
+        return;
+    };
+
+    let options = SolveOptions {
+        timeout: Some(Duration::from_secs(1)),
+        ..SolveOptions::default()
+    };
+    let mut solved = vec![];
+    for backend in [Backend::Propagation, Backend::Dlx] {
+        let mut solution = state.clone();
+        match Solver::with_backend(backend)
+            .with_options(options)
+            .solve(&mut solution)
+        {
+            Ok(()) => {
+                assert_eq!(solution.verify_solution(), Ok(()));
+                for (given, (_, _, value)) in state.iter_cells().zip(solution.iter_cells()) {
+                    assert!(given.2.is_none() || given.2 == value);
+                }
+                solved.push(true);
+            }
+            Err(SolveError::TimedOut) => return,
+            Err(_) => solved.push(false),
+        }
+    }
+
+    assert_eq!(solved[0], solved[1]);
+});
@@ -0,0 +1,69 @@
+#ifndef SUDOKU_SOLVER_H
+#define SUDOKU_SOLVER_H
+
+/* Generated by cbindgen from src/ffi.rs; do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Bytes an output buffer needs for the largest supported grid and its NUL.
+ */
+#define SUDOKU_MAX_OUTPUT ((31 * 31) + 1)
+
+/**
+ * A pointer was null, or the input was not UTF-8 or not a valid puzzle.
+ */
+#define SUDOKU_ERR_INVALID -1
+
+/**
+ * The puzzle has no solution.
+ */
+#define SUDOKU_ERR_UNSOLVABLE -2
+
+/**
+ * The solver panicked.
+ */
+#define SUDOKU_ERR_INTERNAL -3
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Solves `puzzle`, writing the solution as one line of digits to `out`.
+ * Returns 0 on success or a negative `SUDOKU_ERR_*` code.
+ *
+ * # Safety
+ * `puzzle` must be a NUL-terminated string and `out` must point to at least
+ * `SUDOKU_MAX_OUTPUT` writable bytes.
+ */
+int sudoku_solve(const char *puzzle, char *out);
+
+/**
+ * Generates a 9×9 puzzle with a unique solution, writing it as one line of
+ * digits to `out`. `difficulty` is one of "easy", "medium", "hard", "expert"
+ * or "diabolical". Returns 0 on success or a negative `SUDOKU_ERR_*` code.
+ *
+ * # Safety
+ * `difficulty` must be a NUL-terminated string and `out` must point to at
+ * least `SUDOKU_MAX_OUTPUT` writable bytes.
+ */
+int sudoku_generate(const char *difficulty, char *out);
+
+/**
+ * Rates `puzzle`, returning its difficulty from 0 (easy) to 4 (diabolical)
+ * or a negative `SUDOKU_ERR_*` code.
+ *
+ * # Safety
+ * `puzzle` must be a NUL-terminated string.
+ */
+int sudoku_rate(const char *puzzle);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* SUDOKU_SOLVER_H */
@@ -0,0 +1,191 @@
+use crate::state::{SolveError, State};
+use crate::strategies::{self, Step, Technique};
+use crate::trace::cell_name;
+use std::fmt::Display;
+
+// Backdoors larger than this are not searched for, since each extra cell
+// multiplies the work by the number of open cells.
+const MAX_BACKDOOR: usize = 2;
+
+// Where a puzzle stops being easy: what singles leave behind, and how much
+// help they need to finish it. Puzzle setters use this to tune difficulty.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Analysis {
+    pub size: usize,
+    // `None` if naked and hidden singles solve the puzzle.
+    pub bottleneck: Option<Bottleneck>,
+    // The fewest cells that, given their solution digit, let singles solve
+    // the puzzle, or `None` if more than `MAX_BACKDOOR` are needed.
+    pub backdoor: Option<Vec<usize>>,
+}
+
+// The grid once singles run out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bottleneck {
+    pub open: usize,
+    // The easiest deduction that gets past it, or `None` if only guessing
+    // does.
+    pub step: Option<Step>,
+    // The open cell with the fewest candidates, where a guess would be made,
+    // and how many candidates it has.
+    pub cell: usize,
+    pub branching: usize,
+}
+
+impl Display for Analysis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Some(bottleneck) = &self.bottleneck else {
+            return write!(f, "singles solve the puzzle, so it has no bottleneck");
+        };
+
+        writeln!(
+            f,
+            "bottleneck: singles stop with {} cells open",
+            bottleneck.open
+        )?;
+        match &bottleneck.step {
+            Some(step) => writeln!(f, "next step: {}", step.describe(self.size))?,
+            None => writeln!(f, "next step: only guessing makes progress")?,
+        }
+        writeln!(
+            f,
+            "branching factor: {} at {}",
+            bottleneck.branching,
+            cell_name(bottleneck.cell, self.size)
+        )?;
+
+        match &self.backdoor {
+            Some(cells) => {
+                let names: Vec<String> = cells.iter().map(|c| cell_name(*c, self.size)).collect();
+                write!(f, "backdoor: {} ({})", cells.len(), names.join(", "))
+            }
+            None => write!(f, "backdoor: more than {MAX_BACKDOOR}"),
+        }
+    }
+}
+
+// Finds the bottleneck of a puzzle and its smallest backdoor for singles.
+pub fn analyze(state: &State) -> Result<Analysis, SolveError> {
+    let size = state.size();
+    let mut solution = state.clone();
+    solution.solve()?;
+
+    let mut stuck = state.clone();
+    if solve_with_singles(&mut stuck) {
+        return Ok(Analysis {
+            size,
+            bottleneck: None,
+            backdoor: Some(vec![]),
+        });
+    }
+
+    let open: Vec<usize> = (0..size * size)
+        .filter(|idx| stuck.candidates_at(*idx).len() > 1)
+        .collect();
+    let cell = *open
+        .iter()
+        .min_by_key(|idx| stuck.candidates_at(**idx).len())
+        .expect("an unsolved grid has open cells");
+    let bottleneck = Bottleneck {
+        open: open.len(),
+        step: strategies::find_step(&stuck),
+        cell,
+        branching: stuck.candidates_at(cell).len(),
+    };
+
+    let values = solution.values();
+    let backdoor = (1..=MAX_BACKDOOR).find_map(|n| {
+        combinations(&open, n).find(|cells| {
+            let mut state = stuck.clone();
+            for idx in cells {
+                state.collapse(*idx, values[*idx]);
+            }
+            solve_with_singles(&mut state)
+        })
+    });
+
+    Ok(Analysis {
+        size,
+        bottleneck: Some(bottleneck),
+        backdoor,
+    })
+}
+
+fn solve_with_singles(state: &mut State) -> bool {
+    strategies::solve_logically_upto(state, Technique::HiddenSingle, &mut |_| {})
+        .expect("placing solution digits never contradicts")
+}
+
+// Every way of picking `n` of `items`, in lexicographic order.
+pub(crate) fn combinations(items: &[usize], n: usize) -> impl Iterator<Item = Vec<usize>> + '_ {
+    let mut picks: Vec<usize> = (0..n).collect();
+    let mut done = n > items.len();
+
+    std::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+        let combination = picks.iter().map(|p| items[*p]).collect();
+
+        match (0..n).rev().find(|i| picks[*i] < items.len() - n + i) {
+            Some(i) => {
+                picks[i] += 1;
+                for j in i + 1..n {
+                    picks[j] = picks[j - 1] + 1;
+                }
+            }
+            None => done = true,
+        }
+
+        Some(combination)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{analyze, combinations};
+    use crate::state::State;
+    use crate::strategies::Technique;
+
+    #[test]
+    fn can_list_combinations() {
+        let pairs: Vec<Vec<usize>> = combinations(&[3, 5, 7], 2).collect();
+        assert_eq!(pairs, vec![vec![3, 5], vec![3, 7], vec![5, 7]]);
+        assert_eq!(combinations(&[3, 5], 3).count(), 0);
+    }
+
+    #[test]
+    fn can_analyze_puzzles() {
+        let easy = analyze(&State::from(
+            "301086504046521070500000001400800002080347900009050038004090200008734090007208103",
+        ))
+        .unwrap();
+        assert_eq!(easy.bottleneck, None);
+        assert_eq!(easy.backdoor, Some(vec![]));
+
+        let hard = analyze(&State::from(
+            "290050000000703000008002000070004308000500900080001024700000540001060090920000000",
+        ))
+        .unwrap();
+        let bottleneck = hard.bottleneck.clone().unwrap();
+        assert_eq!(bottleneck.open, 51);
+        assert_eq!(
+            bottleneck.step.map(|s| s.technique),
+            Some(Technique::LockedCandidates)
+        );
+        assert_eq!(bottleneck.branching, 2);
+        assert_eq!(hard.backdoor, Some(vec![8]));
+
+        let guessing = analyze(&State::from(
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+        ))
+        .unwrap();
+        assert_eq!(guessing.bottleneck.unwrap().step, None);
+        assert_eq!(guessing.backdoor.map(|cells| cells.len()), Some(2));
+        assert!(hard
+            .to_string()
+            .starts_with("bottleneck: singles stop with 51 cells open"));
+
+        assert!(analyze(&State::from("1100000000000000")).is_err());
+    }
+}
@@ -0,0 +1,305 @@
+use crate::search::Interrupt;
+use crate::state::{SolveError, State};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+// Settings for the simulated annealing backend. The temperature starts at
+// `temperature` and is multiplied by `cooling` every iteration, reheating
+// once it has cooled off without finding a solution.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Annealing {
+    pub iterations: usize,
+    pub temperature: f64,
+    pub cooling: f64,
+    pub seed: Option<u64>,
+}
+
+impl Default for Annealing {
+    fn default() -> Self {
+        Annealing {
+            iterations: 1_000_000,
+            temperature: 1.0,
+            cooling: 0.9999,
+            seed: None,
+        }
+    }
+}
+
+const MIN_TEMPERATURE: f64 = 0.02;
+// swaps made between checks for a timeout or cancellation
+const INTERRUPT_INTERVAL: usize = 1024;
+
+// Fills each block with its missing digits at random, then swaps pairs of
+// unfixed cells within a block to drive the cost down to zero: the number of
+// peers sharing a digit, cells holding a digit propagation ruled out, the
+// distance of each cage from its sum and how far each shape is from holding.
+pub(crate) fn solve(
+    state: &mut State,
+    params: &Annealing,
+    interrupt: &Interrupt,
+) -> Result<(), SolveError> {
+    state
+        .propagate_constraints()
+        .map_err(|e| e.in_grid(state.constraints()))?;
+    if state.is_solved() {
+        return Ok(());
+    }
+
+    let mut rng = match params.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let mut board = Board::fill(state, &mut rng);
+    let mut cost = board.cost();
+    let mut temperature = params.temperature;
+
+    for i in 0..params.iterations {
+        if cost == 0 {
+            break;
+        }
+        if i % INTERRUPT_INTERVAL == 0 {
+            interrupt.check()?;
+        }
+
+        let block = &board.free[rng.gen_range(0..board.free.len())];
+        let (a, b) = match block.choose_multiple(&mut rng, 2).collect::<Vec<_>>()[..] {
+            [a, b] => (*a, *b),
+            _ => continue,
+        };
+
+        let delta = board.swap_delta(a, b);
+        if delta <= 0 || rng.gen::<f64>() < (-(delta as f64) / temperature).exp() {
+            board.values.swap(a, b);
+            cost += delta;
+        }
+
+        temperature *= params.cooling;
+        if temperature < MIN_TEMPERATURE {
+            temperature = params.temperature;
+        }
+    }
+
+    if cost != 0 {
+        return Err(SolveError::IterationLimit(params.iterations));
+    }
+
+    for (idx, val) in board.values.iter().enumerate() {
+        state.collapse(idx, *val);
+    }
+
+    Ok(())
+}
+
+struct Board<'a> {
+    state: &'a State,
+    values: Vec<u8>,
+    // the cells of each block left to fill, for blocks with two or more
+    free: Vec<Vec<usize>>,
+    masks: Vec<u32>,
+    cages_of: Vec<Option<usize>>,
+    // the shapes covering each cell, which may overlap
+    shapes_of: Vec<Vec<usize>>,
+}
+
+impl<'a> Board<'a> {
+    fn fill<R: Rng>(state: &'a State, rng: &mut R) -> Self {
+        let constraints = state.constraints();
+        let mut values = state.values();
+        let mut free = vec![];
+
+        let masks = state.candidate_masks();
+        for block in constraints.blocks() {
+            let mut missing: Vec<u8> = (1..=state.size() as u8)
+                .filter(|v| !block.iter().any(|i| values[*i] == *v))
+                .collect();
+            missing.shuffle(rng);
+
+            let cells: Vec<usize> = block.iter().copied().filter(|i| values[*i] == 0).collect();
+            let assigned = match_candidates(&cells, &missing, &masks).unwrap_or(missing);
+            for (idx, val) in cells.iter().zip(assigned) {
+                values[*idx] = val;
+            }
+            if cells.len() > 1 {
+                free.push(cells);
+            }
+        }
+
+        let mut cages_of = vec![None; values.len()];
+        for (c, cage) in constraints.cages().iter().enumerate() {
+            for idx in &cage.cells {
+                cages_of[*idx] = Some(c);
+            }
+        }
+
+        let mut shapes_of = vec![vec![]; values.len()];
+        for (s, shape) in constraints.shapes().iter().enumerate() {
+            for idx in shape.cells() {
+                shapes_of[idx].push(s);
+            }
+        }
+
+        Board {
+            state,
+            values,
+            free,
+            masks,
+            cages_of,
+            shapes_of,
+        }
+    }
+
+    // Whether the digit in `idx` is still one of its candidates.
+    fn allows(&self, idx: usize) -> bool {
+        self.masks[idx] & (1 << self.values[idx]) != 0
+    }
+
+    fn cost(&self) -> i64 {
+        let clashes: i64 = (0..self.values.len()).map(|i| self.clashes(i)).sum();
+        let excluded = (0..self.values.len()).filter(|i| !self.allows(*i)).count() as i64;
+        let cages: i64 = (0..self.state.constraints().cages().len())
+            .map(|c| self.cage_error(c))
+            .sum();
+        let shapes: i64 = (0..self.state.constraints().shapes().len())
+            .map(|s| self.shape_error(s))
+            .sum();
+
+        clashes / 2 + excluded + cages + shapes
+    }
+
+    // Peers of `idx` holding the same digit.
+    fn clashes(&self, idx: usize) -> i64 {
+        self.state
+            .constraints()
+            .get_constrained_inds(idx)
+            .iter()
+            .filter(|p| self.values[**p] == self.values[idx])
+            .count() as i64
+    }
+
+    fn cage_error(&self, cage: usize) -> i64 {
+        let cage = &self.state.constraints().cages()[cage];
+        let sum: u32 = cage.cells.iter().map(|i| self.values[*i] as u32).sum();
+
+        (sum as i64 - cage.sum as i64).abs()
+    }
+
+    fn shape_error(&self, shape: usize) -> i64 {
+        self.state.constraints().shapes()[shape].error(&self.values)
+    }
+
+    // The change in cost from swapping two cells of the same block, which
+    // are peers and so never clash with each other.
+    fn swap_delta(&mut self, a: usize, b: usize) -> i64 {
+        let mut cages: Vec<usize> = [self.cages_of[a], self.cages_of[b]]
+            .into_iter()
+            .flatten()
+            .collect();
+        cages.dedup();
+        let mut shapes: Vec<usize> = [&self.shapes_of[a], &self.shapes_of[b]]
+            .into_iter()
+            .flatten()
+            .copied()
+            .collect();
+        shapes.sort_unstable();
+        shapes.dedup();
+
+        let local = |board: &Self| {
+            board.clashes(a)
+                + board.clashes(b)
+                + !board.allows(a) as i64
+                + !board.allows(b) as i64
+                + cages.iter().map(|c| board.cage_error(*c)).sum::<i64>()
+                + shapes.iter().map(|s| board.shape_error(*s)).sum::<i64>()
+        };
+
+        let before = local(self);
+        self.values.swap(a, b);
+        let after = local(self);
+        self.values.swap(a, b);
+
+        after - before
+    }
+}
+
+// Gives each cell one of `digits`, all different and among the cell's
+// candidates, trying the digits in the order given.
+fn match_candidates(cells: &[usize], digits: &[u8], masks: &[u32]) -> Option<Vec<u8>> {
+    fn assign(cells: &[usize], digits: &[u8], masks: &[u32], chosen: &mut Vec<u8>) -> bool {
+        let Some(idx) = cells.get(chosen.len()) else {
+            return true;
+        };
+
+        for val in digits {
+            if masks[*idx] & (1 << val) != 0 && !chosen.contains(val) {
+                chosen.push(*val);
+                if assign(cells, digits, masks, chosen) {
+                    return true;
+                }
+                chosen.pop();
+            }
+        }
+
+        false
+    }
+
+    let mut chosen = vec![];
+    assign(cells, digits, masks, &mut chosen).then_some(chosen)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{solve, Annealing};
+    use crate::killer::Cage;
+    use crate::search::Interrupt;
+    use crate::state::{SolveError, State};
+
+    fn seeded(iterations: usize) -> Annealing {
+        Annealing {
+            iterations,
+            seed: Some(11),
+            ..Annealing::default()
+        }
+    }
+
+    #[test]
+    fn can_solve_by_annealing() {
+        let puzzle =
+            "239001008060703000000000000000030080700500300024000100000000501000650470000000062";
+        let mut expected = State::from(puzzle);
+        expected.solve().unwrap();
+
+        let mut state = State::from(puzzle);
+        solve(&mut state, &seeded(200_000), &Interrupt::default()).unwrap();
+        assert_eq!(state.values(), expected.values());
+
+        let mut state = State::from(&"0".repeat(16) as &str);
+        solve(&mut state, &seeded(10_000), &Interrupt::default()).unwrap();
+        assert!(state.is_solved() && state.satisfies_rules());
+    }
+
+    #[test]
+    fn can_anneal_cages() {
+        let mut state = State::from(&"0".repeat(16) as &str).with_cages(vec![
+            Cage {
+                sum: 3,
+                cells: vec![0, 1],
+            },
+            Cage {
+                sum: 7,
+                cells: vec![2, 3],
+            },
+        ]);
+        solve(&mut state, &seeded(10_000), &Interrupt::default()).unwrap();
+
+        assert!(state.satisfies_rules());
+    }
+
+    #[test]
+    fn can_give_up() {
+        let mut state = State::from(&"0".repeat(81) as &str);
+
+        assert_eq!(
+            solve(&mut state, &seeded(1), &Interrupt::default()),
+            Err(SolveError::IterationLimit(1))
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use crate::rating::{self, Difficulty, Rating};
+use crate::solver::Solver;
+use crate::state::{digit_char, SolveError, State};
+use crate::trace::cell_name;
+use std::fmt::Display;
+
+// Solutions are counted up to this many when comparing clues, so very open
+// grids don't take forever; past it every clue looks the same.
+const MAX_COUNTED: usize = 100;
+
+// A clue to add to a puzzle: `value` in cell `cell`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clue {
+    pub cell: usize,
+    pub value: u8,
+}
+
+// Clues suggested for a puzzle being set, in the order they were picked, and
+// the puzzle with them added.
+#[derive(Debug, Clone)]
+pub struct Suggestions {
+    pub clues: Vec<Clue>,
+    pub puzzle: State,
+    pub rating: Rating,
+    pub target: Option<Difficulty>,
+}
+
+impl Suggestions {
+    pub fn reached_target(&self) -> bool {
+        self.target
+            .is_none_or(|target| self.rating.difficulty == target)
+    }
+}
+
+impl Display for Suggestions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let size = self.puzzle.size();
+        if self.clues.is_empty() {
+            writeln!(f, "no clues needed")?;
+        }
+        for clue in &self.clues {
+            writeln!(
+                f,
+                "add {} at {}",
+                digit_char(clue.value),
+                cell_name(clue.cell, size)
+            )?;
+        }
+        write!(f, "rating: {}", self.rating)?;
+
+        match self.target {
+            Some(target) if self.rating.difficulty < target => write!(
+                f,
+                "\nclues only make a puzzle easier, so remove some to reach {target}"
+            ),
+            Some(target) if !self.reached_target() => {
+                write!(f, "\nno single clue brings it down to {target}")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+// Suggests clues to add to a partly set puzzle, greedily: first until its
+// solution is unique, each time the clue that leaves the fewest solutions,
+// then, given a target difficulty, until it is rated no harder than that,
+// each time the clue that keeps it hardest without going below the target.
+// The first solution found is the one the clues are taken from.
+pub fn suggest(puzzle: &State, target: Option<Difficulty>) -> Result<Suggestions, SolveError> {
+    let solver = Solver::new();
+    let mut puzzle = puzzle.clone();
+    let mut clues = vec![];
+
+    loop {
+        let solutions: Vec<State> = solver.solutions(&puzzle).take(2).collect();
+        let (first, second) = match solutions.as_slice() {
+            [] => return Err(SolveError::Unsolvable),
+            [_] => break,
+            [first, second, ..] => (first.values(), second.values()),
+        };
+
+        // a clue where the two differ rules out the second, so each step
+        // makes progress
+        let clue = (0..first.len())
+            .filter(|idx| first[*idx] != second[*idx])
+            .map(|cell| Clue {
+                cell,
+                value: first[cell],
+            })
+            .min_by_key(|clue| with_clue(&puzzle, *clue).count_solutions(MAX_COUNTED))
+            .expect("two different solutions differ in some cell");
+        puzzle = with_clue(&puzzle, clue);
+        clues.push(clue);
+    }
+
+    let solution = solver
+        .solutions(&puzzle)
+        .next()
+        .ok_or(SolveError::Unsolvable)?;
+    let solution = solution.values();
+    let mut rating = rating::rate(&puzzle)?;
+    while let Some(target) = target.filter(|t| rating.difficulty > *t) {
+        let givens = puzzle.given_values();
+        let best = (0..givens.len())
+            .filter(|idx| givens[*idx] == 0)
+            .filter_map(|cell| {
+                let clue = Clue {
+                    cell,
+                    value: solution[cell],
+                };
+                let rating = rating::rate(&with_clue(&puzzle, clue)).ok()?;
+                (rating.difficulty >= target).then_some((clue, rating))
+            })
+            .min_by_key(|(_, rating)| (rating.difficulty, u32::MAX - rating.score));
+        let Some((clue, next)) = best else {
+            break;
+        };
+
+        puzzle = with_clue(&puzzle, clue);
+        clues.push(clue);
+        rating = next;
+    }
+
+    Ok(Suggestions {
+        clues,
+        puzzle,
+        rating,
+        target,
+    })
+}
+
+fn with_clue(puzzle: &State, clue: Clue) -> State {
+    let mut values = puzzle.given_values();
+    values[clue.cell] = clue.value;
+
+    puzzle.with_values(&values)
+}
+
+#[cfg(test)]
+mod test {
+    use super::suggest;
+    use crate::rating::Difficulty;
+    use crate::state::{SolveError, State};
+
+    #[test]
+    fn can_suggest_clues_for_uniqueness() {
+        // the first row of a 4×4 solution leaves several ways to finish it
+        let suggestions = suggest(&State::from("1234000000000000"), None).unwrap();
+
+        assert!(!suggestions.clues.is_empty());
+        assert!(suggestions.puzzle.has_unique_solution());
+        assert!(suggestions.reached_target());
+        assert!(suggestions.to_string().starts_with("add "));
+
+        let unique = State::from(
+            "301086504046521070500000001400800002080347900009050038004090200008734090007208103",
+        );
+        let suggestions = suggest(&unique, None).unwrap();
+        assert!(suggestions.clues.is_empty());
+
+        assert_eq!(
+            suggest(&State::from("1100000000000000"), None).unwrap_err(),
+            SolveError::Unsolvable
+        );
+    }
+
+    #[test]
+    fn can_suggest_clues_for_difficulty() {
+        let diabolical = State::from(
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+        );
+        let suggestions = suggest(&diabolical, Some(Difficulty::Hard)).unwrap();
+        assert!(suggestions.reached_target());
+        assert_eq!(suggestions.rating.difficulty, Difficulty::Hard);
+        assert!(suggestions.puzzle.has_unique_solution());
+
+        let easy = State::from(
+            "301086504046521070500000001400800002080347900009050038004090200008734090007208103",
+        );
+        let suggestions = suggest(&easy, Some(Difficulty::Expert)).unwrap();
+        assert!(!suggestions.reached_target());
+        assert!(suggestions.to_string().contains("remove some"));
+    }
+}
@@ -0,0 +1,595 @@
+use crate::constraints::Constraints;
+use crate::solver::Solver;
+use crate::state::{SolveError, State};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::fs;
+use std::io::{self, BufRead, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchEntry {
+    pub id: String,
+    pub puzzle: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchResult {
+    pub id: String,
+    pub outcome: Result<State, SolveError>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BatchSummary {
+    pub solved: usize,
+    pub failed: usize,
+    // entries passed over because a checkpoint showed them done
+    pub skipped: usize,
+}
+
+// Settings for `solve_stream`.
+#[derive(Debug, Clone)]
+pub struct StreamOptions {
+    pub solver: Solver,
+    // The rules every puzzle is read under, see `read_puzzle`.
+    pub rules: Option<Constraints>,
+    // Results written between flushes of the output and the checkpoint.
+    pub flush_every: usize,
+    // A file holding how far the run got, written at every flush, so a
+    // stopped run picks up where it left off. Removed once the input is done.
+    pub checkpoint: Option<PathBuf>,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        StreamOptions {
+            solver: Solver::new(),
+            rules: None,
+            flush_every: 1000,
+            checkpoint: None,
+        }
+    }
+}
+
+// Reads one puzzle per line, optionally preceded by an id column as in
+// `id,puzzle`. Blank lines and a leading `id,puzzle` header are skipped, and
+// puzzles without an id are numbered by line.
+pub fn read_entries<R: BufRead>(reader: R) -> impl Iterator<Item = io::Result<BatchEntry>> {
+    reader
+        .lines()
+        .enumerate()
+        .filter_map(|(n, line)| match line {
+            Ok(line) => parse_entry(n + 1, &line).map(Ok),
+            Err(e) => Some(Err(e)),
+        })
+}
+
+fn parse_entry(line_number: usize, line: &str) -> Option<BatchEntry> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+
+    match line.split_once(',') {
+        Some((id, puzzle))
+            if line_number == 1 && id.trim() == "id" && puzzle.trim() == "puzzle" =>
+        {
+            None
+        }
+        Some((id, puzzle)) => Some(BatchEntry {
+            id: id.trim().to_string(),
+            puzzle: puzzle.trim().to_string(),
+        }),
+        None => Some(BatchEntry {
+            id: line_number.to_string(),
+            puzzle: line.to_string(),
+        }),
+    }
+}
+
+// Reads the puzzle of an entry, under `rules` if given: the constraints of
+// the variants, regions, cages and shapes every puzzle of a batch shares,
+// built once on an empty grid. Puzzles must then be of the same size.
+pub fn read_puzzle(puzzle: &str, rules: Option<&Constraints>) -> Result<State, SolveError> {
+    match rules {
+        Some(rules) => {
+            Ok(State::parse_with_size(puzzle, rules.size())?.with_constraints(rules.clone()))
+        }
+        None => puzzle.parse(),
+    }
+}
+
+pub fn solve_batch<I>(puzzles: I) -> impl Iterator<Item = Result<State, SolveError>>
+where
+    I: IntoIterator<Item = State>,
+{
+    let solver = Solver::new();
+
+    puzzles.into_iter().map(move |mut state| {
+        solver.solve(&mut state)?;
+        Ok(state)
+    })
+}
+
+pub fn solve_entries<'a, I>(
+    solver: &'a Solver,
+    rules: Option<&'a Constraints>,
+    entries: I,
+) -> impl Iterator<Item = BatchResult> + 'a
+where
+    I: IntoIterator<Item = BatchEntry>,
+    I::IntoIter: 'a,
+{
+    entries.into_iter().map(move |entry| BatchResult {
+        outcome: solve_entry(solver, rules, &entry),
+        id: entry.id,
+    })
+}
+
+// Solves entries across `jobs` threads (or every core when `None`), keeping
+// results in input order.
+#[cfg(feature = "parallel")]
+pub fn solve_entries_parallel(
+    solver: &Solver,
+    rules: Option<&Constraints>,
+    entries: Vec<BatchEntry>,
+    jobs: Option<usize>,
+) -> Result<Vec<BatchResult>, rayon::ThreadPoolBuildError> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs.unwrap_or(0))
+        .build()?;
+
+    Ok(solve_on(&pool, solver, rules, entries))
+}
+
+#[cfg(feature = "parallel")]
+fn solve_on(
+    pool: &rayon::ThreadPool,
+    solver: &Solver,
+    rules: Option<&Constraints>,
+    entries: Vec<BatchEntry>,
+) -> Vec<BatchResult> {
+    pool.install(|| {
+        entries
+            .into_par_iter()
+            .map(|entry| BatchResult {
+                outcome: solve_entry(solver, rules, &entry),
+                id: entry.id,
+            })
+            .collect()
+    })
+}
+
+#[cfg(feature = "parallel")]
+pub fn solve_batch_parallel(puzzles: Vec<State>) -> Vec<Result<State, SolveError>> {
+    let solver = Solver::new();
+
+    puzzles
+        .into_par_iter()
+        .map(|mut state| {
+            solver.solve(&mut state)?;
+            Ok(state)
+        })
+        .collect()
+}
+
+pub(crate) fn solve_entry(
+    solver: &Solver,
+    rules: Option<&Constraints>,
+    entry: &BatchEntry,
+) -> Result<State, SolveError> {
+    let mut state = read_puzzle(&entry.puzzle, rules)?;
+    solver.solve(&mut state)?;
+
+    Ok(state)
+}
+
+const HEADER: &str = "id,status,solution,error";
+
+// Writes one `id,status,solution,error` line per result.  The error is
+// quoted, as messages can hold commas.
+pub fn write_results<I, W>(results: I, writer: &mut W) -> io::Result<BatchSummary>
+where
+    I: IntoIterator<Item = BatchResult>,
+    W: Write,
+{
+    let mut summary = BatchSummary::default();
+
+    writeln!(writer, "{HEADER}")?;
+    for result in results {
+        write_result(result, writer, &mut summary)?;
+    }
+    writer.flush()?;
+
+    Ok(summary)
+}
+
+fn write_result<W: Write>(
+    result: BatchResult,
+    writer: &mut W,
+    summary: &mut BatchSummary,
+) -> io::Result<()> {
+    match result.outcome {
+        Ok(state) => {
+            summary.solved += 1;
+            writeln!(writer, "{},solved,{state},", result.id)
+        }
+        Err(e) => {
+            summary.failed += 1;
+            let error = e.to_string().replace('"', "\"\"");
+            writeln!(writer, "{},failed,,\"{error}\"", result.id)
+        }
+    }
+}
+
+// How far a stopped `solve_stream` run got: the entries and input lines done,
+// and the bytes of input read and output written up to them. Saved as the
+// four numbers on one line.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Checkpoint {
+    pub entries: usize,
+    pub lines: usize,
+    pub input: u64,
+    pub output: u64,
+}
+
+// The checkpoint written by `solve_stream` at `path`, or the start of the
+// input if there is none yet.
+pub fn read_checkpoint(path: &Path) -> io::Result<Checkpoint> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid checkpoint");
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Checkpoint::default()),
+        Err(e) => return Err(e),
+    };
+
+    let numbers: Vec<u64> = text
+        .split_whitespace()
+        .map(|n| n.parse().map_err(|_| invalid()))
+        .collect::<io::Result<_>>()?;
+    match numbers[..] {
+        [entries, lines, input, output] => Ok(Checkpoint {
+            entries: entries as usize,
+            lines: lines as usize,
+            input,
+            output,
+        }),
+        _ => Err(invalid()),
+    }
+}
+
+fn write_checkpoint(path: &Path, checkpoint: &Checkpoint) -> io::Result<()> {
+    let Checkpoint {
+        entries,
+        lines,
+        input,
+        output,
+    } = checkpoint;
+
+    // written beside it and renamed over it, so a stop mid-write leaves the
+    // old checkpoint whole
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    fs::write(&temp, format!("{entries} {lines} {input} {output}\n"))?;
+    fs::rename(&temp, path)
+}
+
+// Opens the output of a run stopped at `checkpoint` to be continued, cutting
+// off anything written after the checkpoint was saved, as those entries are
+// solved again.
+pub fn resume_output(path: &Path, checkpoint: &Checkpoint) -> io::Result<fs::File> {
+    let mut file = fs::File::options().write(true).open(path)?;
+    file.set_len(checkpoint.output)?;
+    file.seek(SeekFrom::End(0))?;
+
+    Ok(file)
+}
+
+// Solves entries as they are read and writes each result as it comes, so
+// memory stays the same however large the input is. With a checkpoint, the
+// input it shows done is skipped and the header is left out, as the output
+// of the earlier run is being continued; open that with `resume_output`.
+pub fn solve_stream<R, W>(
+    mut reader: R,
+    writer: &mut W,
+    options: &StreamOptions,
+) -> io::Result<BatchSummary>
+where
+    R: BufRead,
+    W: Write,
+{
+    let mut done = match &options.checkpoint {
+        Some(path) => read_checkpoint(path)?,
+        None => Checkpoint::default(),
+    };
+    let mut summary = BatchSummary {
+        skipped: done.entries,
+        ..BatchSummary::default()
+    };
+
+    let skipped = io::copy(&mut reader.by_ref().take(done.input), &mut io::sink())?;
+    if skipped < done.input {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "checkpoint is past the end of the input",
+        ));
+    }
+    if done.entries == 0 {
+        let header = format!("{HEADER}\n");
+        writer.write_all(header.as_bytes())?;
+        done.output += header.len() as u64;
+    }
+
+    let mut line = String::new();
+    let mut row = vec![];
+    loop {
+        line.clear();
+        let read = reader.read_line(&mut line)?;
+        if read == 0 {
+            break;
+        }
+        done.lines += 1;
+        done.input += read as u64;
+        let Some(entry) = parse_entry(done.lines, &line) else {
+            continue;
+        };
+
+        let result = BatchResult {
+            outcome: solve_entry(&options.solver, options.rules.as_ref(), &entry),
+            id: entry.id,
+        };
+        row.clear();
+        write_result(result, &mut row, &mut summary)?;
+        writer.write_all(&row)?;
+        done.entries += 1;
+        done.output += row.len() as u64;
+
+        if (done.entries - summary.skipped).is_multiple_of(options.flush_every.max(1)) {
+            writer.flush()?;
+            if let Some(path) = &options.checkpoint {
+                write_checkpoint(path, &done)?;
+            }
+        }
+    }
+    writer.flush()?;
+
+    if let Some(path) = &options.checkpoint {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+
+    Ok(summary)
+}
+
+// Like `solve_stream` without a checkpoint, but reads `flush_every` entries
+// at a time and solves each chunk across `jobs` threads (or every core when
+// `None`), so memory is bounded by the chunk and results keep input order.
+#[cfg(feature = "parallel")]
+pub fn solve_stream_parallel<R, W>(
+    reader: R,
+    writer: &mut W,
+    options: &StreamOptions,
+    jobs: Option<usize>,
+) -> io::Result<BatchSummary>
+where
+    R: BufRead,
+    W: Write,
+{
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs.unwrap_or(0))
+        .build()
+        .map_err(io::Error::other)?;
+    let mut summary = BatchSummary::default();
+
+    writeln!(writer, "{HEADER}")?;
+    let mut entries = read_entries(reader);
+    loop {
+        let chunk: Vec<BatchEntry> = entries
+            .by_ref()
+            .take(options.flush_every.max(1))
+            .collect::<io::Result<_>>()?;
+        if chunk.is_empty() {
+            break;
+        }
+
+        for result in solve_on(&pool, &options.solver, options.rules.as_ref(), chunk) {
+            write_result(result, writer, &mut summary)?;
+        }
+        writer.flush()?;
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        read_checkpoint, read_entries, resume_output, solve_batch, solve_entries, solve_stream,
+        write_checkpoint, write_results, BatchEntry, Checkpoint, StreamOptions,
+    };
+    use crate::solver::Solver;
+    use crate::state::{SolveError, State};
+    use crate::variant::Variant;
+    use std::collections::HashSet;
+
+    const INPUT: &str = "id,puzzle
+easy,301086504046521070500000001400800002080347900009050038004090200008734090007208103
+
+bad,000040007480960501063570820009610203350097006000005094000000005804706910001040070
+short,123
+";
+
+    #[test]
+    fn can_read_entries() {
+        let entries: Vec<BatchEntry> = read_entries(INPUT.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].id, "easy");
+        assert_eq!(entries[2].puzzle, "123");
+
+        let entries: Vec<BatchEntry> = read_entries("\n1000002000030000\n".as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(entries[0].id, "2");
+    }
+
+    #[test]
+    fn can_solve_batch() {
+        let puzzles = [
+            "301086504046521070500000001400800002080347900009050038004090200008734090007208103",
+            "000040007480960501063570820009610203350097006000005094000000005804706910001040070",
+        ];
+        let results: Vec<Result<State, SolveError>> =
+            solve_batch(puzzles.into_iter().map(State::from)).collect();
+
+        assert_eq!(
+            format!("{}", results[0].as_ref().unwrap()),
+            "371986524846521379592473861463819752285347916719652438634195287128734695957268143"
+        );
+        assert!(results[1].is_err());
+    }
+
+    #[test]
+    fn can_solve_stream_under_rules() {
+        let x = State::from("0".repeat(81).as_str()).with_variant(Variant::X);
+        let options = StreamOptions {
+            rules: Some(x.constraints().clone()),
+            ..StreamOptions::default()
+        };
+        let input = format!("empty,{}\nsmall,{}\n", "0".repeat(81), "0".repeat(16));
+
+        let mut out = vec![];
+        let summary = solve_stream(input.as_bytes(), &mut out, &options).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert_eq!((summary.solved, summary.failed), (1, 1));
+        let solution = lines[1].strip_prefix("empty,solved,").unwrap();
+        let solution: State = solution.trim_end_matches(',').parse().unwrap();
+        let diagonal: HashSet<u8> = (0..9).filter_map(|i| solution.get(i, i)).collect();
+        let anti: HashSet<u8> = (0..9).filter_map(|i| solution.get(i, 8 - i)).collect();
+        assert_eq!((diagonal.len(), anti.len()), (9, 9));
+        assert!(lines[2].starts_with("small,failed,"));
+    }
+
+    #[test]
+    fn can_write_results() {
+        let solver = Solver::new();
+        let entries = read_entries(INPUT.as_bytes()).map(|e| e.unwrap());
+        let mut out = vec![];
+        let summary = write_results(solve_entries(&solver, None, entries), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert_eq!(summary.solved, 1);
+        assert_eq!(summary.failed, 2);
+        assert_eq!(lines[0], "id,status,solution,error");
+        assert!(lines[1].starts_with("easy,solved,3719865"));
+        assert_eq!(
+            lines[2],
+            "bad,failed,,\"cell r9c5 can only hold 4, which is already in column 5\""
+        );
+        assert_eq!(
+            lines[3],
+            "short,failed,,\"puzzle length 3 is not a square number\""
+        );
+    }
+
+    #[test]
+    fn can_resume_stream_from_checkpoint() {
+        let checkpoint =
+            std::env::temp_dir().join(format!("sudoku-solver-checkpoint-{}", std::process::id()));
+        let options = StreamOptions {
+            flush_every: 1,
+            checkpoint: Some(checkpoint.clone()),
+            ..StreamOptions::default()
+        };
+
+        let mut out = vec![];
+        let summary = solve_stream(INPUT.as_bytes(), &mut out, &StreamOptions::default()).unwrap();
+        let mut written = vec![];
+        write_results(
+            solve_entries(
+                &Solver::new(),
+                None,
+                read_entries(INPUT.as_bytes()).map(|e| e.unwrap()),
+            ),
+            &mut written,
+        )
+        .unwrap();
+        assert_eq!(out, written);
+        assert_eq!((summary.solved, summary.failed), (1, 2));
+
+        // a run stopped after the first entry, which had written the next
+        // row and part of the one after before the checkpoint was due
+        let full = String::from_utf8(out).unwrap();
+        let first = full.find("bad,").unwrap();
+        let input = INPUT.find("\nbad,").unwrap() + 1;
+        let stopped = Checkpoint {
+            entries: 1,
+            lines: 2,
+            input: input as u64,
+            output: first as u64,
+        };
+        write_checkpoint(&checkpoint, &stopped).unwrap();
+        assert_eq!(read_checkpoint(&checkpoint).unwrap(), stopped);
+        assert!(!checkpoint.with_extension("tmp").exists());
+        let output =
+            std::env::temp_dir().join(format!("sudoku-solver-output-{}", std::process::id()));
+        std::fs::write(&output, &full[..full.find("short,").unwrap() + 3]).unwrap();
+
+        let mut file = resume_output(&output, &read_checkpoint(&checkpoint).unwrap()).unwrap();
+        let summary = solve_stream(INPUT.as_bytes(), &mut file, &options).unwrap();
+
+        assert_eq!(summary.skipped, 1);
+        assert_eq!((summary.solved, summary.failed), (0, 2));
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), full);
+        assert!(!checkpoint.exists());
+        std::fs::remove_file(&output).unwrap();
+
+        std::fs::write(&checkpoint, "x").unwrap();
+        assert!(solve_stream(INPUT.as_bytes(), &mut vec![], &options).is_err());
+        std::fs::write(&checkpoint, "1 2 1000 0").unwrap();
+        assert!(solve_stream(INPUT.as_bytes(), &mut vec![], &options).is_err());
+        std::fs::remove_file(&checkpoint).unwrap();
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn can_solve_in_parallel() {
+        let solver = Solver::new();
+        let entries: Vec<BatchEntry> = read_entries(INPUT.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        let sequential: Vec<String> = solve_entries(&solver, None, entries.clone())
+            .map(|r| format!("{} {:?}", r.id, r.outcome.map(|s| s.to_string())))
+            .collect();
+        let parallel: Vec<String> = super::solve_entries_parallel(&solver, None, entries, Some(2))
+            .unwrap()
+            .into_iter()
+            .map(|r| format!("{} {:?}", r.id, r.outcome.map(|s| s.to_string())))
+            .collect();
+
+        assert_eq!(sequential, parallel);
+
+        // streamed two entries at a time, with the same output as one thread
+        let options = StreamOptions {
+            flush_every: 2,
+            ..StreamOptions::default()
+        };
+        let mut streamed = vec![];
+        let summary =
+            super::solve_stream_parallel(INPUT.as_bytes(), &mut streamed, &options, Some(2))
+                .unwrap();
+        let mut out = vec![];
+        solve_stream(INPUT.as_bytes(), &mut out, &options).unwrap();
+
+        assert_eq!((summary.solved, summary.failed), (1, 2));
+        assert_eq!(streamed, out);
+    }
+}
@@ -0,0 +1,181 @@
+use crate::solver::{Backend, Solver};
+use crate::state::State;
+use crate::strategies::{self, Technique};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+// Throughput and solve times over a dataset of puzzles, such as the list of
+// 17-clue puzzles, for each of one or more solvers so backends can be
+// compared on the same puzzles. Logical deductions don't depend on the
+// backend, so they are counted once.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BenchStats {
+    pub puzzles: usize,
+    pub runs: usize,
+    pub timings: Vec<Timing>,
+    // how many times each technique was used, solving every puzzle by logic
+    // as far as it goes
+    pub techniques: BTreeMap<Technique, usize>,
+    // puzzles that logic alone could not finish
+    pub searched: usize,
+}
+
+// How one backend did over every run of the dataset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timing {
+    pub backend: Backend,
+    pub solved: usize,
+    pub failed: usize,
+    // every solve time, in ascending order
+    pub times: Vec<Duration>,
+}
+
+impl BenchStats {
+    pub fn collect(solvers: &[Solver], puzzles: &[State], runs: usize) -> Self {
+        let mut stats = BenchStats {
+            puzzles: puzzles.len(),
+            runs,
+            timings: solvers
+                .iter()
+                .map(|s| Timing::collect(s, puzzles, runs))
+                .collect(),
+            ..BenchStats::default()
+        };
+
+        for puzzle in puzzles {
+            let mut state = puzzle.clone();
+            let solved = strategies::solve_logically(&mut state, &mut |step| {
+                *stats.techniques.entry(step.technique).or_insert(0) += 1;
+            });
+            if solved != Ok(true) {
+                stats.searched += 1;
+            }
+        }
+
+        stats
+    }
+}
+
+impl Timing {
+    fn collect(solver: &Solver, puzzles: &[State], runs: usize) -> Self {
+        let mut timing = Timing {
+            backend: solver.backend(),
+            solved: 0,
+            failed: 0,
+            times: Vec::with_capacity(puzzles.len() * runs),
+        };
+
+        for _ in 0..runs {
+            for puzzle in puzzles {
+                let mut state = puzzle.clone();
+                let start = Instant::now();
+                let result = solver.solve(&mut state);
+                timing.times.push(start.elapsed());
+                match result {
+                    Ok(()) => timing.solved += 1,
+                    Err(_) => timing.failed += 1,
+                }
+            }
+        }
+        timing.times.sort_unstable();
+
+        timing
+    }
+
+    pub fn total(&self) -> Duration {
+        self.times.iter().sum()
+    }
+
+    pub fn throughput(&self) -> Option<f64> {
+        let secs = self.total().as_secs_f64();
+
+        (secs > 0.0).then(|| self.times.len() as f64 / secs)
+    }
+
+    // The solve time that `fraction` of the solves took at most.
+    pub fn percentile(&self, fraction: f64) -> Option<Duration> {
+        let last = self.times.len().checked_sub(1)?;
+        let rank = (last as f64 * fraction).round() as usize;
+
+        self.times.get(rank.min(last)).copied()
+    }
+
+    pub fn mean_time(&self) -> Option<Duration> {
+        (!self.times.is_empty()).then(|| self.total() / self.times.len() as u32)
+    }
+}
+
+impl Display for BenchStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "puzzles: {}, runs: {}", self.puzzles, self.runs)?;
+
+        let row =
+            |f: &mut std::fmt::Formatter<'_>, label: &str, cell: &dyn Fn(&Timing) -> String| {
+                write!(f, "{label:<12}")?;
+                for timing in &self.timings {
+                    write!(f, "{:>14}", cell(timing))?;
+                }
+                writeln!(f)
+            };
+        let time = |time: Option<Duration>| time.map_or("-".to_string(), |t| format!("{t:.1?}"));
+
+        row(f, "", &|t| t.backend.to_string())?;
+        row(f, "solved", &|t| t.solved.to_string())?;
+        row(f, "failed", &|t| t.failed.to_string())?;
+        row(f, "puzzles/s", &|t| {
+            t.throughput()
+                .map_or("-".to_string(), |p| format!("{p:.1}"))
+        })?;
+        row(f, "mean", &|t| time(t.mean_time()))?;
+        row(f, "median", &|t| time(t.percentile(0.5)))?;
+        row(f, "p90", &|t| time(t.percentile(0.9)))?;
+        row(f, "p99", &|t| time(t.percentile(0.99)))?;
+        row(f, "max", &|t| time(t.percentile(1.0)))?;
+
+        write!(f, "deductions:")?;
+        for (technique, count) in &self.techniques {
+            write!(f, "\n  {technique}: {count}")?;
+        }
+        write!(f, "\nneeded search: {}", self.searched)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BenchStats;
+    use crate::solver::{Backend, Solver};
+    use crate::state::State;
+    use crate::strategies::Technique;
+
+    #[test]
+    fn can_collect_bench_stats() {
+        let puzzles: Vec<State> = [
+            "301086504046521070500000001400800002080347900009050038004090200008734090007208103",
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+            "1100000000000000",
+        ]
+        .into_iter()
+        .map(State::from)
+        .collect();
+        let solvers = [Solver::new(), Solver::with_backend(Backend::Dlx)];
+
+        let stats = BenchStats::collect(&solvers, &puzzles, 2);
+
+        assert_eq!(stats.timings.len(), 2);
+        for timing in &stats.timings {
+            assert_eq!(timing.solved, 4);
+            assert_eq!(timing.failed, 2);
+            assert_eq!(timing.times.len(), 6);
+            assert!(timing.percentile(0.5) <= timing.percentile(1.0));
+        }
+        assert_eq!(stats.timings[1].backend, Backend::Dlx);
+        assert!(stats.techniques[&Technique::NakedSingle] > 0);
+        assert_eq!(stats.searched, 2);
+
+        let report = stats.to_string();
+        assert!(report.contains("propagation"));
+        assert!(report.contains("dlx"));
+        assert!(report.ends_with("needed search: 2"));
+    }
+}
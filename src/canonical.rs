@@ -0,0 +1,231 @@
+use crate::state::State;
+use alloc::{vec, vec::Vec};
+use core::cmp::Ordering;
+use thiserror::Error;
+
+// Larger grids have far too many arrangements of rows and columns to try.
+const MAX_SIZE: usize = 9;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum CanonicalError {
+    #[error("canonical forms need classic rules, without variants, regions or cages")]
+    NotClassic,
+    #[error("canonical forms are only found for grids up to {MAX_SIZE}×{MAX_SIZE}")]
+    TooLarge,
+}
+
+// The representative of every puzzle equivalent to some puzzle, and how many
+// arrangements of its rows and columns map that puzzle onto itself.
+#[derive(Debug, Clone)]
+pub struct Canonical {
+    pub puzzle: State,
+    pub automorphisms: usize,
+}
+
+impl Canonical {
+    // Whether an arrangement other than the identity maps the puzzle onto
+    // itself, up to relabeling digits.
+    pub fn is_symmetric(&self) -> bool {
+        self.automorphisms > 1
+    }
+}
+
+// Finds the smallest puzzle equivalent to `state`, read row by row with blanks
+// before digits. Puzzles are equivalent if one becomes the other by
+// relabeling digits, reordering bands and stacks, reordering rows within a
+// band or columns within a stack, and transposing grids with square blocks.
+// Digits are relabeled in order of first appearance, so only the arrangements
+// of rows and columns need trying.
+pub fn canonicalize(state: &State) -> Result<Canonical, CanonicalError> {
+    if !state.constraints().is_classic() {
+        return Err(CanonicalError::NotClassic);
+    }
+    let size = state.size();
+    if size > MAX_SIZE {
+        return Err(CanonicalError::TooLarge);
+    }
+
+    let (box_w, box_h) = state.constraints().block_shape();
+    let values = state.values();
+    let mut grids = vec![values.clone()];
+    if box_w == box_h {
+        grids.push(
+            (0..size * size)
+                .map(|i| values[i % size * size + i / size])
+                .collect(),
+        );
+    }
+
+    // box_w bands of box_h rows, and box_h stacks of box_w columns
+    let rows = arrangements(box_w, box_h);
+    let cols = arrangements(box_h, box_w);
+
+    let mut best = vec![u8::MAX; size * size];
+    let mut candidate = vec![0; size * size];
+    let mut labels = vec![0; size + 1];
+    let mut automorphisms = 0;
+    for grid in &grids {
+        for row in &rows {
+            for col in &cols {
+                match arrange(grid, row, col, &best, &mut labels, &mut candidate) {
+                    Ordering::Less => {
+                        best.copy_from_slice(&candidate);
+                        automorphisms = 1;
+                    }
+                    Ordering::Equal => automorphisms += 1,
+                    Ordering::Greater => {}
+                }
+            }
+        }
+    }
+
+    Ok(Canonical {
+        puzzle: State::from_values(&best),
+        automorphisms,
+    })
+}
+
+// Writes `grid` with its rows and columns in the given orders into
+// `candidate`, relabeling digits as they appear, and compares it with `best`.
+// Gives up as soon as it is known to be greater.
+fn arrange(
+    grid: &[u8],
+    rows: &[usize],
+    cols: &[usize],
+    best: &[u8],
+    labels: &mut [u8],
+    candidate: &mut [u8],
+) -> Ordering {
+    let size = rows.len();
+    labels.fill(0);
+
+    let mut next = 1;
+    let mut order = Ordering::Equal;
+    for (idx, cell) in candidate.iter_mut().enumerate() {
+        let value = grid[rows[idx / size] * size + cols[idx % size]] as usize;
+        if value != 0 && labels[value] == 0 {
+            labels[value] = next;
+            next += 1;
+        }
+        *cell = labels[value];
+
+        if order == Ordering::Equal {
+            order = (*cell).cmp(&best[idx]);
+            if order == Ordering::Greater {
+                return order;
+            }
+        }
+    }
+
+    order
+}
+
+// Every order of `groups` groups of `per_group` lines that keeps each group
+// together.
+fn arrangements(groups: usize, per_group: usize) -> Vec<Vec<usize>> {
+    let within = permutations(per_group);
+
+    permutations(groups)
+        .into_iter()
+        .flat_map(|group_order| {
+            group_order.into_iter().fold(vec![vec![]], |orders, group| {
+                orders
+                    .iter()
+                    .flat_map(|order: &Vec<usize>| {
+                        within.iter().map(move |lines| {
+                            let mut order = order.clone();
+                            order.extend(lines.iter().map(|l| group * per_group + l));
+                            order
+                        })
+                    })
+                    .collect()
+            })
+        })
+        .collect()
+}
+
+fn permutations(n: usize) -> Vec<Vec<usize>> {
+    if n == 0 {
+        return vec![vec![]];
+    }
+
+    permutations(n - 1)
+        .into_iter()
+        .flat_map(|perm| {
+            (0..n).map(move |i| {
+                let mut perm = perm.clone();
+                perm.insert(i, n - 1);
+                perm
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{arrangements, canonicalize, CanonicalError};
+    use crate::state::State;
+    use crate::variant::Variant;
+
+    const PUZZLE: &str =
+        "301086504046521070500000001400800002080347900009050038004090200008734090007208103";
+
+    #[test]
+    fn can_arrange_rows() {
+        let rows = arrangements(2, 2);
+
+        assert_eq!(rows.len(), 8);
+        assert!(rows.contains(&vec![3, 2, 0, 1]));
+        assert!(!rows.contains(&vec![0, 2, 1, 3]));
+    }
+
+    #[test]
+    fn can_find_equivalent_puzzles() {
+        let values: Vec<u8> = PUZZLE.bytes().map(|b| b - b'0').collect();
+        // transpose, swap the first two rows and swap digits 1 and 2
+        let transformed: String = (0..81)
+            .map(|i| {
+                let (row, col) = (i / 9, i % 9);
+                let row = [1, 0, 2, 3, 4, 5, 6, 7, 8][row];
+                match values[col * 9 + row] {
+                    1 => '2',
+                    2 => '1',
+                    v => (b'0' + v) as char,
+                }
+            })
+            .collect();
+
+        let canonical = canonicalize(&State::from(PUZZLE)).unwrap();
+        assert_eq!(
+            canonical.puzzle.values(),
+            canonicalize(&State::from(transformed.as_str()))
+                .unwrap()
+                .puzzle
+                .values()
+        );
+        assert!(State::from(PUZZLE).is_equivalent_to(&State::from(transformed.as_str())));
+        assert!(!canonical.is_symmetric());
+
+        let other = PUZZLE.replacen('3', "0", 1);
+        assert!(!State::from(PUZZLE).is_equivalent_to(&State::from(other.as_str())));
+    }
+
+    #[test]
+    fn can_count_automorphisms() {
+        let empty = canonicalize(&State::from("0000000000000000")).unwrap();
+        assert_eq!(empty.automorphisms, 128);
+
+        let puzzle = canonicalize(&State::from("1000000000000000")).unwrap();
+        assert_eq!(format!("{}", puzzle.puzzle), "0000000000000001");
+        assert_eq!(puzzle.automorphisms, 8);
+    }
+
+    #[test]
+    fn can_reject_other_rules() {
+        let x = State::from(PUZZLE).with_variant(Variant::X);
+        assert_eq!(canonicalize(&x).unwrap_err(), CanonicalError::NotClassic);
+
+        let large = State::from(&"0".repeat(256) as &str);
+        assert_eq!(canonicalize(&large).unwrap_err(), CanonicalError::TooLarge);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::state::{SolveError, State, MAX_SIZE};
+use alloc::{format, string::String, vec::Vec};
+
+// Short codes for sharing puzzles in links. A code is the grid size, a bit
+// for each cell saying whether it is given, then each given digit less one in
+// as few bits as the size needs, packed six bits to a character of URL-safe
+// base64. A 9×9 puzzle with 25 givens takes 32 characters instead of 81.
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+impl State {
+    // The puzzle's givens as a short code.
+    pub fn to_code(&self) -> String {
+        let size = self.size();
+        let values = self.values();
+        let mut bits = BitWriter::default();
+
+        bits.push(size as u32, 6);
+        for value in &values {
+            bits.push((*value != 0) as u32, 1);
+        }
+        for value in values.iter().filter(|v| **v != 0) {
+            bits.push(*value as u32 - 1, digit_bits(size));
+        }
+
+        bits.finish()
+    }
+
+    // Reads a puzzle from a code written by `to_code`.
+    pub fn from_code(code: &str) -> Result<Self, SolveError> {
+        let invalid = |reason: &str| SolveError::InvalidInput(format!("invalid code: {reason}"));
+        let mut bits = BitReader::new(code.trim()).ok_or_else(|| invalid("unknown character"))?;
+
+        let size = bits.read(6).ok_or_else(|| invalid("empty"))? as usize;
+        if size == 0 || size > MAX_SIZE {
+            return Err(invalid("grid size out of range"));
+        }
+
+        let mut given = Vec::with_capacity(size * size);
+        for _ in 0..size * size {
+            given.push(bits.read(1).ok_or_else(|| invalid("too short"))? == 1);
+        }
+        let mut values = Vec::with_capacity(size * size);
+        for given in given {
+            let value = match given {
+                true => {
+                    bits.read(digit_bits(size))
+                        .ok_or_else(|| invalid("too short"))?
+                        + 1
+                }
+                false => 0,
+            };
+            if value as usize > size {
+                return Err(invalid("digit too large for the grid"));
+            }
+            values.push(value as u8);
+        }
+        if !bits.is_padding() {
+            return Err(invalid("too long"));
+        }
+
+        Ok(State::from_values(&values))
+    }
+}
+
+// Bits needed for the digits 1 to `size` stored less one.
+fn digit_bits(size: usize) -> u32 {
+    usize::BITS - (size - 1).leading_zeros()
+}
+
+#[derive(Default)]
+struct BitWriter {
+    out: String,
+    acc: u32,
+    len: u32,
+}
+
+impl BitWriter {
+    fn push(&mut self, value: u32, bits: u32) {
+        for i in (0..bits).rev() {
+            self.acc = self.acc << 1 | (value >> i & 1);
+            self.len += 1;
+            if self.len == 6 {
+                self.out.push(ALPHABET[self.acc as usize] as char);
+                self.acc = 0;
+                self.len = 0;
+            }
+        }
+    }
+
+    // Pads the last character with zeros.
+    fn finish(mut self) -> String {
+        if self.len > 0 {
+            self.push(0, 6 - self.len);
+        }
+
+        self.out
+    }
+}
+
+struct BitReader {
+    sextets: Vec<u8>,
+    pos: usize,
+}
+
+impl BitReader {
+    fn new(code: &str) -> Option<Self> {
+        let sextets = code
+            .bytes()
+            .map(|b| ALPHABET.iter().position(|a| *a == b).map(|p| p as u8))
+            .collect::<Option<Vec<u8>>>()?;
+
+        Some(BitReader { sextets, pos: 0 })
+    }
+
+    fn read(&mut self, bits: u32) -> Option<u32> {
+        let mut value = 0;
+        for _ in 0..bits {
+            let sextet = self.sextets.get(self.pos / 6)?;
+            value = value << 1 | (*sextet >> (5 - self.pos % 6) & 1) as u32;
+            self.pos += 1;
+        }
+
+        Some(value)
+    }
+
+    // Whether all that is left is the zero padding of the last character.
+    fn is_padding(&mut self) -> bool {
+        let left = self.sextets.len() * 6 - self.pos;
+        left < 6 && self.read(left as u32) == Some(0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::state::State;
+
+    #[test]
+    fn can_round_trip_codes() {
+        for puzzle in [
+            "301086504046521070500000001400800002080347900009050038004090200008734090007208103",
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+            "1000000000000000",
+            "0000000000000000",
+        ] {
+            let code = State::from(puzzle).to_code();
+            assert_eq!(State::from_code(&code).unwrap().to_string(), puzzle);
+        }
+
+        let code = State::from(
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+        )
+        .to_code();
+        assert_eq!(code.len(), 29);
+        assert!(code
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+    }
+
+    #[test]
+    fn can_reject_invalid_codes() {
+        let code = State::from("1000000000000000").to_code();
+
+        assert!(State::from_code("").is_err());
+        assert!(State::from_code("A").is_err());
+        assert!(State::from_code(&code[..code.len() - 1]).is_err());
+        assert!(State::from_code(&format!("{code}A")).is_err());
+        assert!(State::from_code("E+AA").is_err());
+    }
+}
@@ -0,0 +1,194 @@
+use crate::batch::{self, BatchEntry};
+use crate::canonical;
+use crate::constraints::Constraints;
+use crate::rating::{self, Difficulty};
+use crate::solver::Solver;
+use crate::state::State;
+use crate::strategies::Technique;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+// Aggregate figures over a collection of puzzles, for auditing it. Exact
+// repeats of an earlier puzzle are only counted as duplicates; puzzles that
+// are equivalent to an earlier one up to relabeling digits and rearranging
+// rows and columns are counted as equivalent, and otherwise audited as usual.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CollectionStats {
+    pub puzzles: usize,
+    // entries that could not be read as a puzzle, or that the solver's
+    // backend cannot solve
+    pub invalid: usize,
+    pub duplicates: usize,
+    pub equivalent: usize,
+    pub unsolvable: usize,
+    pub multiple_solutions: usize,
+    // solve times of the puzzles with a unique solution, in ascending order
+    pub times: Vec<Duration>,
+    pub difficulties: BTreeMap<Difficulty, usize>,
+    // how many puzzles each technique was needed for
+    pub techniques: BTreeMap<Technique, usize>,
+}
+
+impl CollectionStats {
+    pub fn collect<I>(solver: &Solver, rules: Option<&Constraints>, entries: I) -> Self
+    where
+        I: IntoIterator<Item = BatchEntry>,
+    {
+        let mut stats = CollectionStats::default();
+        let mut seen = HashSet::new();
+        let mut canonical_seen = HashSet::new();
+
+        for entry in entries {
+            stats.puzzles += 1;
+            let Ok(state) = batch::read_puzzle(&entry.puzzle, rules) else {
+                stats.invalid += 1;
+                continue;
+            };
+            if !seen.insert(state.to_string()) {
+                stats.duplicates += 1;
+                continue;
+            }
+            if let Ok(canonical) = canonical::canonicalize(&state) {
+                if !canonical_seen.insert(canonical.puzzle.to_string()) {
+                    stats.equivalent += 1;
+                }
+            }
+
+            stats.audit(solver, state);
+        }
+        stats.times.sort_unstable();
+
+        stats
+    }
+
+    fn audit(&mut self, solver: &Solver, mut state: State) {
+        match solver.count_solutions(&state, 2) {
+            Err(_) => {
+                self.invalid += 1;
+                return;
+            }
+            Ok(0) => {
+                self.unsolvable += 1;
+                return;
+            }
+            Ok(1) => {}
+            _ => {
+                self.multiple_solutions += 1;
+                return;
+            }
+        }
+
+        if let Ok(rating) = rating::rate(&state) {
+            *self.difficulties.entry(rating.difficulty).or_insert(0) += 1;
+            for technique in rating.techniques.keys() {
+                *self.techniques.entry(*technique).or_insert(0) += 1;
+            }
+        }
+
+        let start = Instant::now();
+        if solver.solve(&mut state).is_ok() {
+            self.times.push(start.elapsed());
+        }
+    }
+
+    // The solve time that `fraction` of the timed puzzles took at most.
+    pub fn percentile(&self, fraction: f64) -> Option<Duration> {
+        let last = self.times.len().checked_sub(1)?;
+        let rank = (last as f64 * fraction).round() as usize;
+
+        self.times.get(rank.min(last)).copied()
+    }
+
+    pub fn mean_time(&self) -> Option<Duration> {
+        let total: Duration = self.times.iter().sum();
+
+        (!self.times.is_empty()).then(|| total / self.times.len() as u32)
+    }
+}
+
+impl Display for CollectionStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "puzzles: {}", self.puzzles)?;
+        writeln!(f, "invalid: {}", self.invalid)?;
+        writeln!(f, "duplicates: {}", self.duplicates)?;
+        writeln!(f, "equivalent to an earlier puzzle: {}", self.equivalent)?;
+        writeln!(f, "unsolvable: {}", self.unsolvable)?;
+        writeln!(f, "multiple solutions: {}", self.multiple_solutions)?;
+
+        write!(f, "solve time:")?;
+        match (self.percentile(0.0), self.mean_time()) {
+            (Some(min), Some(mean)) => writeln!(
+                f,
+                " min {min:?}, median {:?}, p90 {:?}, p99 {:?}, max {:?}, mean {mean:?}",
+                self.percentile(0.5).unwrap_or(min),
+                self.percentile(0.9).unwrap_or(min),
+                self.percentile(0.99).unwrap_or(min),
+                self.percentile(1.0).unwrap_or(min),
+            )?,
+            _ => writeln!(f, " no puzzles solved")?,
+        }
+
+        writeln!(f, "difficulty:")?;
+        for (difficulty, count) in &self.difficulties {
+            writeln!(f, "  {difficulty}: {count}")?;
+        }
+        write!(f, "techniques needed:")?;
+        for (technique, count) in &self.techniques {
+            write!(f, "\n  {technique}: {count}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CollectionStats;
+    use crate::batch::BatchEntry;
+    use crate::rating::Difficulty;
+    use crate::solver::Solver;
+    use crate::strategies::Technique;
+
+    #[test]
+    fn can_collect_stats() {
+        let easy =
+            "301086504046521070500000001400800002080347900009050038004090200008734090007208103";
+        // the same puzzle with its digits relabeled
+        let relabeled = easy
+            .chars()
+            .map(|c| match c {
+                '1' => '2',
+                '2' => '1',
+                c => c,
+            })
+            .collect::<String>();
+        let puzzles = [
+            easy,
+            easy,
+            &relabeled,
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+            "1100000000000000",
+            "1000000000000000",
+            "12345",
+        ];
+        let entries = puzzles.iter().enumerate().map(|(i, p)| BatchEntry {
+            id: i.to_string(),
+            puzzle: p.to_string(),
+        });
+
+        let stats = CollectionStats::collect(&Solver::new(), None, entries);
+
+        assert_eq!(stats.puzzles, 7);
+        assert_eq!(stats.invalid, 1);
+        assert_eq!(stats.duplicates, 1);
+        assert_eq!(stats.equivalent, 1);
+        assert_eq!(stats.unsolvable, 1);
+        assert_eq!(stats.multiple_solutions, 1);
+        assert_eq!(stats.times.len(), 3);
+        assert_eq!(stats.difficulties[&Difficulty::Easy], 2);
+        assert_eq!(stats.difficulties[&Difficulty::Diabolical], 1);
+        assert_eq!(stats.techniques[&Technique::NakedSingle], 2);
+        assert!(stats.percentile(0.5) <= stats.percentile(1.0));
+    }
+}
@@ -1,37 +1,365 @@
-use csv::ReaderBuilder;
+use crate::killer::Cage;
+use crate::shapes::Shape;
+use alloc::{sync::Arc, vec, vec::Vec};
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
 
-#[derive(Debug)]
+#[cfg(feature = "std")]
+static CLASSIC: OnceLock<Constraints> = OnceLock::new();
+
+// Cells that must hold every digit exactly once, such as a row, column or
+// block. The cells of a unit are all peers of each other. `strategies::Unit`
+// is the label naming one in a deduction.
+pub trait UnitSource {
+    fn cells(&self) -> &[usize];
+}
+
+impl UnitSource for Vec<usize> {
+    fn cells(&self) -> &[usize] {
+        self
+    }
+}
+
+// A rule under which some cells may not share a digit, making them peers.
+// Every cell's peers are worked out from the rules in force, so a variant
+// only has to say which cells it links.
+pub trait Peers {
+    // Adds the cells each cell may not share a digit with to `peers`, which
+    // is indexed by cell. Duplicates are removed afterwards.
+    fn add_peers(&self, peers: &mut [Vec<usize>]);
+}
+
+impl<U: UnitSource> Peers for U {
+    fn add_peers(&self, peers: &mut [Vec<usize>]) {
+        link_all(self.cells(), peers);
+    }
+}
+
+// Makes every one of `cells` a peer of the others.
+pub(crate) fn link_all(cells: &[usize], peers: &mut [Vec<usize>]) {
+    for cell in cells {
+        peers[*cell].extend(cells.iter().filter(|c| *c != cell));
+    }
+}
+
+// Cells a fixed `(row, col)` offset apart, in either direction, as in
+// anti-knight or anti-king puzzles.
+#[derive(Debug, Clone)]
+struct Moves {
+    size: usize,
+    offsets: Vec<(isize, isize)>,
+}
+
+impl Peers for Moves {
+    fn add_peers(&self, peers: &mut [Vec<usize>]) {
+        let size = self.size as isize;
+        for idx in 0..size * size {
+            let (row, col) = (idx / size, idx % size);
+            for (dr, dc) in &self.offsets {
+                for (r, c) in [(row + dr, col + dc), (row - dr, col - dc)] {
+                    if (0..size).contains(&r) && (0..size).contains(&c) {
+                        peers[idx as usize].push((r * size + c) as usize);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// The rules of a grid, shared between every state that uses them: cloning
+// only bumps a count, so a batch of puzzles or a parallel search works from
+// one table. Adding a rule copies the table first if anything else holds it.
+#[derive(Debug, Clone)]
 pub struct Constraints {
+    table: Arc<ConstraintTable>,
+}
+
+#[derive(Debug, Clone)]
+struct ConstraintTable {
+    box_w: usize,
+    box_h: usize,
     inds: Vec<Vec<usize>>,
+    rows: Vec<Vec<usize>>,
+    cols: Vec<Vec<usize>>,
+    blocks: Vec<Vec<usize>>,
+    diagonals: Vec<Vec<usize>>,
+    windows: Vec<Vec<usize>>,
+    cages: Vec<Cage>,
+    shapes: Vec<Shape>,
+    moves: Vec<Moves>,
+}
+
+impl Default for Constraints {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Constraints {
+    #[cfg(feature = "std")]
     pub fn new() -> Self {
-        let reader = ReaderBuilder::new()
-            .has_headers(false)
-            .from_reader(include_bytes!("../assets/constraints.csv").as_slice());
-
-        let records: Vec<Vec<usize>> = reader
-            .into_records()
-            .map(|x| {
-                x.expect("should be static csv")
-                    .into_iter()
-                    .map(|y| y.parse::<usize>().expect("should be decodable as u8"))
+        CLASSIC.get_or_init(|| Self::for_grid(3, 3)).clone()
+    }
+
+    // Without `std` there is nowhere to keep a shared copy.
+    #[cfg(not(feature = "std"))]
+    pub fn new() -> Self {
+        Self::for_grid(3, 3)
+    }
+
+    // Picks the most square block shape for a grid of `size` digits, preferring
+    // wide blocks, e.g. 3 wide by 2 tall for a 6×6 grid.
+    pub fn for_size(size: usize) -> Self {
+        if size == 9 {
+            return Self::new();
+        }
+
+        let (box_w, box_h) = block_shape_for(size);
+        Self::for_grid(box_w, box_h)
+    }
+
+    // Builds the constraints for a grid of `box_w * box_h` digits, whose
+    // blocks are `box_w` cells wide and `box_h` cells tall.
+    pub fn for_grid(box_w: usize, box_h: usize) -> Self {
+        let size = box_w * box_h;
+
+        let rows: Vec<Vec<usize>> = (0..size)
+            .map(|r| (0..size).map(|c| r * size + c).collect())
+            .collect();
+        let cols: Vec<Vec<usize>> = (0..size)
+            .map(|c| (0..size).map(|r| r * size + c).collect())
+            .collect();
+        let blocks: Vec<Vec<usize>> = (0..size)
+            .map(|b| {
+                let top = b / box_h * box_h;
+                let left = b % box_h * box_w;
+                (0..size)
+                    .map(|i| (top + i / box_w) * size + left + i % box_w)
                     .collect()
             })
             .collect();
 
-        Constraints { inds: records }
+        let mut table = ConstraintTable {
+            box_w,
+            box_h,
+            inds: vec![],
+            rows,
+            cols,
+            blocks,
+            diagonals: vec![],
+            windows: vec![],
+            cages: vec![],
+            shapes: vec![],
+            moves: vec![],
+        };
+        table.link_peers();
+
+        Constraints {
+            table: Arc::new(table),
+        }
+    }
+
+    // Adds both main diagonals as units, as in X-Sudoku.
+    pub fn with_diagonals(self) -> Self {
+        let size = self.size();
+        self.edit(|table| {
+            table.diagonals = vec![
+                (0..size).map(|i| i * size + i).collect(),
+                (0..size).map(|i| i * size + size - 1 - i).collect(),
+            ];
+        })
+    }
+
+    // Adds the extra windows of Hyper Sudoku: block-sized units offset one
+    // cell down and right from each block, leaving a one-cell gap between
+    // windows, e.g. the four windows at rows and columns 2-4 and 6-8 of a
+    // 9×9 grid.
+    pub fn with_windows(self) -> Self {
+        let size = self.size();
+        let (box_w, box_h) = self.block_shape();
+        let tops: Vec<usize> = (1..size)
+            .step_by(box_h + 1)
+            .filter(|t| t + box_h < size)
+            .collect();
+        let lefts: Vec<usize> = (1..size)
+            .step_by(box_w + 1)
+            .filter(|l| l + box_w < size)
+            .collect();
+
+        let windows = tops
+            .iter()
+            .flat_map(|top| lefts.iter().map(move |left| (*top, *left)))
+            .map(|(top, left)| {
+                (0..size)
+                    .map(|i| (top + i / box_w) * size + left + i % box_w)
+                    .collect()
+            })
+            .collect();
+        self.edit(|table| table.windows = windows)
+    }
+
+    // Replaces the blocks with irregular regions, as in jigsaw sudoku.
+    pub fn with_regions(self, regions: Vec<Vec<usize>>) -> Self {
+        self.edit(|table| table.blocks = regions)
+    }
+
+    // Adds killer cages. Cells in a cage see each other, but a cage need not
+    // hold every digit, so cages are not units.
+    pub fn with_cages(self, cages: Vec<Cage>) -> Self {
+        self.edit(|table| table.cages = cages)
+    }
+
+    // Adds thermometers, arrows and pairs to any already added. Cells along a
+    // thermometer all differ, so see each other; arrow cells may repeat a
+    // digit.
+    pub fn with_shapes(self, shapes: Vec<Shape>) -> Self {
+        self.edit(|table| table.shapes.extend(shapes))
+    }
+
+    // Makes every cell a peer of the cells a `(row, col)` offset away, as in
+    // anti-knight or anti-king puzzles. Offsets are applied in both directions.
+    pub fn with_moves(self, moves: &[(isize, isize)]) -> Self {
+        let size = self.size();
+        self.edit(|table| {
+            table.moves.push(Moves {
+                size,
+                offsets: moves.to_vec(),
+            })
+        })
+    }
+
+    // Changes the rules, copying the table first if it is shared, then
+    // relinks every cell's peers.
+    fn edit(mut self, change: impl FnOnce(&mut ConstraintTable)) -> Self {
+        let table = Arc::make_mut(&mut self.table);
+        change(table);
+        table.link_peers();
+
+        self
+    }
+
+    // Whether `self` and `other` are the same shared table, not just equal
+    // rules.
+    pub fn shares_table(&self, other: &Constraints) -> bool {
+        Arc::ptr_eq(&self.table, &other.table)
+    }
+
+    // Every rule that makes cells peers: the units, cages, shapes and moves.
+    pub fn peer_rules(&self) -> impl Iterator<Item = &dyn Peers> {
+        self.table.peer_rules()
+    }
+
+    // Whether these are the rules of plain sudoku for the block shape, without
+    // variants, regions, cages or shapes.
+    pub(crate) fn is_classic(&self) -> bool {
+        let table = &self.table;
+        table.cages.is_empty()
+            && table.shapes.is_empty()
+            && table.inds == Self::for_grid(table.box_w, table.box_h).table.inds
     }
 
     pub fn get_constrained_inds(&self, ind: usize) -> &[usize] {
-        self.inds[ind].as_slice()
+        self.table.inds[ind].as_slice()
+    }
+
+    pub fn size(&self) -> usize {
+        self.table.rows.len()
+    }
+
+    // Width and height of each block, in cells.
+    pub fn block_shape(&self) -> (usize, usize) {
+        (self.table.box_w, self.table.box_h)
+    }
+
+    pub fn rows(&self) -> &[Vec<usize>] {
+        &self.table.rows
+    }
+
+    pub fn cols(&self) -> &[Vec<usize>] {
+        &self.table.cols
+    }
+
+    pub fn blocks(&self) -> &[Vec<usize>] {
+        &self.table.blocks
+    }
+
+    pub fn diagonals(&self) -> &[Vec<usize>] {
+        &self.table.diagonals
+    }
+
+    pub fn windows(&self) -> &[Vec<usize>] {
+        &self.table.windows
+    }
+
+    pub fn cages(&self) -> &[Cage] {
+        &self.table.cages
+    }
+
+    pub fn shapes(&self) -> &[Shape] {
+        &self.table.shapes
+    }
+
+    pub fn units(&self) -> impl Iterator<Item = &[usize]> {
+        let table = &self.table;
+        table
+            .rows
+            .iter()
+            .chain(table.cols.iter())
+            .chain(table.blocks.iter())
+            .chain(table.diagonals.iter())
+            .chain(table.windows.iter())
+            .map(|u| u.cells())
     }
 }
 
+impl ConstraintTable {
+    fn peer_rules(&self) -> impl Iterator<Item = &dyn Peers> {
+        let units = [
+            &self.rows,
+            &self.cols,
+            &self.blocks,
+            &self.diagonals,
+            &self.windows,
+        ]
+        .into_iter()
+        .flatten()
+        .map(|u| u as &dyn Peers);
+        let cages = self.cages.iter().map(|c| c as &dyn Peers);
+        let shapes = self.shapes.iter().map(|s| s as &dyn Peers);
+        let moves = self.moves.iter().map(|m| m as &dyn Peers);
+
+        units.chain(cages).chain(shapes).chain(moves)
+    }
+
+    // Recomputes every cell's peers from the rules in force.
+    fn link_peers(&mut self) {
+        let mut inds = vec![vec![]; self.rows.len() * self.rows.len()];
+        for rule in self.peer_rules() {
+            rule.add_peers(&mut inds);
+        }
+        for peers in inds.iter_mut() {
+            peers.sort_unstable();
+            peers.dedup();
+        }
+
+        self.inds = inds;
+    }
+}
+
+// The block shape `Constraints::for_size` picks for a grid of `size` digits.
+pub(crate) fn block_shape_for(size: usize) -> (usize, usize) {
+    let box_h = (1..=size)
+        .filter(|h| size.is_multiple_of(*h) && h * h <= size)
+        .max()
+        .expect("1 always divides size");
+
+    (size / box_h, box_h)
+}
+
 #[cfg(test)]
 mod test {
-    use super::Constraints;
+    use super::{Constraints, Peers};
+    use crate::killer::Cage;
 
     #[test]
     fn can_read_constraints() {
@@ -44,4 +372,128 @@ mod test {
         assert_eq!(c.get_constrained_inds(0)[0], 1);
         assert_eq!(c.get_constrained_inds(19)[11], 24);
     }
+
+    #[test]
+    fn matches_reference_table() {
+        let c = Constraints::new();
+        let reference = include_str!("../assets/constraints.csv");
+
+        for (ind, line) in reference.lines().enumerate() {
+            let peers: Vec<usize> = line.split(',').map(|x| x.parse().unwrap()).collect();
+            assert_eq!(c.get_constrained_inds(ind), peers.as_slice());
+        }
+    }
+
+    #[test]
+    fn can_build_units() {
+        let c = Constraints::new();
+
+        assert_eq!(c.units().count(), 27);
+        assert_eq!(c.rows()[1], vec![9, 10, 11, 12, 13, 14, 15, 16, 17]);
+        assert_eq!(c.cols()[2], vec![2, 11, 20, 29, 38, 47, 56, 65, 74]);
+        assert_eq!(c.blocks()[4], vec![30, 31, 32, 39, 40, 41, 48, 49, 50]);
+
+        for (ind, peers) in c.table.inds.iter().enumerate() {
+            for unit in c.units().filter(|u| u.contains(&ind)) {
+                assert!(unit.iter().all(|i| *i == ind || peers.contains(i)));
+            }
+        }
+    }
+
+    #[test]
+    fn can_build_rectangular_blocks() {
+        let c = Constraints::for_grid(3, 2);
+
+        assert_eq!(c.size(), 6);
+        assert_eq!(c.block_shape(), (3, 2));
+        assert_eq!(c.blocks()[0], vec![0, 1, 2, 6, 7, 8]);
+        assert_eq!(c.blocks()[1], vec![3, 4, 5, 9, 10, 11]);
+        assert_eq!(c.blocks()[2], vec![12, 13, 14, 18, 19, 20]);
+        assert_eq!(c.get_constrained_inds(0).len(), 12);
+    }
+
+    #[test]
+    fn can_add_diagonals() {
+        let c = Constraints::new().with_diagonals();
+
+        assert_eq!(c.units().count(), 29);
+        assert_eq!(c.diagonals()[0], vec![0, 10, 20, 30, 40, 50, 60, 70, 80]);
+        assert_eq!(c.diagonals()[1], vec![8, 16, 24, 32, 40, 48, 56, 64, 72]);
+        assert_eq!(c.get_constrained_inds(0).len(), 26);
+        assert_eq!(c.get_constrained_inds(40).len(), 32);
+        assert_eq!(c.get_constrained_inds(1).len(), 20);
+    }
+
+    #[test]
+    fn can_add_windows() {
+        let c = Constraints::new().with_windows();
+
+        assert_eq!(c.units().count(), 31);
+        assert_eq!(c.windows()[0], vec![10, 11, 12, 19, 20, 21, 28, 29, 30]);
+        assert_eq!(c.windows()[3], vec![50, 51, 52, 59, 60, 61, 68, 69, 70]);
+        assert_eq!(
+            Constraints::for_size(4).with_windows().windows(),
+            &[vec![5, 6, 9, 10]]
+        );
+    }
+
+    #[test]
+    fn can_add_moves() {
+        let knight = Constraints::new().with_moves(&[(1, 2), (2, 1), (1, -2), (2, -1)]);
+
+        assert_eq!(knight.get_constrained_inds(0).len(), 20);
+        assert_eq!(knight.get_constrained_inds(40).len(), 20 + 8);
+        assert!(knight.get_constrained_inds(40).contains(&(2 * 9 + 3)));
+        assert!(knight.get_constrained_inds(21).contains(&40));
+
+        let king = Constraints::new().with_moves(&[(1, 1), (1, -1)]);
+        assert_eq!(
+            king.get_constrained_inds(40),
+            Constraints::new().get_constrained_inds(40)
+        );
+        assert!(king.get_constrained_inds(2).contains(&12));
+    }
+
+    #[test]
+    fn can_link_peers_from_rules() {
+        let mut peers = vec![vec![]; 4];
+        vec![0, 2, 3].add_peers(&mut peers);
+        assert_eq!(peers, vec![vec![2, 3], vec![], vec![0, 3], vec![0, 2]]);
+
+        let cage = Cage {
+            sum: 3,
+            cells: vec![0, 40],
+        };
+        let c = Constraints::new()
+            .with_moves(&[(1, 1), (1, -1)])
+            .with_cages(vec![cage]);
+        assert_eq!(c.peer_rules().count(), 27 + 2);
+        assert!(c.get_constrained_inds(0).contains(&40));
+        assert!(c.get_constrained_inds(20).contains(&30));
+        assert!(!Constraints::new().get_constrained_inds(0).contains(&40));
+        assert!(!Constraints::new().get_constrained_inds(20).contains(&30));
+    }
+
+    #[test]
+    fn can_share_tables() {
+        let classic = Constraints::new();
+        assert!(classic.shares_table(&Constraints::new()));
+        assert!(classic.shares_table(&Constraints::for_size(9)));
+
+        let x = classic.clone().with_diagonals();
+        assert!(!x.shares_table(&classic));
+        assert!(classic.diagonals().is_empty());
+        assert!(x.clone().shares_table(&x));
+    }
+
+    #[test]
+    fn can_pick_block_shape() {
+        assert_eq!(Constraints::for_size(4).blocks()[1], vec![2, 3, 6, 7]);
+        assert_eq!(
+            Constraints::for_size(6).blocks()[1],
+            vec![3, 4, 5, 9, 10, 11]
+        );
+        assert_eq!(Constraints::for_size(16).blocks()[0].len(), 16);
+        assert_eq!(Constraints::for_size(25).get_constrained_inds(0).len(), 64);
+    }
 }
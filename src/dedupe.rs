@@ -0,0 +1,126 @@
+use crate::canonical;
+use crate::state::State;
+use std::collections::HashMap;
+
+// Why a puzzle in a collection is redundant, naming the index of the puzzle
+// it repeats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Duplicate {
+    // It becomes the earlier puzzle by relabeling digits and rearranging rows
+    // and columns, or is the same puzzle outright.
+    Equivalent(usize),
+    // It has every given of the other puzzle, and more.
+    Superset(usize),
+}
+
+// Marks each puzzle that repeats another, keeping the first of every set of
+// equivalent puzzles and, of two puzzles where one only adds givens to the
+// other, the one with fewer. Puzzles without a canonical form, such as
+// variants, are only equivalent to exact copies.
+pub fn find_duplicates(puzzles: &[State]) -> Vec<Option<Duplicate>> {
+    let mut duplicates = vec![None; puzzles.len()];
+
+    let mut first = HashMap::new();
+    for (i, puzzle) in puzzles.iter().enumerate() {
+        let key = match canonical::canonicalize(puzzle) {
+            Ok(canonical) => (true, canonical.puzzle.to_string()),
+            Err(_) => (false, format!("{}:{puzzle}", puzzle.size())),
+        };
+        if let Some(of) = first.get(&key) {
+            duplicates[i] = Some(Duplicate::Equivalent(*of));
+        } else {
+            first.insert(key, i);
+        }
+    }
+
+    // kept puzzles holding each (size, cell, digit) given
+    let givens = |puzzle: &State| {
+        let size = puzzle.size();
+        puzzle
+            .values()
+            .into_iter()
+            .enumerate()
+            .filter(|(_, v)| *v != 0)
+            .map(move |(idx, v)| (size, idx, v))
+            .collect::<Vec<_>>()
+    };
+    let mut holding: HashMap<(usize, usize, u8), Vec<usize>> = HashMap::new();
+    for (i, puzzle) in puzzles.iter().enumerate() {
+        if duplicates[i].is_none() {
+            for given in givens(puzzle) {
+                holding.entry(given).or_default().push(i);
+            }
+        }
+    }
+
+    for (i, puzzle) in puzzles.iter().enumerate() {
+        if duplicates[i].is_some() {
+            continue;
+        }
+
+        let mine = givens(puzzle);
+        let Some(rarest) = mine.iter().min_by_key(|g| holding[g].len()) else {
+            continue;
+        };
+        for other in &holding[rarest] {
+            let theirs = &puzzles[*other];
+            if *other == i || duplicates[*other].is_some() || theirs.filled_count() <= mine.len() {
+                continue;
+            }
+
+            let values = theirs.values();
+            if mine.iter().all(|(_, idx, v)| values[*idx] == *v) {
+                duplicates[*other] = Some(Duplicate::Superset(i));
+            }
+        }
+    }
+
+    duplicates
+}
+
+#[cfg(test)]
+mod test {
+    use super::{find_duplicates, Duplicate};
+    use crate::state::State;
+    use crate::variant::Variant;
+
+    #[test]
+    fn can_find_duplicates() {
+        let puzzles = [
+            "1000000000000000",
+            "0000000000000020",
+            "1200000000000000",
+            "1000000000000000",
+            "1000000000000034",
+            "1200000000000000",
+        ]
+        .map(State::from);
+
+        assert_eq!(
+            find_duplicates(&puzzles),
+            vec![
+                None,
+                Some(Duplicate::Equivalent(0)),
+                Some(Duplicate::Superset(0)),
+                Some(Duplicate::Equivalent(0)),
+                Some(Duplicate::Superset(0)),
+                Some(Duplicate::Equivalent(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn can_find_duplicate_variants() {
+        let x = |puzzle| State::from(puzzle).with_variant(Variant::X);
+        let puzzles = [
+            x("1000000000000000"),
+            x("0000000000000020"),
+            x("1000000000000000"),
+        ];
+
+        assert_eq!(
+            find_duplicates(&puzzles),
+            vec![None, None, Some(Duplicate::Equivalent(0))]
+        );
+    }
+}
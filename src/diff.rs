@@ -0,0 +1,120 @@
+use crate::state::{digit_char, State};
+use alloc::vec::Vec;
+use core::fmt::Display;
+
+// A cell where an attempt at a puzzle differs from its solution. Rows and
+// columns count from zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellDiff {
+    Wrong {
+        row: usize,
+        col: usize,
+        value: u8,
+        expected: u8,
+    },
+    Empty {
+        row: usize,
+        col: usize,
+        expected: u8,
+    },
+}
+
+impl Display for CellDiff {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            CellDiff::Wrong {
+                row,
+                col,
+                value,
+                expected,
+            } => write!(
+                f,
+                "r{}c{} is {} but should be {}",
+                row + 1,
+                col + 1,
+                digit_char(value),
+                digit_char(expected)
+            ),
+            CellDiff::Empty { row, col, expected } => write!(
+                f,
+                "r{}c{} is empty but should be {}",
+                row + 1,
+                col + 1,
+                digit_char(expected)
+            ),
+        }
+    }
+}
+
+impl State {
+    // The cells where this grid, an attempt at a puzzle, holds a different
+    // digit from `solution` or none at all, row by row. Cells left open in
+    // `solution` are skipped.
+    pub fn diff(&self, solution: &State) -> Vec<CellDiff> {
+        assert_eq!(
+            self.size(),
+            solution.size(),
+            "grids of different sizes can't be compared"
+        );
+
+        self.iter_cells()
+            .zip(solution.iter_cells())
+            .filter_map(|((row, col, value), (_, _, expected))| {
+                let expected = expected?;
+                match value {
+                    Some(value) if value == expected => None,
+                    Some(value) => Some(CellDiff::Wrong {
+                        row,
+                        col,
+                        value,
+                        expected,
+                    }),
+                    None => Some(CellDiff::Empty { row, col, expected }),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CellDiff;
+    use crate::state::State;
+
+    #[test]
+    fn can_diff_against_solution() {
+        let solution = State::from("1234341221434321");
+        assert!(solution.diff(&solution).is_empty());
+
+        let attempt = State::from("1234340221434312");
+        let diffs = attempt.diff(&solution);
+        assert_eq!(
+            diffs,
+            vec![
+                CellDiff::Empty {
+                    row: 1,
+                    col: 2,
+                    expected: 1
+                },
+                CellDiff::Wrong {
+                    row: 3,
+                    col: 2,
+                    value: 1,
+                    expected: 2
+                },
+                CellDiff::Wrong {
+                    row: 3,
+                    col: 3,
+                    value: 2,
+                    expected: 1
+                },
+            ]
+        );
+        assert_eq!(diffs[0].to_string(), "r2c3 is empty but should be 1");
+        assert_eq!(diffs[1].to_string(), "r4c3 is 1 but should be 2");
+
+        assert!(attempt
+            .diff(&State::from("0".repeat(16).as_str()))
+            .is_empty());
+    }
+}
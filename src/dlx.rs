@@ -0,0 +1,356 @@
+use crate::search::Interrupt;
+use crate::state::State;
+
+// Algorithm X over a toroidal doubly linked list. Columns are one per cell
+// plus one per (unit, digit) pair, and each candidate (cell, digit) is a row
+// covering its cell column and the digit column of every unit it is in.
+// Peers outside a common unit, such as anti-knight neighbours, share an
+// optional column per digit that may be covered at most once.
+// Each killer cage has a column per digit and one more for the set of digits
+// it holds, picked by a row per set adding up to its sum. A set's row covers
+// the digits left out of it, so the cage's cells must cover exactly the rest.
+// Shapes other than sandwiches work the same way, with a column per (cell,
+// digit) along the shape covered either by that candidate or by the row of a
+// completion of the shape putting another digit there.
+struct Links {
+    left: Vec<usize>,
+    right: Vec<usize>,
+    up: Vec<usize>,
+    down: Vec<usize>,
+    column: Vec<usize>,
+    // the (cell, digit) of each row, or `None` for rows picking a cage's digits
+    candidate: Vec<Option<(usize, u8)>>,
+    sizes: Vec<usize>,
+}
+
+const ROOT: usize = 0;
+
+impl Links {
+    fn new(columns: usize, optional: usize) -> Self {
+        let nodes = columns + 1;
+        let mut links = Links {
+            left: (0..nodes).map(|i| (i + nodes - 1) % nodes).collect(),
+            right: (0..nodes).map(|i| (i + 1) % nodes).collect(),
+            up: (0..nodes).collect(),
+            down: (0..nodes).collect(),
+            column: (0..nodes).collect(),
+            candidate: vec![None; nodes],
+            sizes: vec![0; nodes],
+        };
+        links.sizes[ROOT] = usize::MAX;
+
+        // optional columns are left out of the header ring so they are never
+        // chosen, only covered
+        for col in nodes..nodes + optional {
+            links.left.push(col);
+            links.right.push(col);
+            links.up.push(col);
+            links.down.push(col);
+            links.column.push(col);
+            links.candidate.push(None);
+            links.sizes.push(0);
+        }
+
+        links
+    }
+
+    fn add_row(&mut self, candidate: Option<(usize, u8)>, columns: &[usize]) {
+        let first = self.left.len();
+
+        for (i, col) in columns.iter().map(|c| c + 1).enumerate() {
+            let node = first + i;
+            self.left.push(if i == 0 { node } else { node - 1 });
+            self.right.push(first);
+            if i > 0 {
+                self.right[node - 1] = node;
+                self.left[first] = node;
+            }
+
+            self.up.push(self.up[col]);
+            self.down.push(col);
+            let above = self.up[col];
+            self.down[above] = node;
+            self.up[col] = node;
+
+            self.column.push(col);
+            self.candidate.push(candidate);
+            self.sizes[col] += 1;
+        }
+    }
+
+    fn cover(&mut self, col: usize) {
+        let (l, r) = (self.left[col], self.right[col]);
+        self.right[l] = r;
+        self.left[r] = l;
+
+        let mut row = self.down[col];
+        while row != col {
+            let mut node = self.right[row];
+            while node != row {
+                let (u, d) = (self.up[node], self.down[node]);
+                self.down[u] = d;
+                self.up[d] = u;
+                self.sizes[self.column[node]] -= 1;
+                node = self.right[node];
+            }
+            row = self.down[row];
+        }
+    }
+
+    fn uncover(&mut self, col: usize) {
+        let mut row = self.up[col];
+        while row != col {
+            let mut node = self.left[row];
+            while node != row {
+                let (u, d) = (self.up[node], self.down[node]);
+                self.down[u] = node;
+                self.up[d] = node;
+                self.sizes[self.column[node]] += 1;
+                node = self.left[node];
+            }
+            row = self.up[row];
+        }
+
+        let (l, r) = (self.left[col], self.right[col]);
+        self.right[l] = col;
+        self.left[r] = col;
+    }
+
+    fn smallest_column(&self) -> Option<usize> {
+        let mut best = None;
+        let mut col = self.right[ROOT];
+        while col != ROOT {
+            if best.is_none_or(|b| self.sizes[col] < self.sizes[b]) {
+                best = Some(col);
+            }
+            col = self.right[col];
+        }
+
+        best
+    }
+
+    fn search<F>(
+        &mut self,
+        chosen: &mut Vec<usize>,
+        interrupt: &Interrupt,
+        on_cover: &mut F,
+    ) -> bool
+    where
+        F: FnMut(&[(usize, u8)]) -> bool,
+    {
+        if interrupt.check().is_err() {
+            return false;
+        }
+
+        let Some(col) = self.smallest_column() else {
+            let candidates: Vec<(usize, u8)> =
+                chosen.iter().filter_map(|n| self.candidate[*n]).collect();
+            return on_cover(&candidates);
+        };
+
+        self.cover(col);
+        let mut row = self.down[col];
+        let mut keep_going = true;
+        while row != col && keep_going {
+            chosen.push(row);
+            let mut node = self.right[row];
+            while node != row {
+                self.cover(self.column[node]);
+                node = self.right[node];
+            }
+
+            keep_going = self.search(chosen, interrupt, on_cover);
+
+            let mut node = self.left[row];
+            while node != row {
+                self.uncover(self.column[node]);
+                node = self.left[node];
+            }
+            chosen.pop();
+            row = self.down[row];
+        }
+        self.uncover(col);
+
+        keep_going
+    }
+}
+
+// Enumerates the solutions of `state` consistent with its current candidates
+// and rules.
+// `on_solution` returns whether the search should continue, and the return
+// value reports the same. An interrupted search stops as if it had returned
+// false.
+pub(crate) fn search<F>(state: &State, interrupt: &Interrupt, on_solution: &mut F) -> bool
+where
+    F: FnMut(&State) -> bool,
+{
+    let size = state.size();
+    let cells = size * size;
+    let units: Vec<&[usize]> = state.constraints().units().collect();
+
+    let mut units_of = vec![vec![]; cells];
+    for (u, unit) in units.iter().enumerate() {
+        for idx in unit.iter() {
+            units_of[*idx].push(u);
+        }
+    }
+
+    let cages = state.constraints().cages();
+    let mut cages_of = vec![vec![]; cells];
+    for (c, cage) in cages.iter().enumerate() {
+        for idx in &cage.cells {
+            cages_of[*idx].push(c);
+        }
+    }
+    let cage_columns = |c: usize| cells + units.len() * size + c * (size + 1);
+
+    // each shape's columns start where the previous one's end
+    let mut shape_columns = vec![];
+    let mut shapes_of = vec![vec![]; cells];
+    let mut next = cells + units.len() * size + cages.len() * (size + 1);
+    for shape in state.constraints().shapes() {
+        let Some(completions) = shape.completions(size) else {
+            continue;
+        };
+        let shape_cells = shape.cells();
+        for (position, idx) in shape_cells.iter().enumerate() {
+            shapes_of[*idx].push(next + position * size);
+        }
+        shape_columns.push((next, shape_cells.len(), completions));
+        next += shape_cells.len() * size + 1;
+    }
+
+    let primary = next;
+    let mut pairs_of = vec![vec![]; cells];
+    let mut pairs = 0;
+    for (idx, unit_inds) in units_of.iter().enumerate() {
+        for peer in state.constraints().get_constrained_inds(idx) {
+            if *peer > idx && !unit_inds.iter().any(|u| units_of[*peer].contains(u)) {
+                pairs_of[idx].push(pairs);
+                pairs_of[*peer].push(pairs);
+                pairs += 1;
+            }
+        }
+    }
+
+    let mut links = Links::new(primary, pairs * size);
+    for (idx, unit_inds) in units_of.iter().enumerate() {
+        for val in state.candidates_at(idx) {
+            let columns: Vec<usize> = std::iter::once(idx)
+                .chain(
+                    unit_inds
+                        .iter()
+                        .map(|u| cells + u * size + val as usize - 1),
+                )
+                .chain(
+                    cages_of[idx]
+                        .iter()
+                        .map(|c| cage_columns(*c) + val as usize - 1),
+                )
+                .chain(shapes_of[idx].iter().map(|s| s + val as usize - 1))
+                .chain(
+                    pairs_of[idx]
+                        .iter()
+                        .map(|p| primary + p * size + val as usize - 1),
+                )
+                .collect();
+            links.add_row(Some((idx, val)), &columns);
+        }
+    }
+    for (c, cage) in cages.iter().enumerate() {
+        for set in cage.digit_sets(size) {
+            let columns: Vec<usize> = std::iter::once(cage_columns(c) + size)
+                .chain(
+                    (1..=size)
+                        .filter(|d| set & 1 << d == 0)
+                        .map(|d| cage_columns(c) + d - 1),
+                )
+                .collect();
+            links.add_row(None, &columns);
+        }
+    }
+    for (first, length, completions) in &shape_columns {
+        for completion in completions {
+            let columns: Vec<usize> = std::iter::once(first + length * size)
+                .chain(completion.iter().enumerate().flat_map(|(position, digit)| {
+                    (1..=size)
+                        .filter(move |d| *d != *digit as usize)
+                        .map(move |d| first + position * size + d - 1)
+                }))
+                .collect();
+            links.add_row(None, &columns);
+        }
+    }
+
+    links.search(&mut vec![], interrupt, &mut |candidates| {
+        let mut solution = state.clone();
+        for (idx, val) in candidates {
+            solution.collapse(*idx, *val);
+        }
+
+        // sandwiches are not part of the exact cover, so check every rule here
+        !solution.satisfies_rules() || on_solution(&solution)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::search;
+    use crate::search::Interrupt;
+    use crate::state::State;
+
+    #[test]
+    fn can_solve_with_dlx() {
+        let state = State::from(
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+        );
+        let mut solutions = vec![];
+        search(&state, &Interrupt::default(), &mut |s| {
+            solutions.push(format!("{s}"));
+            true
+        });
+
+        assert_eq!(
+            solutions,
+            vec![
+                "812753649943682175675491283154237896369845721287169534521974368438526917796318452"
+            ]
+        );
+    }
+
+    #[test]
+    fn can_stop_early() {
+        let state = State::from(&"0".repeat(81) as &str);
+        let mut count = 0;
+        let finished = search(&state, &Interrupt::default(), &mut |_| {
+            count += 1;
+            count < 3
+        });
+
+        assert!(!finished);
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn can_detect_no_solution() {
+        let state = State::from(
+            "110000000000000000000000000000000000000000000000000000000000000000000000000000000",
+        );
+
+        assert!(search(&state, &Interrupt::default(), &mut |_| panic!(
+            "should have no solution"
+        )));
+    }
+
+    #[test]
+    fn can_solve_other_sizes() {
+        let state = State::from(&"0".repeat(256) as &str);
+        let mut solution = None;
+        search(&state, &Interrupt::default(), &mut |s| {
+            solution = Some(s.clone());
+            false
+        });
+
+        assert!(solution.unwrap().count_solutions(2) == 1);
+    }
+}
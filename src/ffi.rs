@@ -0,0 +1,179 @@
+use crate::generator::{self, Symmetry};
+use crate::rating::{self, Difficulty};
+use crate::state::{SolveError, State};
+use std::ffi::{c_char, c_int, CStr};
+use std::panic::{self, UnwindSafe};
+
+// C bindings, with a header generated by cbindgen in
+// `include/sudoku_solver.h`. Strings are NUL-terminated, puzzles use any
+// format `State::from_str` accepts, and every function returns a negative
+// `SUDOKU_ERR_*` code instead of panicking across the boundary.
+
+/// Bytes an output buffer needs for the largest supported grid and its NUL.
+pub const SUDOKU_MAX_OUTPUT: usize = 31 * 31 + 1;
+
+/// A pointer was null, or the input was not UTF-8 or not a valid puzzle.
+pub const SUDOKU_ERR_INVALID: c_int = -1;
+/// The puzzle has no solution.
+pub const SUDOKU_ERR_UNSOLVABLE: c_int = -2;
+/// The solver panicked.
+pub const SUDOKU_ERR_INTERNAL: c_int = -3;
+
+fn code(error: SolveError) -> c_int {
+    match error {
+        SolveError::InvalidInput(_) => SUDOKU_ERR_INVALID,
+        _ => SUDOKU_ERR_UNSOLVABLE,
+    }
+}
+
+// Reads a NUL-terminated string, or `None` for null or non-UTF-8 input.
+//
+// # Safety
+// `ptr` must be null or point to a NUL-terminated string.
+unsafe fn read_str<'a>(ptr: *const c_char) -> Option<&'a str> {
+    if ptr.is_null() {
+        return None;
+    }
+
+    unsafe { CStr::from_ptr(ptr) }.to_str().ok()
+}
+
+// Copies `value` and a NUL into `out`.
+//
+// # Safety
+// `out` must be null or point to at least `SUDOKU_MAX_OUTPUT` writable bytes.
+unsafe fn write_str(value: &str, out: *mut c_char) -> c_int {
+    if out.is_null() || value.len() >= SUDOKU_MAX_OUTPUT {
+        return SUDOKU_ERR_INVALID;
+    }
+
+    unsafe {
+        std::ptr::copy_nonoverlapping(value.as_ptr(), out.cast(), value.len());
+        *out.add(value.len()) = 0;
+    }
+
+    0
+}
+
+fn guarded(f: impl FnOnce() -> c_int + UnwindSafe) -> c_int {
+    panic::catch_unwind(f).unwrap_or(SUDOKU_ERR_INTERNAL)
+}
+
+/// Solves `puzzle`, writing the solution as one line of digits to `out`.
+/// Returns 0 on success or a negative `SUDOKU_ERR_*` code.
+///
+/// # Safety
+/// `puzzle` must be a NUL-terminated string and `out` must point to at least
+/// `SUDOKU_MAX_OUTPUT` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn sudoku_solve(puzzle: *const c_char, out: *mut c_char) -> c_int {
+    guarded(|| {
+        let Some(puzzle) = (unsafe { read_str(puzzle) }) else {
+            return SUDOKU_ERR_INVALID;
+        };
+
+        let mut state: State = match puzzle.parse() {
+            Ok(state) => state,
+            Err(e) => return code(e),
+        };
+        match state.solve() {
+            Ok(()) => unsafe { write_str(&state.to_string(), out) },
+            Err(e) => code(e),
+        }
+    })
+}
+
+/// Generates a 9×9 puzzle with a unique solution, writing it as one line of
+/// digits to `out`. `difficulty` is one of "easy", "medium", "hard", "expert"
+/// or "diabolical". Returns 0 on success or a negative `SUDOKU_ERR_*` code.
+///
+/// # Safety
+/// `difficulty` must be a NUL-terminated string and `out` must point to at
+/// least `SUDOKU_MAX_OUTPUT` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn sudoku_generate(difficulty: *const c_char, out: *mut c_char) -> c_int {
+    guarded(|| {
+        let difficulty = match unsafe { read_str(difficulty) }.map(str::parse::<Difficulty>) {
+            Some(Ok(difficulty)) => difficulty,
+            _ => return SUDOKU_ERR_INVALID,
+        };
+
+        let puzzle = generator::generate(difficulty, Symmetry::None);
+        unsafe { write_str(&puzzle.to_string(), out) }
+    })
+}
+
+/// Rates `puzzle`, returning its difficulty from 0 (easy) to 4 (diabolical)
+/// or a negative `SUDOKU_ERR_*` code.
+///
+/// # Safety
+/// `puzzle` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn sudoku_rate(puzzle: *const c_char) -> c_int {
+    guarded(|| {
+        let Some(puzzle) = (unsafe { read_str(puzzle) }) else {
+            return SUDOKU_ERR_INVALID;
+        };
+
+        match puzzle.parse().and_then(|state: State| rating::rate(&state)) {
+            Ok(rating) => rating.difficulty as c_int,
+            Err(e) => code(e),
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::ffi::CString;
+
+    fn solve(puzzle: &str) -> (c_int, String) {
+        let puzzle = CString::new(puzzle).unwrap();
+        let mut out = vec![0 as c_char; SUDOKU_MAX_OUTPUT];
+        let result = unsafe { sudoku_solve(puzzle.as_ptr(), out.as_mut_ptr()) };
+        let solution = unsafe { CStr::from_ptr(out.as_ptr()) };
+
+        (result, solution.to_str().unwrap().to_string())
+    }
+
+    #[test]
+    fn can_solve_over_ffi() {
+        assert_eq!(
+            solve(
+                "301086504046521070500000001400800002080347900009050038004090200008734090007208103"
+            ),
+            (
+                0,
+                "371986524846521379592473861463819752285347916719652438634195287128734695957268143"
+                    .to_string()
+            )
+        );
+        assert_eq!(solve("12").0, SUDOKU_ERR_INVALID);
+        assert_eq!(solve("1100000000000000").0, SUDOKU_ERR_UNSOLVABLE);
+        assert_eq!(
+            unsafe { sudoku_solve(std::ptr::null(), std::ptr::null_mut()) },
+            SUDOKU_ERR_INVALID
+        );
+    }
+
+    #[test]
+    fn can_generate_and_rate_over_ffi() {
+        let difficulty = CString::new("easy").unwrap();
+        let mut out = vec![0 as c_char; SUDOKU_MAX_OUTPUT];
+
+        assert_eq!(
+            unsafe { sudoku_generate(difficulty.as_ptr(), out.as_mut_ptr()) },
+            0
+        );
+        assert_eq!(
+            unsafe { sudoku_rate(out.as_ptr()) },
+            Difficulty::Easy as c_int
+        );
+
+        let unknown = CString::new("trivial").unwrap();
+        assert_eq!(
+            unsafe { sudoku_generate(unknown.as_ptr(), out.as_mut_ptr()) },
+            SUDOKU_ERR_INVALID
+        );
+    }
+}
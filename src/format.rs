@@ -0,0 +1,340 @@
+use crate::state::{digit_char, State};
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{fmt::Display, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Oneline,
+    Grid,
+    Candidates,
+    Marks,
+    Code,
+    #[cfg(feature = "serde")]
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "oneline" => Ok(OutputFormat::Oneline),
+            "grid" => Ok(OutputFormat::Grid),
+            "candidates" => Ok(OutputFormat::Candidates),
+            "marks" => Ok(OutputFormat::Marks),
+            "code" => Ok(OutputFormat::Code),
+            #[cfg(feature = "serde")]
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!("unknown output format: {s}")),
+        }
+    }
+}
+
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let name = match self {
+            OutputFormat::Oneline => "oneline",
+            OutputFormat::Grid => "grid",
+            OutputFormat::Candidates => "candidates",
+            OutputFormat::Marks => "marks",
+            OutputFormat::Code => "code",
+            #[cfg(feature = "serde")]
+            OutputFormat::Json => "json",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+// Whether to color grids: always, never, or only when printing to a
+// terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn enabled(self, terminal: bool) -> bool {
+        match self {
+            ColorChoice::Auto => terminal,
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(format!("unknown color choice: {s}")),
+        }
+    }
+}
+
+impl Display for ColorChoice {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let name = match self {
+            ColorChoice::Auto => "auto",
+            ColorChoice::Always => "always",
+            ColorChoice::Never => "never",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+const BOLD: &str = "\x1b[1m";
+const CYAN: &str = "\x1b[36m";
+const RED: &str = "\x1b[1;31m";
+const RESET: &str = "\x1b[0m";
+
+impl State {
+    pub fn format(&self, format: OutputFormat) -> String {
+        self.format_with(format, false)
+    }
+
+    // Formats like `format`, coloring the digits of `Grid` output if `color`
+    // is set: givens bold, digits the solver placed cyan and digits clashing
+    // with a peer red.
+    pub fn format_with(&self, format: OutputFormat, color: bool) -> String {
+        match format {
+            OutputFormat::Oneline => self.to_string(),
+            OutputFormat::Grid => {
+                let values = self.values();
+                let windows: Vec<usize> = self.constraints().windows().concat();
+                self.boxed(1, " ", false, |idx, _| {
+                    let c = match values[idx] {
+                        0 => '·',
+                        v => digit_char(v),
+                    };
+                    let c = match windows.contains(&idx) {
+                        true => circled(c).to_string(),
+                        false => c.to_string(),
+                    };
+                    let paint = match values[idx] {
+                        _ if !color => None,
+                        0 => None,
+                        v if self.clashes(idx, v) => Some(RED),
+                        _ if self.is_given_at(idx) => Some(BOLD),
+                        _ => Some(CYAN),
+                    };
+                    match paint {
+                        Some(paint) => format!("{paint}{c}{RESET}"),
+                        None => c,
+                    }
+                })
+            }
+            OutputFormat::Candidates => {
+                let (box_w, box_h) = self.constraints().block_shape();
+                self.boxed(box_h, "   ", true, |idx, line| {
+                    let candidates = self.candidates_at(idx);
+                    let digits: Vec<String> = (1..=box_w)
+                        .map(|i| (line * box_w + i) as u8)
+                        .map(|v| match candidates.contains(&v) {
+                            true => digit_char(v).to_string(),
+                            false => "·".to_string(),
+                        })
+                        .collect();
+                    digits.join(" ")
+                })
+            }
+            OutputFormat::Marks => self.to_marks(),
+            OutputFormat::Code => self.to_code(),
+            #[cfg(feature = "serde")]
+            OutputFormat::Json => serde_json::to_string(self).expect("state serializes to json"),
+        }
+    }
+
+    // Lays cells out in blocks separated by box-drawing rules. Each cell is
+    // `lines` lines tall, drawn by `render(idx, line)`, and cells within a
+    // block are separated by `gap` horizontally and, if `spacer` is set, by
+    // a blank line vertically.
+    fn boxed<F>(&self, lines: usize, gap: &str, spacer: bool, render: F) -> String
+    where
+        F: Fn(usize, usize) -> String,
+    {
+        let size = self.size();
+        let (box_w, box_h) = self.constraints().block_shape();
+        let segment_width =
+            visible_width(&render(0, 0)) * box_w + gap.chars().count() * (box_w - 1) + 2;
+        let rule = |left: &str, mid: &str, right: &str| {
+            let segments = vec!["─".repeat(segment_width); size / box_w];
+            format!("{left}{}{right}\n", segments.join(mid))
+        };
+        let blank = {
+            let segments = vec![" ".repeat(segment_width); size / box_w];
+            format!("│{}│\n", segments.join("│"))
+        };
+
+        let mut out = rule("┌", "┬", "┐");
+        for row in 0..size {
+            if row > 0 && row % box_h == 0 {
+                out.push_str(&rule("├", "┼", "┤"));
+            } else if row > 0 && spacer {
+                out.push_str(&blank);
+            }
+
+            for line in 0..lines {
+                let blocks: Vec<String> = (0..size / box_w)
+                    .map(|b| {
+                        let cells: Vec<String> = (0..box_w)
+                            .map(|c| render(row * size + b * box_w + c, line))
+                            .collect();
+                        format!(" {} ", cells.join(gap))
+                    })
+                    .collect();
+                out.push_str(&format!("│{}│\n", blocks.join("│")));
+            }
+        }
+        out.push_str(&rule("└", "┴", "┘"));
+
+        out
+    }
+
+    // Whether `value` in cell `idx` is also in one of the cell's peers.
+    fn clashes(&self, idx: usize, value: u8) -> bool {
+        self.constraints()
+            .get_constrained_inds(idx)
+            .iter()
+            .any(|p| self.candidates_at(*p) == [value])
+    }
+}
+
+// Characters a terminal shows, skipping ANSI color codes.
+fn visible_width(s: &str) -> usize {
+    let mut width = 0;
+    let mut escaped = false;
+    for c in s.chars() {
+        match c {
+            '\x1b' => escaped = true,
+            'm' if escaped => escaped = false,
+            _ if escaped => {}
+            _ => width += 1,
+        }
+    }
+
+    width
+}
+
+// Marks a cell inside a Hyper Sudoku window, as ① to ⑳ or Ⓐ to Ⓩ.
+fn circled(c: char) -> char {
+    let circled = match c {
+        '1'..='9' => 0x2460 + c as u32 - '1' as u32,
+        'A'..='K' => 0x2469 + c as u32 - 'A' as u32,
+        'L'..='Z' => 0x24C1 + c as u32 - 'L' as u32,
+        _ => return '○',
+    };
+
+    char::from_u32(circled).expect("circled characters are valid")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ColorChoice, OutputFormat};
+    use crate::state::State;
+    use crate::variant::Variant;
+
+    #[test]
+    fn can_format_oneline() {
+        let puzzle =
+            "301086504046521070500000001400800002080347900009050038004090200008734090007208103";
+        let state = State::from(puzzle);
+
+        assert_eq!(state.format(OutputFormat::Oneline), puzzle);
+    }
+
+    #[test]
+    fn can_format_grid() {
+        let state = State::from("1000002000030000");
+
+        assert_eq!(
+            state.format(OutputFormat::Grid),
+            "┌─────┬─────┐
+│ 1 · │ · · │
+│ · · │ 2 · │
+├─────┼─────┤
+│ · · │ · 3 │
+│ · · │ · · │
+└─────┴─────┘
+"
+        );
+    }
+
+    #[test]
+    fn can_format_candidates() {
+        let mut state = State::from("1000002000030000");
+        state.propagate_constraints().unwrap();
+        let out = state.format(OutputFormat::Candidates);
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert_eq!(lines[0], "┌───────────┬───────────┐");
+        assert_eq!(lines[1], "│ 1 ·   · 2 │ · ·   · · │");
+        assert_eq!(lines[2], "│ · ·   · · │ 3 ·   · 4 │");
+        assert_eq!(lines[3], "│           │           │");
+        assert_eq!(lines[5], "│ 3 4   3 4 │ · ·   · · │");
+        assert_eq!(lines.len(), 13);
+
+        let state = State::from(&"0".repeat(81) as &str);
+        let out = state.format(OutputFormat::Candidates);
+        assert_eq!(out.lines().count(), 1 + 9 * 3 + 6 + 2 + 1);
+        assert!(out.lines().nth(1).unwrap().starts_with("│ 1 2 3   1 2 3"));
+    }
+
+    #[test]
+    fn can_mark_windows() {
+        let state = State::from("1000002000030000").with_variant(Variant::Hyper);
+
+        assert_eq!(
+            state.format(OutputFormat::Grid),
+            "┌─────┬─────┐
+│ 1 · │ · · │
+│ · ○ │ ② · │
+├─────┼─────┤
+│ · ○ │ ○ 3 │
+│ · · │ · · │
+└─────┴─────┘
+"
+        );
+    }
+
+    #[test]
+    fn can_color_grid() {
+        let mut state = State::from("1000002000030000");
+        state.collapse(1, 3);
+        state.collapse(15, 3);
+        let out = state.format_with(OutputFormat::Grid, true);
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert_eq!(lines[0], "┌─────┬─────┐");
+        assert_eq!(lines[1], "│ \x1b[1m1\x1b[0m \x1b[36m3\x1b[0m │ · · │");
+        assert_eq!(lines[3], "├─────┼─────┤");
+        assert_eq!(lines[4], "│ · · │ · \x1b[1;31m3\x1b[0m │");
+        assert_eq!(lines[5], "│ · · │ · \x1b[1;31m3\x1b[0m │");
+        assert_eq!(
+            state.format_with(OutputFormat::Oneline, true),
+            state.format(OutputFormat::Oneline)
+        );
+        assert!(ColorChoice::Auto.enabled(true) && !ColorChoice::Auto.enabled(false));
+        assert_eq!("never".parse(), Ok(ColorChoice::Never));
+    }
+
+    #[test]
+    fn can_parse_format() {
+        assert_eq!("Grid".parse::<OutputFormat>(), Ok(OutputFormat::Grid));
+        assert!("fancy".parse::<OutputFormat>().is_err());
+    }
+}
@@ -0,0 +1,305 @@
+use crate::constraints::Constraints;
+use crate::rating::{self, Difficulty};
+use crate::search::{self, Interrupt};
+use crate::state::State;
+use crate::variant::{self, Variant};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use std::{fmt::Display, str::FromStr};
+use tracing::info;
+
+const MAX_ATTEMPTS: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symmetry {
+    None,
+    Rotational,
+    Mirror,
+    Diagonal,
+}
+
+impl Symmetry {
+    // The cells that must be given or left out together with `idx` in a grid
+    // of `size`.
+    fn orbit(&self, idx: usize, size: usize) -> Vec<usize> {
+        let (row, col) = (idx / size, idx % size);
+        let partner = match self {
+            Symmetry::None => idx,
+            Symmetry::Rotational => size * size - 1 - idx,
+            Symmetry::Mirror => row * size + (size - 1 - col),
+            Symmetry::Diagonal => col * size + row,
+        };
+
+        if partner == idx {
+            vec![idx]
+        } else {
+            vec![idx, partner]
+        }
+    }
+}
+
+impl FromStr for Symmetry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(Symmetry::None),
+            "rotational" => Ok(Symmetry::Rotational),
+            "mirror" => Ok(Symmetry::Mirror),
+            "diagonal" => Ok(Symmetry::Diagonal),
+            _ => Err(format!("unknown symmetry: {s}")),
+        }
+    }
+}
+
+impl Display for Symmetry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Symmetry::None => "none",
+            Symmetry::Rotational => "rotational",
+            Symmetry::Mirror => "mirror",
+            Symmetry::Diagonal => "diagonal",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+pub fn generate(difficulty: Difficulty, symmetry: Symmetry) -> State {
+    generate_variant(difficulty, symmetry, &[])
+}
+
+// Generates a puzzle whose unique solution also follows `variants`.
+pub fn generate_variant(difficulty: Difficulty, symmetry: Symmetry, variants: &[Variant]) -> State {
+    let constraints = variant::apply_all(variants, Constraints::new());
+
+    generate_with_rng(difficulty, symmetry, &constraints, &mut rand::thread_rng())
+}
+
+// Like `generate_variant`, but the same seed always generates the same
+// puzzle.
+pub fn generate_seeded(
+    difficulty: Difficulty,
+    symmetry: Symmetry,
+    variants: &[Variant],
+    seed: u64,
+) -> State {
+    let constraints = variant::apply_all(variants, Constraints::new());
+
+    generate_with_rng(
+        difficulty,
+        symmetry,
+        &constraints,
+        &mut StdRng::seed_from_u64(seed),
+    )
+}
+
+fn generate_with_rng<R: Rng>(
+    difficulty: Difficulty,
+    symmetry: Symmetry,
+    constraints: &Constraints,
+    rng: &mut R,
+) -> State {
+    let mut best: Option<(State, Difficulty)> = None;
+
+    for attempt in 0..MAX_ATTEMPTS {
+        let (puzzle, rated) = carve(difficulty, symmetry, constraints, rng);
+        info!("generation attempt {attempt} produced a {rated} puzzle");
+
+        if rated == difficulty {
+            return puzzle;
+        }
+        if best.as_ref().is_none_or(|(_, d)| rated > *d) {
+            best = Some((puzzle, rated));
+        }
+    }
+
+    best.expect("should have made at least one attempt").0
+}
+
+// Removes clues from a random complete grid for as long as the puzzle stays
+// uniquely solvable, then restores clues until it is no harder than
+// `difficulty`.
+fn carve<R: Rng>(
+    difficulty: Difficulty,
+    symmetry: Symmetry,
+    constraints: &Constraints,
+    rng: &mut R,
+) -> (State, Difficulty) {
+    let puzzle = |values: &[u8]| State::from_values(values).with_constraints(constraints.clone());
+    let solution = fill_grid(constraints, rng).values();
+    let mut values = solution.clone();
+
+    let mut inds: Vec<usize> = (0..values.len()).collect();
+    inds.shuffle(rng);
+
+    for idx in &inds {
+        let orbit: Vec<usize> = symmetry
+            .orbit(*idx, constraints.size())
+            .into_iter()
+            .filter(|i| values[*i] != 0)
+            .collect();
+        if orbit.is_empty() {
+            continue;
+        }
+
+        for i in &orbit {
+            values[*i] = 0;
+        }
+        if !puzzle(&values).has_unique_solution() {
+            for i in &orbit {
+                values[*i] = solution[*i];
+            }
+        }
+    }
+
+    inds.shuffle(rng);
+    let mut inds = inds.into_iter();
+    loop {
+        let carved = puzzle(&values);
+        let rated = rating::rate(&carved)
+            .expect("carved puzzle should be consistent")
+            .difficulty;
+        if rated <= difficulty {
+            return (carved, rated);
+        }
+
+        let idx = inds
+            .find(|i| values[*i] == 0)
+            .expect("a full grid should be rated easy");
+        for i in symmetry.orbit(idx, constraints.size()) {
+            values[i] = solution[i];
+        }
+    }
+}
+
+fn fill_grid<R: Rng>(constraints: &Constraints, rng: &mut R) -> State {
+    let mut grid = None;
+    search::search_ordered(
+        State::from_values(&vec![0; constraints.size().pow(2)])
+            .with_constraints(constraints.clone()),
+        &mut |_, _, candidates| candidates.shuffle(rng),
+        &Interrupt::default(),
+        &mut |s| {
+            grid = Some(s.clone());
+            false
+        },
+    );
+
+    grid.expect("an empty grid should always be fillable")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{fill_grid, generate_seeded, generate_with_rng, Symmetry};
+    use crate::constraints::Constraints;
+    use crate::rating::{rate, Difficulty};
+    use crate::variant::Variant;
+    use proptest::prelude::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn can_fill_grid() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let grid = fill_grid(&Constraints::new(), &mut rng);
+
+        assert!(grid.values().iter().all(|v| *v != 0));
+        assert_eq!(grid.count_solutions(2), 1);
+    }
+
+    #[test]
+    fn can_generate_unique_puzzle() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let puzzle = generate_with_rng(
+            Difficulty::Easy,
+            Symmetry::Rotational,
+            &Constraints::new(),
+            &mut rng,
+        );
+        let values = puzzle.values();
+
+        assert!(puzzle.has_unique_solution());
+        assert_eq!(rate(&puzzle).unwrap().difficulty, Difficulty::Easy);
+        for idx in 0..81 {
+            assert_eq!(values[idx] == 0, values[80 - idx] == 0);
+        }
+    }
+
+    #[test]
+    fn can_generate_other_sizes() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let puzzle = generate_with_rng(
+            Difficulty::Easy,
+            Symmetry::Mirror,
+            &Constraints::for_size(4),
+            &mut rng,
+        );
+        let values = puzzle.values();
+
+        assert_eq!(puzzle.size(), 4);
+        assert!(puzzle.has_unique_solution());
+        for idx in 0..16 {
+            let mirrored = idx / 4 * 4 + 3 - idx % 4;
+            assert_eq!(values[idx] == 0, values[mirrored] == 0);
+        }
+    }
+
+    #[test]
+    fn can_generate_target_difficulty() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let puzzle = generate_with_rng(
+            Difficulty::Medium,
+            Symmetry::None,
+            &Constraints::new(),
+            &mut rng,
+        );
+
+        assert!(puzzle.has_unique_solution());
+        assert_eq!(rate(&puzzle).unwrap().difficulty, Difficulty::Medium);
+    }
+
+    #[test]
+    fn can_generate_variant() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let constraints = Variant::AntiKnight.apply(Constraints::new());
+        let puzzle = generate_with_rng(Difficulty::Easy, Symmetry::None, &constraints, &mut rng);
+
+        assert!(puzzle.has_unique_solution());
+
+        let mut solution = puzzle.clone();
+        solution.solve().unwrap();
+        assert!(solution.satisfies_rules());
+    }
+
+    #[test]
+    fn can_generate_seeded() {
+        let generate = |seed| generate_seeded(Difficulty::Easy, Symmetry::None, &[], seed);
+        let puzzle = generate(3);
+
+        assert_eq!(puzzle.values(), generate(3).values());
+        assert_ne!(puzzle.values(), generate(4).values());
+    }
+
+    #[test]
+    fn can_parse_options() {
+        assert_eq!("mirror".parse::<Symmetry>(), Ok(Symmetry::Mirror));
+        assert!("impossible".parse::<Symmetry>().is_err());
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(8))]
+
+        #[test]
+        fn generated_puzzles_are_unique(
+            seed in any::<u64>(),
+            difficulty in prop_oneof![Just(Difficulty::Easy), Just(Difficulty::Medium)],
+            symmetry in prop_oneof![Just(Symmetry::None), Just(Symmetry::Rotational)],
+        ) {
+            let puzzle = generate_seeded(difficulty, symmetry, &[], seed);
+            prop_assert!(puzzle.has_unique_solution());
+
+            let mut solution = puzzle.clone();
+            solution.solve().unwrap();
+            prop_assert_eq!(solution.verify_solution(), Ok(()));
+        }
+    }
+}
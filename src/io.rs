@@ -0,0 +1,238 @@
+use crate::state::{digit_char, SolveError, State};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::{fmt::Display, str::FromStr};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum FileError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("puzzle {index}: {source}")]
+    Parse { index: usize, source: SolveError },
+    #[error("could not detect the puzzle format of {0}, use --format")]
+    UnknownFormat(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    // SadMan Sudoku: one grid row per line, `#` comment lines.
+    Sdk,
+    // Collections: one puzzle per line.
+    Sdm,
+    // Simple Sudoku: one grid row per line with `|` and `-` decorations.
+    Ss,
+}
+
+impl FileFormat {
+    pub fn from_path(path: &Path) -> Result<Self, FileError> {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .and_then(|e| e.parse().ok())
+            .ok_or_else(|| FileError::UnknownFormat(path.display().to_string()))
+    }
+}
+
+impl FromStr for FileFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sdk" => Ok(FileFormat::Sdk),
+            "sdm" => Ok(FileFormat::Sdm),
+            "ss" => Ok(FileFormat::Ss),
+            _ => Err(format!("unknown puzzle format: {s}")),
+        }
+    }
+}
+
+impl Display for FileFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            FileFormat::Sdk => "sdk",
+            FileFormat::Sdm => "sdm",
+            FileFormat::Ss => "ss",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+pub fn read_puzzles<R: Read>(reader: R, format: FileFormat) -> Result<Vec<State>, FileError> {
+    let lines: Vec<String> = BufReader::new(reader)
+        .lines()
+        .collect::<Result<Vec<String>, _>>()?
+        .into_iter()
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty() && !l.starts_with('#') && !l.starts_with('['))
+        .collect();
+
+    let puzzles = match format {
+        FileFormat::Sdm => lines,
+        FileFormat::Sdk | FileFormat::Ss => vec![lines.concat()],
+    };
+
+    puzzles
+        .iter()
+        .enumerate()
+        .map(|(index, p)| {
+            p.parse().map_err(|source| FileError::Parse {
+                index: index + 1,
+                source,
+            })
+        })
+        .collect()
+}
+
+pub fn read_file(path: &Path, format: Option<FileFormat>) -> Result<Vec<State>, FileError> {
+    let format = match format {
+        Some(format) => format,
+        None => FileFormat::from_path(path)?,
+    };
+
+    read_puzzles(std::fs::File::open(path)?, format)
+}
+
+pub fn write_puzzles<W: Write>(
+    writer: &mut W,
+    puzzles: &[State],
+    format: FileFormat,
+) -> Result<(), FileError> {
+    for (i, puzzle) in puzzles.iter().enumerate() {
+        match format {
+            FileFormat::Sdm => writeln!(writer, "{puzzle}")?,
+            FileFormat::Sdk | FileFormat::Ss => {
+                if i > 0 {
+                    writeln!(writer)?;
+                }
+                write!(writer, "{}", rows(puzzle, format == FileFormat::Ss))?;
+            }
+        }
+    }
+
+    Ok(writer.flush()?)
+}
+
+pub fn write_file(
+    path: &Path,
+    puzzles: &[State],
+    format: Option<FileFormat>,
+) -> Result<(), FileError> {
+    let format = match format {
+        Some(format) => format,
+        None => FileFormat::from_path(path)?,
+    };
+
+    write_puzzles(&mut std::fs::File::create(path)?, puzzles, format)
+}
+
+fn rows(puzzle: &State, decorated: bool) -> String {
+    let size = puzzle.size();
+    let (box_w, box_h) = puzzle.constraints().block_shape();
+    let values = puzzle.values();
+    let mut out = String::new();
+
+    for (r, row) in values.chunks(size).enumerate() {
+        if decorated && r > 0 && r % box_h == 0 {
+            out.push_str(&"-".repeat(size + size / box_w - 1));
+            out.push('\n');
+        }
+
+        for (c, v) in row.iter().enumerate() {
+            if decorated && c > 0 && c % box_w == 0 {
+                out.push('|');
+            }
+            out.push(match v {
+                0 => '.',
+                v => digit_char(*v),
+            });
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read_puzzles, write_puzzles, FileError, FileFormat};
+    use crate::state::State;
+    use std::path::Path;
+
+    const PUZZLE: &str =
+        "301086504046521070500000001400800002080347900009050038004090200008734090007208103";
+
+    #[test]
+    fn can_read_sdk() {
+        let sdk = "#A someone
+#D an easy one
+3.1.865.4
+.46521.7.
+5.......1
+4..8....2
+.8.3479..
+..9.5..38
+..4.9.2..
+..8734.9.
+..72.81.3
+";
+        let puzzles = read_puzzles(sdk.as_bytes(), FileFormat::Sdk).unwrap();
+
+        assert_eq!(puzzles.len(), 1);
+        assert_eq!(puzzles[0].to_string(), PUZZLE);
+    }
+
+    #[test]
+    fn can_round_trip_formats() {
+        let puzzles = vec![State::from(PUZZLE), State::from("1000002000030000")];
+
+        for format in [FileFormat::Sdm, FileFormat::Sdk, FileFormat::Ss] {
+            let puzzles = match format {
+                FileFormat::Sdm => puzzles.clone(),
+                _ => puzzles[..1].to_vec(),
+            };
+            let mut out = vec![];
+            write_puzzles(&mut out, &puzzles, format).unwrap();
+            let read = read_puzzles(out.as_slice(), format).unwrap();
+
+            assert_eq!(
+                read.iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+                puzzles.iter().map(|s| s.to_string()).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn can_write_ss() {
+        let mut out = vec![];
+        write_puzzles(&mut out, &[State::from(PUZZLE)], FileFormat::Ss).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert_eq!(lines[0], "3.1|.86|5.4");
+        assert_eq!(lines[3], "-----------");
+        assert_eq!(lines.len(), 11);
+    }
+
+    #[test]
+    fn can_detect_format() {
+        assert_eq!(
+            FileFormat::from_path(Path::new("puzzles/hard.SDM")).unwrap(),
+            FileFormat::Sdm
+        );
+        assert!(matches!(
+            FileFormat::from_path(Path::new("puzzle.txt")),
+            Err(FileError::UnknownFormat(_))
+        ));
+    }
+
+    #[test]
+    fn can_report_bad_puzzle() {
+        let err = read_puzzles("1000002000030000\n12\n".as_bytes(), FileFormat::Sdm).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "puzzle 2: puzzle length 2 is not a square number"
+        );
+    }
+}
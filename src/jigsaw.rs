@@ -0,0 +1,136 @@
+use crate::state::{SolveError, State};
+use std::collections::HashMap;
+
+// Reads a region layout with one character per cell, where cells sharing a
+// character form a region, e.g. `111222333...` for classic blocks. Whitespace
+// is ignored. A grid of `size` needs `size` connected regions of `size` cells.
+pub fn parse_regions(layout: &str, size: usize) -> Result<Vec<Vec<usize>>, SolveError> {
+    let invalid = |message: String| SolveError::InvalidInput(format!("region layout {message}"));
+    let layout: Vec<char> = layout.chars().filter(|c| !c.is_whitespace()).collect();
+    if layout.len() != size * size {
+        return Err(invalid(format!(
+            "has {} cells but a grid of size {size} needs {}",
+            layout.len(),
+            size * size
+        )));
+    }
+
+    let mut ids: HashMap<char, usize> = HashMap::new();
+    let mut regions: Vec<Vec<usize>> = vec![];
+    for (idx, c) in layout.iter().enumerate() {
+        let id = *ids.entry(*c).or_insert_with(|| {
+            regions.push(vec![]);
+            regions.len() - 1
+        });
+        regions[id].push(idx);
+    }
+
+    if regions.len() != size {
+        return Err(invalid(format!(
+            "has {} regions, expected {size}",
+            regions.len()
+        )));
+    }
+    for region in &regions {
+        let name = layout[region[0]];
+        if region.len() != size {
+            return Err(invalid(format!(
+                "region '{name}' has {} cells, expected {size}",
+                region.len()
+            )));
+        }
+        if !is_connected(region, size) {
+            return Err(invalid(format!("region '{name}' is not connected")));
+        }
+    }
+
+    Ok(regions)
+}
+
+// Whether the cells are joined by orthogonal steps.
+fn is_connected(region: &[usize], size: usize) -> bool {
+    let mut seen = vec![region[0]];
+    let mut frontier = vec![region[0]];
+
+    while let Some(idx) = frontier.pop() {
+        for next in region.iter().copied() {
+            let steps = (idx / size).abs_diff(next / size) + (idx % size).abs_diff(next % size);
+            if steps == 1 && !seen.contains(&next) {
+                seen.push(next);
+                frontier.push(next);
+            }
+        }
+    }
+
+    seen.len() == region.len()
+}
+
+impl State {
+    // Replaces the blocks with irregular regions read by `parse_regions`.
+    pub fn with_regions(self, layout: &str) -> Result<Self, SolveError> {
+        let regions = parse_regions(layout, self.size())?;
+        let constraints = self.constraints().clone().with_regions(regions);
+
+        Ok(self.with_constraints(constraints))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_regions;
+    use crate::solver::{Backend, Solver};
+    use crate::state::State;
+    use crate::validate::Solutions;
+
+    const LAYOUT: &str = "54111 54411 54222 54322 53333";
+
+    #[test]
+    fn can_parse_regions() {
+        assert_eq!(parse_regions(LAYOUT, 5).unwrap()[2], vec![2, 3, 4, 8, 9]);
+        assert_eq!(
+            parse_regions("1112 1322 3342 3444", 4).unwrap(),
+            vec![
+                vec![0, 1, 2, 4],
+                vec![3, 6, 7, 11],
+                vec![5, 8, 9, 12],
+                vec![10, 13, 14, 15]
+            ]
+        );
+        assert!(parse_regions("1112 1322 3342 344", 4).is_err());
+        assert!(parse_regions("1111 2222 3333 4445", 4).is_err());
+        assert_eq!(
+            parse_regions("1212 1212 3434 3434", 4)
+                .unwrap_err()
+                .to_string(),
+            "region layout region '1' is not connected"
+        );
+    }
+
+    #[test]
+    fn can_solve_jigsaw() {
+        for backend in [Backend::Propagation, Backend::Dlx] {
+            let mut state = State::from(&"0".repeat(25) as &str)
+                .with_regions(LAYOUT)
+                .unwrap();
+            Solver::with_backend(backend).solve(&mut state).unwrap();
+            let values = state.values();
+
+            for region in state.constraints().blocks() {
+                let mut digits: Vec<u8> = region.iter().map(|i| values[*i]).collect();
+                digits.sort_unstable();
+                assert_eq!(digits, vec![1, 2, 3, 4, 5]);
+            }
+        }
+    }
+
+    #[test]
+    fn can_validate_jigsaw() {
+        // 1s in r1c3 and r2c4 share a region but not a row or column
+        let state = State::from("0010000010000000000000000");
+
+        assert!(state.validate().duplicates.is_empty());
+        let validation = state.with_regions(LAYOUT).unwrap().validate();
+        assert_eq!(validation.duplicates.len(), 1);
+        assert_eq!(validation.solutions, Solutions::None);
+    }
+}
@@ -0,0 +1,311 @@
+use crate::constraints::{self, Peers};
+use crate::state::{SolveError, State};
+use alloc::collections::BTreeMap;
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+// A killer cage: its cells must hold distinct digits adding up to `sum`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cage {
+    pub sum: u32,
+    pub cells: Vec<usize>,
+}
+
+impl Peers for Cage {
+    fn add_peers(&self, peers: &mut [Vec<usize>]) {
+        constraints::link_all(&self.cells, peers);
+    }
+}
+
+impl Cage {
+    // Narrows each cell's candidate mask to the digits that appear in some
+    // distinct combination adding up to the cage's sum. Returns the narrowed
+    // masks in the order of `cells`.
+    pub(crate) fn supported(&self, masks: &[u32]) -> Vec<u32> {
+        let masks: Vec<u32> = self.cells.iter().map(|i| masks[*i]).collect();
+        let mut supported = vec![0; masks.len()];
+        let mut memo = BTreeMap::new();
+
+        feasible(&masks, 0, self.sum, &mut supported, &mut memo);
+
+        supported
+    }
+
+    // Every set of distinct digits up to `size` that fills the cage and adds
+    // up to its sum, as a mask with bit `d` set for digit `d`. The exact cover
+    // and SAT backends pick one of these per cage.
+    #[cfg(feature = "std")]
+    pub(crate) fn digit_sets(&self, size: usize) -> Vec<u32> {
+        let mut sets = vec![];
+        add_digit_sets(1, size as u32, self.cells.len(), self.sum, 0, &mut sets);
+
+        sets
+    }
+}
+
+// Adds every set of `left` more distinct digits from `from` up to `size`
+// adding up to `remaining` to `sets`, as masks on top of `set`.
+#[cfg(feature = "std")]
+fn add_digit_sets(
+    from: u32,
+    size: u32,
+    left: usize,
+    remaining: u32,
+    set: u32,
+    sets: &mut Vec<u32>,
+) {
+    if left == 0 {
+        if remaining == 0 {
+            sets.push(set);
+        }
+        return;
+    }
+
+    for digit in (from..=size).take_while(|d| *d <= remaining) {
+        add_digit_sets(
+            digit + 1,
+            size,
+            left - 1,
+            remaining - digit,
+            set | 1 << digit,
+            sets,
+        );
+    }
+}
+
+// Whether cells from `used.count_ones()` onwards can be filled with unused
+// digits adding up to `remaining`, marking every digit that takes part in a
+// completion. Each set of used digits is explored once.
+fn feasible(
+    masks: &[u32],
+    used: u32,
+    remaining: u32,
+    supported: &mut [u32],
+    memo: &mut BTreeMap<u32, bool>,
+) -> bool {
+    let depth = used.count_ones() as usize;
+    if depth == masks.len() {
+        return remaining == 0;
+    }
+    if let Some(result) = memo.get(&used) {
+        return *result;
+    }
+
+    let mut result = false;
+    for val in 1..u32::BITS {
+        if masks[depth] & 1 << val == 0 || used & 1 << val != 0 || val > remaining {
+            continue;
+        }
+
+        if feasible(masks, used | 1 << val, remaining - val, supported, memo) {
+            supported[depth] |= 1 << val;
+            result = true;
+        }
+    }
+    memo.insert(used, result);
+
+    result
+}
+
+// Reads one cage per line as `sum,cell,cell,...`, naming cells as `r1c1`.
+// Blank lines and lines starting with `#` are skipped.
+pub fn parse_cages(input: &str, size: usize) -> Result<Vec<Cage>, SolveError> {
+    let invalid = |line: usize, message: String| {
+        SolveError::InvalidInput(format!("cage on line {line}: {message}"))
+    };
+    let mut cages: Vec<Cage> = vec![];
+
+    for (n, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut fields = line.split(',').map(|f| f.trim());
+        let sum = fields
+            .next()
+            .and_then(|f| f.parse().ok())
+            .ok_or_else(|| invalid(n + 1, "expected a sum".to_string()))?;
+        let cells = fields
+            .map(|f| parse_cell(f, size).ok_or_else(|| invalid(n + 1, format!("bad cell '{f}'"))))
+            .collect::<Result<Vec<usize>, SolveError>>()?;
+
+        if cells.is_empty() {
+            return Err(invalid(n + 1, "no cells".to_string()));
+        }
+        if let Some(cell) = cells
+            .iter()
+            .enumerate()
+            .find(|(i, c)| cells[..*i].contains(c) || cages.iter().any(|g| g.cells.contains(c)))
+        {
+            return Err(invalid(
+                n + 1,
+                format!("cell index {} is already caged", cell.1),
+            ));
+        }
+
+        cages.push(Cage { sum, cells });
+    }
+
+    Ok(cages)
+}
+
+pub(crate) fn parse_cell(name: &str, size: usize) -> Option<usize> {
+    let name = name.to_lowercase();
+    let (row, col) = name.strip_prefix('r')?.split_once('c')?;
+    let (row, col): (usize, usize) = (row.parse().ok()?, col.parse().ok()?);
+
+    match (1..=size).contains(&row) && (1..=size).contains(&col) {
+        true => Some((row - 1) * size + col - 1),
+        false => None,
+    }
+}
+
+impl State {
+    pub fn with_cages(self, cages: Vec<Cage>) -> Self {
+        let constraints = self.constraints().clone().with_cages(cages);
+        self.with_constraints(constraints)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_cages, Cage};
+    use crate::solver::{Backend, SolveOptions, Solver};
+    use crate::state::State;
+    use std::time::Duration;
+
+    const SOLUTION: &str =
+        "812753649943682175675491283154237896369845721287169534521974368438526917796318452";
+
+    #[test]
+    fn can_prune_cage_candidates() {
+        let cage = Cage {
+            sum: 3,
+            cells: vec![0, 1],
+        };
+        let all = 0b11_1111_1110;
+
+        assert_eq!(cage.supported(&[all, all]), vec![0b110, 0b110]);
+        assert_eq!(cage.supported(&[1 << 1, all]), vec![0b10, 0b100]);
+        assert_eq!(cage.supported(&[1 << 3, all]), vec![0, 0]);
+
+        let cage = Cage {
+            sum: 24,
+            cells: vec![0, 1, 2],
+        };
+        assert_eq!(cage.supported(&[all, all, all]), vec![0b11_1000_0000; 3]);
+    }
+
+    #[test]
+    fn can_parse_cages() {
+        let cages = parse_cages("# sum,cells\n3,r1c1,r1c2\n\n4, r4c4 , R4C3\n", 4).unwrap();
+
+        assert_eq!(
+            cages,
+            vec![
+                Cage {
+                    sum: 3,
+                    cells: vec![0, 1]
+                },
+                Cage {
+                    sum: 4,
+                    cells: vec![15, 14]
+                }
+            ]
+        );
+        assert!(parse_cages("3,r1c1,r5c1", 4).is_err());
+        assert!(parse_cages("3,r1c1\n4,r1c1,r1c2", 4).is_err());
+        assert!(parse_cages("r1c1", 4).is_err());
+    }
+
+    #[test]
+    fn can_solve_killer() {
+        // Every row of a 4×4 grid caged in pairs, with no givens.
+        let cages = parse_cages(
+            "3,r1c1,r1c2
+7,r1c3,r1c4
+7,r2c1,r2c2
+3,r2c3,r2c4
+5,r3c1,r3c2
+5,r3c3,r3c4
+5,r4c1,r4c2
+5,r4c3,r4c4",
+            4,
+        )
+        .unwrap();
+
+        for backend in [Backend::Propagation, Backend::Dlx] {
+            let mut state = State::from(&"0".repeat(16) as &str).with_cages(cages.clone());
+            let solver = Solver::with_backend(backend);
+
+            assert_eq!(solver.count_solutions(&state, 10), Ok(8));
+            solver.solve(&mut state).unwrap();
+            assert!(state.satisfies_rules());
+        }
+    }
+
+    #[test]
+    fn can_solve_killer_on_every_backend() {
+        // every row split into cages of 2, 3, 2 and 2 cells, with no givens
+        let values: Vec<u32> = SOLUTION.chars().filter_map(|c| c.to_digit(10)).collect();
+        let cages: Vec<Cage> = (0..9)
+            .flat_map(|row| {
+                [(0, 2), (2, 5), (5, 7), (7, 9)].map(|(a, b)| (row * 9 + a, row * 9 + b))
+            })
+            .map(|(first, last)| Cage {
+                sum: values[first..last].iter().sum(),
+                cells: (first..last).collect(),
+            })
+            .collect();
+        let solver = |backend| {
+            Solver::with_backend(backend)
+                .with_options(SolveOptions::default().timeout(Duration::from_secs(20)))
+        };
+
+        let backends = [
+            Backend::Propagation,
+            Backend::Dlx,
+            #[cfg(feature = "sat")]
+            Backend::Sat,
+        ];
+        for backend in backends {
+            let mut state = State::from(&"0".repeat(81) as &str).with_cages(cages.clone());
+            solver(backend).solve(&mut state).unwrap();
+            assert!(state.satisfies_rules());
+        }
+
+        // annealing needs a start from some givens
+        let givens: String = SOLUTION
+            .chars()
+            .enumerate()
+            .map(|(i, c)| if i % 3 == 0 { c } else { '0' })
+            .collect();
+        let mut state = State::from(givens.as_str()).with_cages(cages);
+        solver(Backend::Annealing).solve(&mut state).unwrap();
+        assert!(state.satisfies_rules());
+    }
+
+    #[test]
+    fn can_list_cage_digit_sets() {
+        let cage = Cage {
+            sum: 10,
+            cells: vec![0, 1, 2],
+        };
+
+        assert_eq!(
+            cage.digit_sets(9),
+            vec![
+                1 << 1 | 1 << 2 | 1 << 7,
+                1 << 1 | 1 << 3 | 1 << 6,
+                1 << 1 | 1 << 4 | 1 << 5,
+                1 << 2 | 1 << 3 | 1 << 5,
+            ]
+        );
+        assert!(cage.digit_sets(4).is_empty());
+    }
+}
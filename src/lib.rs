@@ -1,23 +1,341 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+#[cfg(feature = "std")]
+use annealing::Annealing;
+use format::OutputFormat;
+#[cfg(feature = "std")]
+use render::RenderOptions;
+#[cfg(feature = "std")]
+use solver::{Backend, Heuristic, SolveOptions, Solver};
 use state::State;
+#[cfg(feature = "std")]
+use std::path::PathBuf;
+#[cfg(feature = "std")]
+use std::time::Duration;
+#[cfg(feature = "std")]
+use strategies::Technique;
+#[cfg(feature = "std")]
+use variant::Variant;
 
+#[cfg(feature = "std")]
+pub mod analysis;
+#[cfg(feature = "std")]
+pub mod annealing;
+#[cfg(feature = "std")]
+pub mod author;
+#[cfg(feature = "std")]
+pub mod batch;
+#[cfg(feature = "std")]
+pub mod bench;
+pub mod canonical;
+pub mod code;
+#[cfg(feature = "std")]
+pub mod collection;
 pub mod constraints;
+#[cfg(feature = "std")]
+pub mod dedupe;
+pub mod diff;
+#[cfg(feature = "std")]
+mod dlx;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod format;
+#[cfg(feature = "std")]
+pub mod generator;
+#[cfg(feature = "std")]
+pub mod io;
+#[cfg(feature = "std")]
+pub mod jigsaw;
+pub mod killer;
+pub mod marks;
+pub mod minimize;
+pub mod parse;
+#[cfg(feature = "std")]
+pub mod pdf;
+#[cfg(feature = "tui")]
+pub mod play;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "std")]
+pub mod rating;
+#[cfg(feature = "std")]
+pub mod render;
+#[cfg(feature = "std")]
+pub mod repair;
+#[cfg(feature = "std")]
+pub mod report;
+#[cfg(feature = "std")]
+pub mod samurai;
+#[cfg(feature = "sat")]
+mod sat;
+#[cfg(feature = "std")]
+mod scratch;
+mod search;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod shapes;
+#[cfg(feature = "std")]
+pub mod solver;
 pub mod state;
+pub mod stats;
+pub mod strategies;
+pub mod trace;
+pub mod transform;
+pub mod validate;
+pub mod variant;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
+// A puzzle and the settings to solve and print it with, made by
+// `ConfigBuilder::build`.
+#[cfg(feature = "std")]
 pub struct Config {
     puzzle: State,
+    solver: Solver,
+    output_format: OutputFormat,
+    color: bool,
+    explain: bool,
+    stats: bool,
+    max_technique: Option<Technique>,
+    render: Option<(PathBuf, RenderOptions)>,
+}
+
+#[cfg(feature = "std")]
+impl Config {
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+}
+
+// Every setting of a `Config` but its puzzle, so that they can be set once
+// and used for many puzzles: `build` makes the config to run on a puzzle,
+// and `solver` a solver with the same settings for everything else.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Default)]
+pub struct ConfigBuilder {
+    backend: Backend,
+    output_format: OutputFormat,
+    color: bool,
+    explain: bool,
+    stats: bool,
+    max_technique: Option<Technique>,
+    annealing: Annealing,
+    options: SolveOptions,
+    variants: Vec<Variant>,
+    render: Option<(PathBuf, RenderOptions)>,
 }
 
-impl From<String> for Config {
-    fn from(puzzle: String) -> Self {
+#[cfg(feature = "std")]
+impl ConfigBuilder {
+    pub fn backend(self, backend: Backend) -> Self {
+        ConfigBuilder { backend, ..self }
+    }
+
+    pub fn output_format(self, output_format: OutputFormat) -> Self {
+        ConfigBuilder {
+            output_format,
+            ..self
+        }
+    }
+
+    // Colors grid output with ANSI escape codes.
+    pub fn color(self, color: bool) -> Self {
+        ConfigBuilder { color, ..self }
+    }
+
+    pub fn explain(self, explain: bool) -> Self {
+        ConfigBuilder { explain, ..self }
+    }
+
+    pub fn stats(self, stats: bool) -> Self {
+        ConfigBuilder { stats, ..self }
+    }
+
+    pub fn max_technique(self, max_technique: Option<Technique>) -> Self {
+        ConfigBuilder {
+            max_technique,
+            ..self
+        }
+    }
+
+    // Settings for the annealing backend. A seed set with `seed`, before or
+    // after, takes the place of its own.
+    pub fn annealing(self, annealing: Annealing) -> Self {
+        ConfigBuilder {
+            annealing: Annealing {
+                seed: self.options.seed.or(annealing.seed),
+                ..annealing
+            },
+            ..self
+        }
+    }
+
+    pub fn require_unique(mut self, require_unique: bool) -> Self {
+        self.options.require_unique = require_unique;
+        self
+    }
+
+    pub fn heuristic(mut self, heuristic: Heuristic) -> Self {
+        self.options.heuristic = heuristic;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.options.timeout = timeout;
+        self
+    }
+
+    // Seeds both random candidate ordering and the annealing backend.
+    pub fn seed(mut self, seed: Option<u64>) -> Self {
+        self.options.seed = seed;
+        self.annealing.seed = seed.or(self.annealing.seed);
+        self
+    }
+
+    // Extra rules added to every puzzle built.
+    pub fn variants(self, variants: &[Variant]) -> Self {
+        ConfigBuilder {
+            variants: variants.to_vec(),
+            ..self
+        }
+    }
+
+    pub fn render(self, path: PathBuf, options: RenderOptions) -> Self {
+        ConfigBuilder {
+            render: Some((path, options)),
+            ..self
+        }
+    }
+
+    pub fn solver(&self) -> Solver {
+        Solver::with_backend(self.backend)
+            .with_annealing(self.annealing)
+            .with_options(self.options)
+    }
+
+    // `puzzle` with the configured variants added.
+    pub fn apply_variants(&self, puzzle: State) -> State {
+        puzzle.with_variants(&self.variants)
+    }
+
+    pub fn build(&self, puzzle: State) -> Config {
         Config {
-            puzzle: State::from(puzzle.as_str()),
+            puzzle: self.apply_variants(puzzle),
+            solver: self.solver(),
+            output_format: self.output_format,
+            color: self.color,
+            explain: self.explain,
+            stats: self.stats,
+            max_technique: self.max_technique,
+            render: self.render.clone(),
+        }
+    }
+}
+
+// Solves the configured puzzle and prints the solution to stdout, returning
+// the error instead if it could not be solved.
+#[cfg(feature = "std")]
+pub fn run(mut config: Config) -> anyhow::Result<()> {
+    let solver = &config.solver;
+
+    #[cfg(feature = "serde")]
+    if config.output_format == OutputFormat::Json {
+        let report = report::SolveReport::new(solver, &config.puzzle);
+        println!(
+            "{}",
+            serde_json::to_string(&report).expect("report serializes to json")
+        );
+        return match report.error {
+            Some(error) => Err(anyhow::anyhow!(error)),
+            None => Ok(()),
+        };
+    }
+
+    let puzzle = config.puzzle.clone();
+    let mut stats = None;
+    if let Some(max) = config.max_technique {
+        let logical = solver.solve_logical_only(&mut config.puzzle, max)?;
+        if config.explain {
+            print!("{logical}");
+        }
+        if !logical.is_solved() {
+            println!(
+                "progress:{}",
+                labelled(&config.puzzle, config.output_format, config.color)
+            );
+            anyhow::bail!(
+                "no technique up to {max} applies, {} cells left open",
+                logical.unsolved
+            );
         }
+    } else if config.explain {
+        let trace = solver.solve_with_trace(&mut config.puzzle)?;
+        match config.output_format {
+            OutputFormat::Candidates => print!("{}", trace.with_candidates(&puzzle)),
+            _ => print!("{trace}"),
+        }
+    } else if config.stats {
+        stats = Some(solver.solve_with_stats(&mut config.puzzle)?);
+    } else {
+        solver.solve(&mut config.puzzle)?;
+    }
+
+    println!(
+        "solution:{}",
+        labelled(&config.puzzle, config.output_format, config.color)
+    );
+    if let Some(stats) = stats {
+        println!("{stats}");
+    }
+    if let Some((path, options)) = &config.render {
+        let progress = (!options.pencil_marks).then_some(&config.puzzle);
+        std::fs::write(path, render::svg(&puzzle, progress, options))?;
     }
+
+    Ok(())
 }
 
-pub fn run(mut config: Config) {
-    match config.puzzle.solve() {
-        Ok(_) => println!("solution: {}", config.puzzle),
-        Err(e) => println!("{e}"),
+// Formats a grid to follow a `label:` prefix, on the same line for oneline
+// output and starting on the next line otherwise, in color if `color` is set.
+pub fn labelled(state: &State, format: OutputFormat, color: bool) -> String {
+    match format {
+        OutputFormat::Oneline | OutputFormat::Marks | OutputFormat::Code => {
+            format!(" {}", state.format(format))
+        }
+        _ => format!("\n{}", state.format_with(format, color).trim_end()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Config;
+    use crate::annealing::Annealing;
+    use crate::solver::Backend;
+    use crate::state::State;
+    use crate::variant::Variant;
+
+    #[test]
+    fn can_build_config() {
+        let builder = Config::builder()
+            .seed(Some(7))
+            .annealing(Annealing {
+                iterations: 10,
+                ..Annealing::default()
+            })
+            .backend(Backend::Dlx)
+            .require_unique(true)
+            .variants(&[Variant::X]);
+        let config = builder.build(State::from(&"0".repeat(81) as &str));
+
+        assert_eq!(builder.annealing.iterations, 10);
+        assert_eq!(builder.annealing.seed, Some(7));
+        assert_eq!(config.solver.backend(), Backend::Dlx);
+        assert_eq!(config.solver.options().seed, Some(7));
+        assert!(config.solver.options().require_unique);
+        assert_eq!(config.puzzle.constraints().diagonals().len(), 2);
     }
 }
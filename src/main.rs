@@ -1,22 +1,928 @@
-use clap::Parser;
+use clap::{Args, CommandFactory, Parser, Subcommand};
 
-use log::LevelFilter;
-use sudoku_solver::{self, Config};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use sudoku_solver::analysis;
+use sudoku_solver::annealing::Annealing;
+use sudoku_solver::author;
+use sudoku_solver::batch::{self, BatchEntry, Checkpoint, StreamOptions};
+use sudoku_solver::bench::BenchStats;
+use sudoku_solver::collection::CollectionStats;
+use sudoku_solver::constraints::Constraints;
+use sudoku_solver::dedupe::{self, Duplicate};
+use sudoku_solver::diff::CellDiff;
+use sudoku_solver::format::{ColorChoice, OutputFormat};
+use sudoku_solver::generator::{self, Symmetry};
+use sudoku_solver::io::{self as puzzle_io, FileFormat};
+use sudoku_solver::killer;
+use sudoku_solver::pdf;
+use sudoku_solver::rating::{self, Difficulty};
+use sudoku_solver::render::RenderOptions;
+use sudoku_solver::repair;
+use sudoku_solver::samurai::MultiGridState;
+use sudoku_solver::shapes;
+use sudoku_solver::solver::{Backend, Heuristic, Solver};
+use sudoku_solver::state::State;
+use sudoku_solver::strategies::Technique;
+use sudoku_solver::variant::Variant;
+use sudoku_solver::{self, Config, ConfigBuilder};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::format::FmtSpan;
 
+// Without a subcommand the arguments of `solve` are taken directly, so
+// `sudoku-solver -p ...` solves a puzzle as `sudoku-solver solve -p ...` does.
 #[derive(Parser, Debug)]
 struct Cli {
-    #[arg(short, long)]
-    puzzle: String,
+    /// Puzzle file format, detected from the file extension by default
+    #[arg(long, global = true)]
+    format: Option<FileFormat>,
 
-    #[arg(short, long, default_value = "warn")]
+    /// Log level: off, error, warn, info, debug or trace; debug times each solve and trace each strategy, propagation pass and search node
+    #[arg(short, long, default_value = "warn", global = true)]
     log: LevelFilter,
+
+    /// Read puzzles passed with --puzzle or on stdin as short codes, as printed by `--output-format code`
+    #[arg(long, global = true)]
+    code: bool,
+
+    /// Number of digits in the grid, e.g. 4, 9, 16 or 25 (inferred from the puzzle length by default)
+    #[arg(long, global = true)]
+    size: Option<usize>,
+
+    /// Solver backend: propagation, dlx, annealing or sat (requires the `sat` feature)
+    #[arg(short, long, default_value = "propagation", global = true)]
+    backend: Backend,
+
+    /// Iterations the annealing backend runs before giving up
+    #[arg(long, default_value_t = Annealing::default().iterations, global = true)]
+    iterations: usize,
+
+    /// Starting temperature of the annealing backend
+    #[arg(long, default_value_t = Annealing::default().temperature, global = true)]
+    temperature: f64,
+
+    /// Seed for random choices, for reproducible runs: puzzle generation, the random heuristic and the annealing backend
+    #[arg(long, global = true)]
+    seed: Option<u64>,
+
+    /// Fail when a puzzle has more than one solution instead of printing one of them
+    #[arg(long, global = true)]
+    unique: bool,
+
+    /// Order to try candidates in when guessing: ascending, least-constraining or random
+    #[arg(long, default_value = "ascending", global = true)]
+    heuristic: Heuristic,
+
+    /// Give up on a puzzle after this many seconds
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds, global = true)]
+    timeout: Option<Duration>,
+
+    /// How to print grids: oneline, grid, candidates, marks (one line with the candidates of open cells, which can be read back as a puzzle) or code (a short code for sharing, read back with --code)
+    #[arg(long, default_value = "oneline", global = true)]
+    output_format: OutputFormat,
+
+    /// Color grid output: givens bold, placed digits cyan and clashing digits red; auto colors only when printing to a terminal, always or never
+    #[arg(long, default_value = "auto", global = true)]
+    color: ColorChoice,
+
+    /// Extra rules to solve or generate with: x, hyper, anti-knight, anti-king or non-consecutive; repeat or separate with commas to combine them
+    #[arg(long, value_delimiter = ',', global = true)]
+    variant: Vec<Variant>,
+
+    /// Jigsaw region layout, one character per cell naming the cell's region
+    #[arg(long, global = true)]
+    regions: Option<String>,
+
+    /// Killer cages, one `sum,r1c1,r1c2,...` line per cage; the puzzle defaults to an empty grid
+    #[arg(long, global = true)]
+    cages: Option<PathBuf>,
+
+    /// Greater-than markers between adjacent cells, one `r1c1<r1c2` or `r1c1>r1c2` per line
+    #[arg(long, value_name = "PATH", global = true)]
+    inequalities: Option<PathBuf>,
+
+    /// Sandwich clues, one `r1=15` or `c3=0` per line: the sum of the digits between the 1 and the 9 in that row or column
+    #[arg(long, value_name = "PATH", global = true)]
+    sandwiches: Option<PathBuf>,
+
+    /// Thermometers, arrows and kropki dots as JSON: `{"thermometers": [["r1c1", ...]], "arrows": [{"circle": "r1c1", "cells": [...]}], "white_dots": [["r1c1", "r1c2"]], "black_dots": [...]}`
+    #[cfg(feature = "serde")]
+    #[arg(long, value_name = "PATH", global = true)]
+    shapes: Option<PathBuf>,
+
+    #[command(flatten)]
+    solve: SolveArgs,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Args, Debug, Default, PartialEq)]
+struct SolveArgs {
+    /// Puzzle to solve, read from stdin when omitted or `-`
+    #[arg(short, long)]
+    puzzle: Option<String>,
+
+    /// Solve every puzzle in an .sdk, .sdm or .ss file
+    #[arg(short, long, conflicts_with = "puzzle")]
+    file: Option<PathBuf>,
+
+    /// Print each logical step taken before the solution, with the candidates left after each one for --output-format candidates
+    #[arg(long)]
+    explain: bool,
+
+    /// Also draw the puzzle and its solution as an SVG image
+    #[arg(long, value_name = "PATH", conflicts_with = "file")]
+    render: Option<PathBuf>,
+
+    /// Draw the puzzle with pencil marks instead of its solution
+    #[arg(long, requires = "render")]
+    pencil_marks: bool,
+
+    /// Print solve statistics after the solution: time, deductions, guesses and search depth
+    #[arg(long, conflicts_with = "explain")]
+    stats: bool,
+
+    /// Solve by logic alone, never guessing, with techniques up to this one, e.g. hidden-pair or x-wing
+    #[arg(long, value_name = "TECHNIQUE", conflicts_with = "stats")]
+    max_technique: Option<Technique>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Solve a puzzle, or every puzzle in a file; the default without a subcommand
+    Solve(SolveArgs),
+    /// Generate a new puzzle with a unique solution
+    Generate {
+        #[arg(short, long, default_value = "medium")]
+        difficulty: Difficulty,
+
+        #[arg(short, long, default_value = "none")]
+        symmetry: Symmetry,
+
+        /// Also write the puzzle to an .sdk, .sdm or .ss file
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Generate a printable PDF worksheet of puzzles, followed by their answers
+    ExportPdf {
+        /// Where to write the PDF
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Number of puzzles to generate
+        #[arg(short, long, default_value_t = 6)]
+        count: usize,
+
+        /// Puzzles to print on each page, up to 12
+        #[arg(long, default_value_t = 4)]
+        per_page: usize,
+
+        /// Difficulties to generate, taken in turn, e.g. easy,medium,hard
+        #[arg(short, long, value_delimiter = ',', default_value = "medium")]
+        difficulty: Vec<Difficulty>,
+
+        #[arg(short, long, default_value = "none")]
+        symmetry: Symmetry,
+    },
+    /// Rate the difficulty of a puzzle by the techniques needed to solve it
+    Rate {
+        /// Read from stdin when omitted or `-`
+        #[arg(short, long)]
+        puzzle: Option<String>,
+    },
+    /// Find where a puzzle gets hard: where singles stop, the branching factor there and the fewest cells that must be given for singles to finish it
+    Analyze {
+        /// Read from stdin when omitted or `-`
+        #[arg(short, long)]
+        puzzle: Option<String>,
+    },
+    /// Show the next logical step for a puzzle without solving it
+    Hint {
+        /// Read from stdin when omitted or `-`
+        #[arg(short, long)]
+        puzzle: Option<String>,
+    },
+    /// Play a puzzle in the terminal, with hints and mistake checking
+    #[cfg(feature = "tui")]
+    Play {
+        /// Read from stdin when omitted or `-`
+        #[arg(short, long)]
+        puzzle: Option<String>,
+    },
+    /// Remove every given a puzzle's unique solution does not depend on
+    Minimize {
+        /// Read from stdin when omitted or `-`
+        #[arg(short, long)]
+        puzzle: Option<String>,
+    },
+    /// Suggest clues to add to a puzzle being set until its solution is unique and, with --difficulty, it is rated that hard
+    Author {
+        /// Read from stdin when omitted or `-`
+        #[arg(short, long)]
+        puzzle: Option<String>,
+
+        /// Difficulty to aim for once the solution is unique
+        #[arg(short, long)]
+        difficulty: Option<Difficulty>,
+    },
+    /// Find the fewest givens to remove or change so a puzzle with no solution, or several, has exactly one
+    Repair {
+        /// Read from stdin when omitted or `-`
+        #[arg(short, long)]
+        puzzle: Option<String>,
+    },
+    /// Compare an attempt at a puzzle with its solution, listing wrong and empty cells
+    Diff {
+        /// Grid to check, read from stdin when omitted or `-`
+        #[arg(short, long)]
+        attempt: Option<String>,
+
+        /// The solution to compare against
+        #[arg(short, long, required_unless_present = "puzzle")]
+        solution: Option<String>,
+
+        /// Solve this puzzle for the solution instead
+        #[arg(short, long, conflicts_with = "solution")]
+        puzzle: Option<String>,
+    },
+    /// Check a puzzle for conflicting givens and count its solutions
+    Validate {
+        /// Read from stdin when omitted or `-`
+        #[arg(short, long)]
+        puzzle: Option<String>,
+    },
+    /// Solve a samurai puzzle of five 9×9 grids overlapping on a 21×21 board
+    Samurai {
+        /// Read from stdin when omitted or `-`
+        #[arg(short, long)]
+        puzzle: Option<String>,
+
+        /// Read the board from a file instead
+        #[arg(short, long, conflicts_with = "puzzle")]
+        file: Option<PathBuf>,
+    },
+    /// Answer solve, hint, validate and generate requests as a JSON API over HTTP
+    #[cfg(feature = "server")]
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        addr: String,
+    },
+    /// Audit a file of puzzles, one per line or as `id,puzzle` CSV: solve times, difficulties, techniques, duplicates and puzzles without a unique solution
+    Stats {
+        #[arg(short, long)]
+        file: PathBuf,
+    },
+    /// Find puzzles in a file that repeat another up to symmetry or only add givens to another
+    Dedupe {
+        #[arg(short, long)]
+        file: PathBuf,
+
+        /// Write the puzzles kept here as `id,puzzle` CSV
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Time solving every puzzle in a file, one per line or as `id,puzzle` CSV, such as a local copy of a standard dataset like the 17-clue list
+    Bench {
+        #[arg(short, long)]
+        file: PathBuf,
+
+        /// Times to solve the whole file
+        #[arg(short, long, default_value_t = 1)]
+        runs: usize,
+
+        /// Also time this backend on the same puzzles and print the two side by side
+        #[arg(long, value_name = "BACKEND")]
+        compare: Option<Backend>,
+    },
+    /// Solve a file of puzzles, one per line or as `id,puzzle` CSV
+    SolveBatch {
+        #[arg(short, long)]
+        file: PathBuf,
+
+        /// Write results here instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Number of threads to solve with (requires the `parallel` feature, defaults to every core)
+        #[arg(short, long, conflicts_with = "checkpoint")]
+        jobs: Option<usize>,
+
+        /// Record how many puzzles are done in this file, and resume from it if it exists, appending to --output; solves on one thread, streaming the input
+        #[arg(long, value_name = "PATH")]
+        checkpoint: Option<PathBuf>,
+
+        /// Results to write between flushes of the output and the checkpoint
+        #[arg(long, default_value_t = StreamOptions::default().flush_every)]
+        flush_every: usize,
+    },
 }
 
 fn main() {
     let cli = Cli::parse();
 
-    env_logger::Builder::new().filter_level(cli.log).init();
-    let config = Config::from(cli.puzzle);
+    tracing_subscriber::fmt()
+        .with_max_level(cli.log)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_writer(io::stderr)
+        .with_ansi(io::stderr().is_terminal())
+        .init();
+
+    let color = cli.color.enabled(io::stdout().is_terminal());
+    let config = Config::builder()
+        .backend(cli.backend)
+        .annealing(Annealing {
+            iterations: cli.iterations,
+            temperature: cli.temperature,
+            ..Annealing::default()
+        })
+        .require_unique(cli.unique)
+        .timeout(cli.timeout)
+        .heuristic(cli.heuristic)
+        .seed(cli.seed)
+        .output_format(cli.output_format)
+        .color(color)
+        .variants(&cli.variant);
+    let solver = config.solver();
+
+    let rules = Rules {
+        config: &config,
+        code: cli.code,
+        regions: cli.regions,
+        cages: cli
+            .cages
+            .map(|path| std::fs::read_to_string(path).unwrap_or_else(exit_with)),
+        inequalities: cli
+            .inequalities
+            .map(|path| std::fs::read_to_string(path).unwrap_or_else(exit_with)),
+        sandwiches: cli
+            .sandwiches
+            .map(|path| std::fs::read_to_string(path).unwrap_or_else(exit_with)),
+        #[cfg(feature = "serde")]
+        shapes: cli
+            .shapes
+            .map(|path| std::fs::read_to_string(path).unwrap_or_else(exit_with)),
+        #[cfg(not(feature = "serde"))]
+        shapes: None,
+    };
+
+    let command = match cli.command {
+        Some(_) if cli.solve != SolveArgs::default() => Cli::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "solve options cannot be used with a subcommand",
+            )
+            .exit(),
+        Some(command) => command,
+        None => Command::Solve(cli.solve),
+    };
+
+    match command {
+        Command::Generate {
+            difficulty,
+            symmetry,
+            output,
+        } => {
+            let puzzle = generate(difficulty, symmetry, &cli.variant, cli.seed, 0);
+            println!(
+                "puzzle:{}",
+                sudoku_solver::labelled(&puzzle, cli.output_format, color)
+            );
+
+            if let Some(path) = output {
+                if let Err(e) = puzzle_io::write_file(&path, &[puzzle], cli.format) {
+                    exit_with(e)
+                }
+            }
+        }
+        Command::ExportPdf {
+            output,
+            count,
+            per_page,
+            difficulty,
+            symmetry,
+        } => {
+            let puzzles: Vec<(State, String)> = difficulty
+                .iter()
+                .cycle()
+                .take(count)
+                .enumerate()
+                .map(|(i, difficulty)| {
+                    let puzzle = generate(*difficulty, symmetry, &cli.variant, cli.seed, i as u64);
+                    let rated = rating::rate(&puzzle).map_or(*difficulty, |r| r.difficulty);
+                    (puzzle, format!("{}. {rated}", i + 1))
+                })
+                .collect();
+
+            let pdf = pdf::worksheet(&puzzles, per_page).unwrap_or_else(exit_with);
+            std::fs::write(&output, pdf).unwrap_or_else(exit_with);
+            println!("wrote {count} puzzles to {}", output.display());
+        }
+        Command::Rate { puzzle } => {
+            let state = rules.load(&required_puzzle(puzzle), cli.size);
+
+            match rating::rate(&state) {
+                Ok(rating) => println!("rating: {rating}"),
+                Err(e) => exit_with(e),
+            }
+        }
+        Command::Analyze { puzzle } => {
+            let state = rules.load(&required_puzzle(puzzle), cli.size);
+
+            match analysis::analyze(&state) {
+                Ok(analysis) => println!("{analysis}"),
+                Err(e) => exit_with(e),
+            }
+        }
+        Command::Hint { puzzle } => {
+            let state = rules.load(&required_puzzle(puzzle), cli.size);
+
+            match solver.next_hint(&state) {
+                Some(hint) => println!("hint: {hint}"),
+                None => println!("no logical step found"),
+            }
+        }
+        #[cfg(feature = "tui")]
+        Command::Play { puzzle } => {
+            let state = rules.load(&required_puzzle(puzzle), cli.size);
+
+            if let Err(e) = sudoku_solver::play::play(state) {
+                exit_with(e)
+            }
+        }
+        Command::Minimize { puzzle } => {
+            let state = rules.load(&required_puzzle(puzzle), cli.size);
+
+            match state.minimize() {
+                Ok(minimized) => {
+                    println!(
+                        "puzzle:{}",
+                        sudoku_solver::labelled(&minimized, cli.output_format, color)
+                    );
+                    println!("clues: {}", minimized.filled_count());
+                }
+                Err(e) => exit_with(e),
+            }
+        }
+        Command::Author { puzzle, difficulty } => {
+            let state = rules.load(&required_puzzle(puzzle), cli.size);
+
+            match author::suggest(&state, difficulty) {
+                Ok(suggestions) => {
+                    println!("{suggestions}");
+                    println!(
+                        "puzzle:{}",
+                        sudoku_solver::labelled(&suggestions.puzzle, cli.output_format, color)
+                    );
+                }
+                Err(e) => exit_with(e),
+            }
+        }
+        Command::Repair { puzzle } => {
+            let state = rules.load(&required_puzzle(puzzle), cli.size);
+
+            match repair::repair(&state) {
+                Some(repair) => {
+                    println!("{repair}");
+                    println!(
+                        "puzzle:{}",
+                        sudoku_solver::labelled(&repair.puzzle, cli.output_format, color)
+                    );
+                }
+                None => exit_with(
+                    "no repair found changing few enough givens; try `author` to add clues instead",
+                ),
+            }
+        }
+        Command::Diff {
+            attempt,
+            solution,
+            puzzle,
+        } => {
+            let attempt = rules.load(&required_puzzle(attempt), cli.size);
+            let solution = match (solution, puzzle) {
+                (Some(solution), _) => rules.load(&solution, cli.size),
+                (None, puzzle) => {
+                    let mut state = rules.load(&puzzle.expect("clap requires one"), cli.size);
+                    if state.count_solutions(2) > 1 {
+                        exit_with::<()>("puzzle has more than one solution; pass --solution");
+                    }
+                    solver.solve(&mut state).unwrap_or_else(exit_with);
+                    state
+                }
+            };
+            if attempt.size() != solution.size() {
+                exit_with::<()>(format!(
+                    "attempt is {0}×{0} but the solution is {1}×{1}",
+                    attempt.size(),
+                    solution.size()
+                ));
+            }
+
+            let diffs = attempt.diff(&solution);
+            for diff in &diffs {
+                println!("{diff}");
+            }
+            if diffs.is_empty() {
+                println!("attempt matches the solution");
+            } else {
+                let wrong = diffs
+                    .iter()
+                    .filter(|d| matches!(d, CellDiff::Wrong { .. }))
+                    .count();
+                println!("{wrong} wrong, {} empty", diffs.len() - wrong);
+                std::process::exit(1);
+            }
+        }
+        Command::Validate { puzzle } => {
+            let state = rules.load(&required_puzzle(puzzle), cli.size);
+            let validation = state.validate();
+
+            println!("{validation}");
+            if !validation.is_valid() {
+                std::process::exit(1);
+            }
+        }
+        Command::Samurai { puzzle, file } => {
+            let puzzle = match (puzzle, file) {
+                (None, Some(file)) => std::fs::read_to_string(file).unwrap_or_else(exit_with),
+                (puzzle, _) => required_puzzle(puzzle),
+            };
+            let mut board = MultiGridState::samurai(&puzzle).unwrap_or_else(exit_with);
+
+            match board.solve() {
+                Ok(()) => println!("solution:\n{board}"),
+                Err(e) => exit_with(e),
+            }
+        }
+        #[cfg(feature = "server")]
+        Command::Serve { addr } => {
+            if let Err(e) = sudoku_solver::server::serve(&addr, &solver) {
+                exit_with(e)
+            }
+        }
+        Command::Stats { file } => {
+            let entries = File::open(&file)
+                .map(BufReader::new)
+                .and_then(|reader| batch::read_entries(reader).collect::<io::Result<Vec<_>>>())
+                .unwrap_or_else(exit_with);
+
+            let rules = rules.batch(cli.size, &cli.variant);
+            println!(
+                "{}",
+                CollectionStats::collect(&solver, rules.as_ref(), entries)
+            );
+        }
+        Command::Dedupe { file, output } => {
+            let rules = rules.batch(cli.size, &cli.variant);
+            if let Err(e) = dedupe(&file, rules.as_ref(), output.as_deref()) {
+                exit_with(e)
+            }
+        }
+        Command::Bench {
+            file,
+            runs,
+            compare,
+        } => {
+            let mut solvers = vec![solver];
+            solvers.extend(compare.map(|backend| config.clone().backend(backend).solver()));
+            let rules = rules.batch(cli.size, &cli.variant);
+            if let Err(e) = bench(&file, rules.as_ref(), &solvers, runs) {
+                exit_with(e)
+            }
+        }
+        Command::SolveBatch {
+            file,
+            output,
+            jobs,
+            checkpoint,
+            flush_every,
+        } => {
+            let options = StreamOptions {
+                solver,
+                rules: rules.batch(cli.size, &cli.variant),
+                flush_every,
+                checkpoint,
+            };
+            if let Err(e) = solve_batch(&file, output.as_deref(), &options, jobs) {
+                exit_with(e)
+            }
+        }
+        Command::Solve(args) => {
+            let config = config
+                .clone()
+                .explain(args.explain)
+                .stats(args.stats)
+                .max_technique(args.max_technique);
+            let config = match args.render {
+                Some(path) => config.render(
+                    path,
+                    RenderOptions {
+                        pencil_marks: args.pencil_marks,
+                        ..RenderOptions::default()
+                    },
+                ),
+                None => config,
+            };
+
+            // stdin is only read when there is no file, so a file is not
+            // mistaken for piped input in scripts
+            let puzzle = match (&args.puzzle, &args.file) {
+                (None, Some(_)) => None,
+                _ => read_puzzle(args.puzzle),
+            };
+            let puzzles = match (puzzle, args.file) {
+                (Some(puzzle), _) => vec![rules.read(&puzzle, cli.size)],
+                (None, Some(file)) => match puzzle_io::read_file(&file, cli.format) {
+                    Ok(puzzles) => puzzles.into_iter().map(|p| rules.apply(p)).collect(),
+                    Err(e) => exit_with(e),
+                },
+                (None, None) if rules.has_shapes() => {
+                    let size = cli.size.unwrap_or(9);
+                    let empty = State::parse_with_size(&"0".repeat(size * size), size)
+                        .unwrap_or_else(exit_with);
+                    vec![rules.apply(empty)]
+                }
+                (None, None) => Cli::command()
+                    .error(
+                        clap::error::ErrorKind::MissingRequiredArgument,
+                        "either --puzzle, --file, a puzzle on stdin or a subcommand is required",
+                    )
+                    .exit(),
+            };
+
+            let mut failed = false;
+            for puzzle in puzzles {
+                if let Err(e) = sudoku_solver::run(config.build(puzzle)) {
+                    eprintln!("{e}");
+                    failed = true;
+                }
+            }
+            if failed {
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+// Variant rules applied to every puzzle read: regions, cages and shapes from
+// their own options, and the variants set on `config`. Puzzles passed on to
+// `config.build` are only read, as building applies the variants itself.
+struct Rules<'a> {
+    config: &'a ConfigBuilder,
+    code: bool,
+    regions: Option<String>,
+    cages: Option<String>,
+    inequalities: Option<String>,
+    sandwiches: Option<String>,
+    shapes: Option<String>,
+}
+
+impl Rules<'_> {
+    fn has_shapes(&self) -> bool {
+        self.cages.is_some()
+            || self.inequalities.is_some()
+            || self.sandwiches.is_some()
+            || self.shapes.is_some()
+    }
+
+    // The rules every puzzle of a batch is read under, built on an empty grid
+    // of `size`, or 9 by default. `None` without any, so puzzles of every
+    // size can be read.
+    fn batch(&self, size: Option<usize>, variants: &[Variant]) -> Option<Constraints> {
+        if size.is_none() && variants.is_empty() && self.regions.is_none() && !self.has_shapes() {
+            return None;
+        }
+
+        let size = size.unwrap_or(9);
+        let empty =
+            State::parse_with_size(&"0".repeat(size * size), size).unwrap_or_else(exit_with);
+        Some(self.load_state(empty).constraints().clone())
+    }
+
+    fn load(&self, puzzle: &str, size: Option<usize>) -> State {
+        self.load_state(self.read(puzzle, size))
+    }
+
+    fn load_state(&self, state: State) -> State {
+        self.config.apply_variants(self.apply(state))
+    }
+
+    fn read(&self, puzzle: &str, size: Option<usize>) -> State {
+        let state = match size {
+            _ if self.code => State::from_code(puzzle),
+            Some(size) => State::parse_with_size(puzzle, size),
+            None => puzzle.parse(),
+        }
+        .unwrap_or_else(exit_with);
+
+        self.apply(state)
+    }
+
+    fn apply(&self, state: State) -> State {
+        let state = match &self.regions {
+            Some(layout) => state.with_regions(layout).unwrap_or_else(exit_with),
+            None => state,
+        };
+        let state = match &self.cages {
+            Some(cages) => {
+                let cages = killer::parse_cages(cages, state.size()).unwrap_or_else(exit_with);
+                state.with_cages(cages)
+            }
+            None => state,
+        };
+
+        let state = match &self.inequalities {
+            Some(inequalities) => {
+                let inequalities = shapes::parse_inequalities(inequalities, state.size())
+                    .unwrap_or_else(exit_with);
+                state.with_shapes(inequalities)
+            }
+            None => state,
+        };
+        let state = match &self.sandwiches {
+            Some(sandwiches) => {
+                let sandwiches =
+                    shapes::parse_sandwiches(sandwiches, state.size()).unwrap_or_else(exit_with);
+                state.with_shapes(sandwiches)
+            }
+            None => state,
+        };
+
+        match &self.shapes {
+            #[cfg(feature = "serde")]
+            Some(shapes) => {
+                let shapes = shapes::parse_shapes(shapes, state.size()).unwrap_or_else(exit_with);
+                state.with_shapes(shapes)
+            }
+            _ => state,
+        }
+    }
+}
+
+// The puzzle given as an argument, or read from stdin when the argument is
+// omitted or `-`. Without the argument an interactive or empty stdin counts
+// as no puzzle.
+fn read_puzzle(puzzle: Option<String>) -> Option<String> {
+    match puzzle.as_deref() {
+        Some("-") => Some(io::read_to_string(io::stdin()).unwrap_or_else(exit_with)),
+        Some(_) => puzzle,
+        None if io::stdin().is_terminal() => None,
+        None => Some(io::read_to_string(io::stdin()).unwrap_or_else(exit_with))
+            .filter(|input| !input.trim().is_empty()),
+    }
+}
+
+fn required_puzzle(puzzle: Option<String>) -> String {
+    read_puzzle(puzzle)
+        .unwrap_or_else(|| exit_with("no puzzle given: pass --puzzle or pipe one to stdin"))
+}
+
+fn exit_with<T>(error: impl std::fmt::Display) -> T {
+    eprintln!("{error}");
+    std::process::exit(1)
+}
+
+// Generates the `n`th puzzle of a series, the same one every time for the
+// same seed.
+fn generate(
+    difficulty: Difficulty,
+    symmetry: Symmetry,
+    variants: &[Variant],
+    seed: Option<u64>,
+    n: u64,
+) -> State {
+    match seed {
+        Some(seed) => {
+            generator::generate_seeded(difficulty, symmetry, variants, seed.wrapping_add(n))
+        }
+        None => generator::generate_variant(difficulty, symmetry, variants),
+    }
+}
+
+fn parse_seconds(s: &str) -> Result<Duration, String> {
+    let seconds: f64 = s
+        .parse()
+        .map_err(|_| format!("not a number of seconds: {s}"))?;
+
+    Duration::try_from_secs_f64(seconds).map_err(|e| e.to_string())
+}
+
+// Solves every puzzle in `file` `runs` times over with each solver, and
+// reports how many were solved and how fast. Puzzles that cannot be read are
+// reported and left out.
+fn bench(
+    file: &Path,
+    rules: Option<&Constraints>,
+    solvers: &[Solver],
+    runs: usize,
+) -> anyhow::Result<()> {
+    let entries: Vec<BatchEntry> =
+        batch::read_entries(BufReader::new(File::open(file)?)).collect::<io::Result<_>>()?;
+
+    let mut puzzles = vec![];
+    for entry in entries {
+        match batch::read_puzzle(&entry.puzzle, rules) {
+            Ok(puzzle) => puzzles.push(puzzle),
+            Err(e) => println!("{}: {e}", entry.id),
+        }
+    }
+
+    println!("{}", BenchStats::collect(solvers, &puzzles, runs));
+
+    Ok(())
+}
+
+// Reports every redundant puzzle in `file`, and writes the rest to `output`.
+// Puzzles that cannot be read are reported and left out.
+fn dedupe(file: &Path, rules: Option<&Constraints>, output: Option<&Path>) -> anyhow::Result<()> {
+    let entries: Vec<BatchEntry> =
+        batch::read_entries(BufReader::new(File::open(file)?)).collect::<io::Result<_>>()?;
+
+    let mut puzzles = vec![];
+    let mut read = vec![];
+    for entry in entries {
+        match batch::read_puzzle(&entry.puzzle, rules) {
+            Ok(puzzle) => {
+                puzzles.push(puzzle);
+                read.push(entry);
+            }
+            Err(e) => println!("{}: {e}", entry.id),
+        }
+    }
+
+    let duplicates = dedupe::find_duplicates(&puzzles);
+    for (entry, duplicate) in read.iter().zip(&duplicates) {
+        match duplicate {
+            Some(Duplicate::Equivalent(of)) => {
+                println!("{}: equivalent to {}", entry.id, read[*of].id)
+            }
+            Some(Duplicate::Superset(of)) => {
+                println!("{}: adds givens to {}", entry.id, read[*of].id)
+            }
+            None => {}
+        }
+    }
+
+    let kept: Vec<&BatchEntry> = read
+        .iter()
+        .zip(&duplicates)
+        .filter(|(_, d)| d.is_none())
+        .map(|(entry, _)| entry)
+        .collect();
+    if let Some(path) = output {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "id,puzzle")?;
+        for entry in &kept {
+            writeln!(writer, "{},{}", entry.id, entry.puzzle)?;
+        }
+        writer.flush()?;
+    }
+    eprintln!("kept {} of {} puzzles", kept.len(), read.len());
+
+    Ok(())
+}
+
+// Solves a file of puzzles across threads with the `parallel` feature, and
+// otherwise, or to resume from a checkpoint, streams it on one thread.
+fn solve_batch(
+    file: &Path,
+    output: Option<&Path>,
+    options: &StreamOptions,
+    jobs: Option<usize>,
+) -> anyhow::Result<()> {
+    let checkpoint = match &options.checkpoint {
+        Some(path) => batch::read_checkpoint(path)?,
+        None => Checkpoint::default(),
+    };
+    let mut writer: Box<dyn Write> = match output {
+        Some(path) if checkpoint.entries > 0 => {
+            Box::new(BufWriter::new(batch::resume_output(path, &checkpoint)?))
+        }
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout())),
+    };
+    let reader = BufReader::new(File::open(file)?);
+
+    let summary = match &options.checkpoint {
+        #[cfg(feature = "parallel")]
+        None => batch::solve_stream_parallel(reader, &mut writer, options, jobs)?,
+        _ => {
+            #[cfg(not(feature = "parallel"))]
+            if jobs.is_some_and(|j| j != 1) {
+                tracing::warn!("built without the parallel feature, solving on one thread");
+            }
+            batch::solve_stream(reader, &mut writer, options)?
+        }
+    };
+
+    if summary.skipped > 0 {
+        eprintln!("resumed after {} puzzles", summary.skipped);
+    }
+    eprintln!("solved: {}, failed: {}", summary.solved, summary.failed);
 
-    sudoku_solver::run(config);
+    Ok(())
 }
@@ -0,0 +1,173 @@
+use crate::parse;
+use crate::state::{digit_char, digit_value, SolveError, State, MAX_SIZE};
+use alloc::{format, string::String, vec, vec::Vec};
+
+// Saves and loads a grid with its candidates, so a partly solved grid or a
+// player's pencil marks can be picked up again exactly. Each cell is written
+// as its digit once determined, or as its candidates in brackets, e.g.
+// `1[23][234]4...`. Whitespace and grid decorations are ignored as they are
+// in puzzles, and blanks stand for every candidate, so a puzzle is already a
+// valid save.
+
+impl State {
+    // The grid on one line, open cells as their candidates in brackets.
+    pub fn to_marks(&self) -> String {
+        let mut out = String::new();
+        for (row, col, value) in self.iter_cells() {
+            match value {
+                Some(value) => out.push(digit_char(value)),
+                None => {
+                    out.push('[');
+                    out.extend(self.candidates(row, col).map(digit_char));
+                    out.push(']');
+                }
+            }
+        }
+
+        out
+    }
+
+    // Reads a grid written by `to_marks`, working out the size from the
+    // number of cells.
+    pub fn parse_marks(value: &str) -> Result<Self, SolveError> {
+        let cells = marked_cells(&parse::normalize(value))?;
+        let size = (1..=cells.len())
+            .find(|n| n * n >= cells.len())
+            .unwrap_or(0);
+        if size == 0 || size * size != cells.len() {
+            return Err(SolveError::InvalidInput(format!(
+                "grid has {} cells, which is not a square number",
+                cells.len()
+            )));
+        }
+        if size > MAX_SIZE {
+            return Err(SolveError::InvalidInput(format!(
+                "grid size must be between 1 and {MAX_SIZE}"
+            )));
+        }
+
+        let full = ((1 << size) - 1) << 1;
+        let mut masks = Vec::with_capacity(cells.len());
+        for (idx, cell) in cells.into_iter().enumerate() {
+            let mask = match cell {
+                Marked::Blank => full,
+                Marked::Candidates(mask) if mask & !full != 0 => {
+                    return Err(SolveError::InvalidInput(format!(
+                        "r{}c{} has a candidate too large for a grid of size {size}",
+                        idx / size + 1,
+                        idx % size + 1
+                    )))
+                }
+                Marked::Candidates(0) => {
+                    return Err(SolveError::InvalidInput(format!(
+                        "r{}c{} has no candidates",
+                        idx / size + 1,
+                        idx % size + 1
+                    )))
+                }
+                Marked::Candidates(mask) => mask,
+            };
+            masks.push(mask);
+        }
+
+        // Determined cells are taken as given, as they are in puzzles.
+        let values: Vec<u8> = masks
+            .iter()
+            .map(|m| match m.is_power_of_two() {
+                true => m.trailing_zeros() as u8,
+                false => 0,
+            })
+            .collect();
+        let mut state = State::from_values(&values);
+        state.set_masks(&masks);
+
+        Ok(state)
+    }
+}
+
+enum Marked {
+    Blank,
+    Candidates(u32),
+}
+
+// Splits normalized input into cells: single digits, blanks and bracketed
+// candidate lists.
+fn marked_cells(value: &str) -> Result<Vec<Marked>, SolveError> {
+    let mut cells = vec![];
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        let cell = match c {
+            '[' => {
+                let mut mask = 0;
+                loop {
+                    match chars.next() {
+                        Some(']') => break,
+                        Some(c) => mask |= 1 << digit(c)?,
+                        None => {
+                            return Err(SolveError::InvalidInput(
+                                "unclosed '[' in pencil marks".into(),
+                            ))
+                        }
+                    }
+                }
+                Marked::Candidates(mask)
+            }
+            c => match digit_value(c) {
+                Some(0) => Marked::Blank,
+                Some(_) => Marked::Candidates(1 << digit(c)?),
+                None => {
+                    return Err(SolveError::InvalidInput(format!(
+                        "invalid character '{c}' in pencil marks"
+                    )))
+                }
+            },
+        };
+        cells.push(cell);
+    }
+
+    Ok(cells)
+}
+
+fn digit(c: char) -> Result<u8, SolveError> {
+    match digit_value(c) {
+        Some(v) if v > 0 && (v as usize) <= MAX_SIZE => Ok(v),
+        _ => Err(SolveError::InvalidInput(format!(
+            "invalid candidate '{c}' in pencil marks"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::state::State;
+    use crate::strategies::{self, Technique};
+
+    #[test]
+    fn can_round_trip_marks() {
+        let mut state = State::from("1000000000000000");
+        state.propagate_constraints().unwrap();
+        let marks = state.to_marks();
+        assert!(marks.starts_with("1[234][234][234][234][234][1234]"));
+
+        let loaded = State::parse_marks(&marks).unwrap();
+        assert_eq!(loaded.to_marks(), marks);
+        assert_eq!(State::from(marks.as_str()).to_marks(), marks);
+
+        let puzzle =
+            "000030007480960501063570820009610203350097006000005094000000005804706910001040070";
+        let mut state = State::from(puzzle);
+        strategies::solve_logically_upto(&mut state, Technique::NakedSingle, &mut |_| {}).unwrap();
+        let loaded: State = state.to_marks().parse().unwrap();
+        assert_eq!(loaded.snapshot(), state.snapshot());
+        assert_eq!(State::parse_marks(puzzle).unwrap().to_string(), puzzle);
+    }
+
+    #[test]
+    fn can_reject_invalid_marks() {
+        assert!(State::parse_marks("1[234]00").is_err());
+        assert!(State::parse_marks("1[23400000000000").is_err());
+        assert!(State::parse_marks("1[]00000000000000").is_err());
+        assert!(State::parse_marks("1[25]00000000000000").is_err());
+        assert!(State::parse_marks("1[2x]00000000000000").is_err());
+    }
+}
@@ -0,0 +1,82 @@
+use crate::state::{SolveError, State};
+
+impl State {
+    // Whether the puzzle has a unique solution that removing any one of its
+    // givens would lose.
+    pub fn is_minimal(&self) -> bool {
+        let values = self.given_values();
+
+        self.has_unique_solution()
+            && (0..values.len())
+                .filter(|i| values[*i] != 0)
+                .all(|i| !self.without_given(&values, i).has_unique_solution())
+    }
+
+    // Removes givens in order for as long as the solution stays unique. A
+    // given kept once stays needed as more are removed, so a single pass
+    // leaves a minimal puzzle.
+    pub fn minimize(&self) -> Result<State, SolveError> {
+        match self.count_solutions(2) {
+            0 => return Err(SolveError::Unsolvable),
+            1 => {}
+            _ => return Err(SolveError::MultipleSolutions),
+        }
+
+        let mut values = self.given_values();
+        for i in 0..values.len() {
+            if values[i] != 0 && self.without_given(&values, i).has_unique_solution() {
+                values[i] = 0;
+            }
+        }
+
+        Ok(self.with_values(&values))
+    }
+
+    fn without_given(&self, values: &[u8], idx: usize) -> State {
+        let mut values = values.to_vec();
+        values[idx] = 0;
+
+        self.with_values(&values)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::state::{SolveError, State};
+
+    #[test]
+    fn can_check_minimality() {
+        let seventeen = State::from(
+            "000000010400000000020000000000050407008000300001090000300400200050100000000806000",
+        );
+        assert!(seventeen.is_minimal());
+
+        let easy = State::from(
+            "301086504046521070500000001400800002080347900009050038004090200008734090007208103",
+        );
+        assert!(!easy.is_minimal());
+        assert!(!State::from(&"0".repeat(81) as &str).is_minimal());
+    }
+
+    #[test]
+    fn can_minimize() {
+        let easy = State::from(
+            "301086504046521070500000001400800002080347900009050038004090200008734090007208103",
+        );
+        let minimized = easy.minimize().unwrap();
+
+        assert!(minimized.is_minimal());
+        assert!(minimized.filled_count() < easy.filled_count());
+        let mut a = easy.clone();
+        let mut b = minimized.clone();
+        a.solve().unwrap();
+        b.solve().unwrap();
+        assert_eq!(format!("{a}"), format!("{b}"));
+        assert_eq!(a.minimize().unwrap().to_string(), minimized.to_string());
+
+        assert_eq!(
+            State::from(&"0".repeat(16) as &str).minimize().unwrap_err(),
+            SolveError::MultipleSolutions
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use crate::state::digit_char;
+use alloc::string::String;
+use alloc::vec::Vec;
+// Strips the whitespace, row breaks and grid decorations found in puzzles
+// pasted from websites or from `--output-format grid`, and maps every blank
+// marker to `.`, leaving one character per cell. Input with commas or tabs,
+// or nested arrays, is read as delimited fields instead, see
+// `normalize_fields`.
+pub fn normalize(input: &str) -> String {
+    if input.contains([',', '\t']) || is_nested_array(input) {
+        return normalize_fields(input);
+    }
+
+    input
+        .chars()
+        .filter(|c| !is_separator(*c))
+        .map(|c| match c {
+            '_' | '·' | '*' => '.',
+            c => c,
+        })
+        .collect()
+}
+
+// Reads a puzzle pasted from a spreadsheet or a program, with one field per
+// cell: comma or tab separated rows, or nested arrays such as
+// `[[5,3,0,...],...]`, or `[[5 3 0 ...] ...]` as numpy prints them. Fields
+// may be quoted or hold numbers above 9, and empty fields, `0` and `null` or
+// `None` are blanks.
+fn normalize_fields(input: &str) -> String {
+    let rows: Vec<&str> = match input.contains('[') {
+        // the innermost brackets hold the rows, so the commas between rows
+        // don't make empty fields
+        true => input
+            .split('[')
+            .filter_map(|part| part.split_once(']').map(|(row, _)| row))
+            .collect(),
+        false => input.lines().collect(),
+    };
+
+    rows.iter()
+        .flat_map(|row| match row.contains([',', '\t']) {
+            true => row.split([',', '\t']).collect::<Vec<_>>(),
+            false => row.split_whitespace().collect(),
+        })
+        .map(|field| field.trim().trim_matches(['"', '\'']).trim())
+        .filter(|field| field.is_empty() || !field.chars().all(is_separator))
+        .map(|field| match field.parse::<u8>() {
+            Ok(0) => '.',
+            Ok(n) if n < 36 => digit_char(n),
+            _ => match field {
+                "" | "null" | "None" | "_" | "·" | "*" | "." => '.',
+                field if field.chars().count() == 1 => field.chars().next().unwrap_or('.'),
+                // left for the parser to reject
+                _ => '?',
+            },
+        })
+        .collect()
+}
+
+// Whether `input` is an array of rows, such as `[[5,3,0,...],...]`. Pencil
+// marks are bracketed too, but their brackets never nest.
+pub(crate) fn is_nested_array(input: &str) -> bool {
+    input.trim_start().starts_with('[') && input.trim_start()[1..].trim_start().starts_with('[')
+}
+
+fn is_separator(c: char) -> bool {
+    c.is_whitespace()
+        || matches!(c, '|' | '-' | '+' | '=')
+        || ('\u{2500}'..='\u{257F}').contains(&c)
+}
+
+#[cfg(test)]
+mod test {
+    use super::normalize;
+    use crate::format::OutputFormat;
+    use crate::state::State;
+
+    #[test]
+    fn can_normalize_pasted_grid() {
+        let pasted = "
+            3 . 1 | . 8 6 | 5 . 4
+            . 4 6 | 5 2 1 | . 7 .
+            5 . . | . . . | . . 1
+            ------+-------+------
+            4 . . | 8 . . | . . 2
+            . 8 . | 3 4 7 | 9 . .
+            . . 9 | . 5 . | . 3 8
+            ------+-------+------
+            . . 4 | . 9 . | 2 . .
+            . . 8 | 7 3 4 | . 9 .
+            _ _ 7 | 2 _ 8 | 1 _ 3
+        ";
+
+        assert_eq!(
+            normalize(pasted).replace('.', "0"),
+            "301086504046521070500000001400800002080347900009050038004090200008734090007208103"
+        );
+    }
+
+    #[test]
+    fn can_normalize_delimited_fields() {
+        let puzzle = "1000002000030000";
+        for pasted in [
+            "1,0,0,0\n0,0,2,0\n0,0,0,3\n0,0,0,0",
+            "1,,,\n,,2,\n,,,3\n,,,",
+            "1\t\t\t\n\t\t2\t\n\t\t\t3\n\t\t\t",
+            "[[1, 0, 0, 0], [0, 0, 2, 0], [0, 0, 0, 3], [0, 0, 0, 0]]",
+            "[\n  [1,0,0,0],\n  [0,0,2,0],\n  [0,0,0,3],\n  [0,0,0,0]\n]",
+            "[[1 0 0 0] [0 0 2 0] [0 0 0 3] [0 0 0 0]]",
+            "[[1 0 0 0]\n [0 0 2 0]\n [0 0 0 3]\n [0 0 0 0]]",
+            "[1, null, null, null, null, null, 2, 0, 0, 0, 0, 3, 0, 0, 0, 0]",
+            "\"1\",\"\",\"\",\"\"\n\"\",\"\",\"2\",\"\"\n,,,3\n,,,",
+        ] {
+            assert_eq!(State::from(pasted).to_string(), puzzle, "{pasted}");
+        }
+
+        let sixteen = (1..=16).map(|n| n.to_string()).collect::<Vec<_>>();
+        let row = sixteen.join(",");
+        let grid = State::with_size(&format!("{row}\n{}", "0,".repeat(239)), 16);
+        assert_eq!(grid.get(0, 15), Some(16));
+        assert_eq!(grid.get(1, 0), None);
+        let numpy = format!(
+            "[[{}]\n{}]",
+            sixteen.join(" "),
+            " [0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0]\n".repeat(15)
+        );
+        assert_eq!(State::from(numpy.as_str()).get(0, 15), Some(16));
+
+        assert!("1,0,0,0,x,0,0,0,0,0,0,0,0,0,0,0".parse::<State>().is_err());
+        assert!("1,0,0,0,99,0,0,0,0,0,0,0,0,0,0,0".parse::<State>().is_err());
+        assert!("1,0,0,0,ab,0,0,0,0,0,0,0,0,0,0,0".parse::<State>().is_err());
+    }
+
+    #[test]
+    fn can_round_trip_grid_output() {
+        let puzzle =
+            "301086504046521070500000001400800002080347900009050038004090200008734090007208103";
+        let grid = State::from(puzzle).format(OutputFormat::Grid);
+
+        assert_eq!(State::from(grid.as_str()).to_string(), puzzle);
+    }
+}
@@ -0,0 +1,223 @@
+use crate::render;
+use crate::state::{digit_char, SolveError, State};
+use std::fmt::Write;
+
+// An A4 page, in points.
+const PAGE_WIDTH: f64 = 595.0;
+const PAGE_HEIGHT: f64 = 842.0;
+const MARGIN: f64 = 40.0;
+const LABEL_HEIGHT: f64 = 20.0;
+const MAX_PER_PAGE: usize = 12;
+
+// Lays out `puzzles`, each with a label such as "1. hard", `per_page` to a
+// page and followed by pages of their solutions, as a printable PDF.
+pub fn worksheet(puzzles: &[(State, String)], per_page: usize) -> Result<Vec<u8>, SolveError> {
+    if !(1..=MAX_PER_PAGE).contains(&per_page) {
+        return Err(SolveError::InvalidInput(format!(
+            "puzzles per page must be between 1 and {MAX_PER_PAGE}"
+        )));
+    }
+
+    let mut solutions = vec![];
+    for (puzzle, _) in puzzles {
+        let mut solution = puzzle.clone();
+        solution.solve()?;
+        solutions.push(solution);
+    }
+
+    let mut pages = vec![];
+    for chunk in puzzles.chunks(per_page) {
+        let grids: Vec<_> = chunk.iter().map(|(p, label)| (p, None, label)).collect();
+        pages.push(page(&grids, per_page));
+    }
+    let answers: Vec<_> = puzzles
+        .iter()
+        .zip(&solutions)
+        .map(|((p, label), s)| (p, Some(s), label))
+        .collect();
+    for chunk in answers.chunks(MAX_PER_PAGE) {
+        pages.push(page(chunk, MAX_PER_PAGE));
+    }
+
+    Ok(document(&pages))
+}
+
+// Draws a page of grids in a layout with room for `slots` of them, each with
+// its label above it and, if given, the digits of its solution in grey.
+fn page(grids: &[(&State, Option<&State>, &String)], slots: usize) -> String {
+    // as many columns as keeps the slots close to square on a portrait page
+    let cols = ((slots as f64 * PAGE_WIDTH / PAGE_HEIGHT).sqrt().round() as usize).max(1);
+    let rows = slots.div_ceil(cols);
+    let slot_w = (PAGE_WIDTH - 2.0 * MARGIN) / cols as f64;
+    let slot_h = (PAGE_HEIGHT - 2.0 * MARGIN) / rows as f64;
+    let side = slot_w.min(slot_h - LABEL_HEIGHT) * 0.9;
+
+    let mut out = String::new();
+    for (i, (puzzle, solution, label)) in grids.iter().enumerate() {
+        let left = MARGIN + (i % cols) as f64 * slot_w + (slot_w - side) / 2.0;
+        let top = PAGE_HEIGHT - MARGIN - (i / cols) as f64 * slot_h - LABEL_HEIGHT;
+        let _ = writeln!(
+            out,
+            "0 g BT /F2 11 Tf {left:.2} {:.2} Td ({}) Tj ET",
+            top + 6.0,
+            escape(label)
+        );
+        grid(&mut out, puzzle, *solution, left, top, side);
+    }
+
+    out
+}
+
+// Draws a grid `side` points wide with its top left corner at `(left, top)`.
+fn grid(
+    out: &mut String,
+    puzzle: &State,
+    solution: Option<&State>,
+    left: f64,
+    top: f64,
+    side: f64,
+) {
+    let size = puzzle.size();
+    let cell = side / size as f64;
+    let font = cell * 0.6;
+    let givens = puzzle.given_values();
+    let solved = solution.unwrap_or(puzzle).values();
+
+    for idx in 0..size * size {
+        let (value, style) = match (givens[idx], solved[idx]) {
+            (0, v) if v != 0 => (v, "0.45 g /F1"),
+            (0, _) => continue,
+            (v, _) => (v, "0 g /F2"),
+        };
+
+        // Helvetica digits are 0.556 em wide
+        let x = left + ((idx % size) as f64 + 0.5) * cell - font * 0.278;
+        let y = top - ((idx / size) as f64 + 0.5) * cell - font * 0.35;
+        let _ = writeln!(
+            out,
+            "{style} {font:.2} Tf BT {x:.2} {y:.2} Td ({}) Tj ET",
+            escape(&digit_char(value).to_string())
+        );
+    }
+
+    let _ = writeln!(out, "0 G");
+    for ((col1, row1), (col2, row2), thick) in render::edges(puzzle) {
+        let _ = writeln!(
+            out,
+            "{} w {:.2} {:.2} m {:.2} {:.2} l S",
+            if thick { 2.0 } else { 0.5 },
+            left + col1 as f64 * cell,
+            top - row1 as f64 * cell,
+            left + col2 as f64 * cell,
+            top - row2 as f64 * cell
+        );
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('(', "\\(")
+        .replace(')', "\\)")
+}
+
+// Wraps page content streams into a PDF using the standard Helvetica fonts,
+// which every reader has, so nothing needs embedding.
+fn document(pages: &[String]) -> Vec<u8> {
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        String::new(),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string(),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold >>".to_string(),
+    ];
+
+    let mut kids = vec![];
+    for content in pages {
+        let id = objects.len() + 1;
+        kids.push(format!("{id} 0 R"));
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {PAGE_WIDTH} {PAGE_HEIGHT}] \
+             /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+            id + 1
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{content}\nendstream",
+            content.len()
+        ));
+    }
+    objects[1] = format!(
+        "<< /Type /Pages /Kids [{}] /Count {} >>",
+        kids.join(" "),
+        pages.len()
+    );
+
+    let mut out = String::from("%PDF-1.4\n");
+    let mut offsets = vec![];
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        let _ = write!(out, "{} 0 obj\n{object}\nendobj\n", i + 1);
+    }
+
+    let xref = out.len();
+    let _ = write!(out, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        let _ = writeln!(out, "{offset:010} 00000 n ");
+    }
+    let _ = write!(
+        out,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+        objects.len() + 1
+    );
+
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{escape, worksheet};
+    use crate::state::{SolveError, State};
+
+    const PUZZLE: &str =
+        "301086504046521070500000001400800002080347900009050038004090200008734090007208103";
+
+    fn puzzles(count: usize) -> Vec<(State, String)> {
+        (1..=count)
+            .map(|i| (State::from(PUZZLE), format!("{i}. easy")))
+            .collect()
+    }
+
+    #[test]
+    fn can_lay_out_worksheet() {
+        let pdf = String::from_utf8(worksheet(&puzzles(3), 2).unwrap()).unwrap();
+
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        // two pages of puzzles and one of answers
+        assert!(pdf.contains("/Count 3"));
+        assert_eq!(pdf.matches("(3. easy) Tj").count(), 2);
+    }
+
+    #[test]
+    fn can_index_objects() {
+        let pdf = String::from_utf8(worksheet(&puzzles(1), 1).unwrap()).unwrap();
+        let xref = pdf.rfind("\nxref\n").unwrap() + 1;
+        let startxref: usize = pdf.lines().rev().nth(1).unwrap().parse().unwrap();
+        assert_eq!(startxref, xref);
+
+        for (i, entry) in pdf[xref..].lines().skip(3).take(8).enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(&format!("{} 0 obj", i + 1)));
+        }
+    }
+
+    #[test]
+    fn can_reject_bad_worksheets() {
+        assert!(worksheet(&puzzles(1), 0).is_err());
+
+        let unsolvable = vec![(State::from("1100000000000000"), "1".to_string())];
+        assert!(matches!(
+            worksheet(&unsolvable, 1),
+            Err(SolveError::Contradiction { .. })
+        ));
+        assert_eq!(escape("a (b)"), "a \\(b\\)");
+    }
+}
@@ -0,0 +1,346 @@
+use crate::solver::Solver;
+use crate::state::{digit_char, digit_value, SolveError, State};
+use crate::trace::cell_name;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::io;
+
+// A puzzle being played: the digits entered so far, pencil marks and the
+// solution they are checked against.
+#[derive(Debug, Clone)]
+pub struct Game {
+    puzzle: State,
+    solution: Vec<u8>,
+    values: Vec<u8>,
+    marks: Vec<u32>,
+    cursor: usize,
+    pencil: bool,
+    checked: bool,
+    message: String,
+}
+
+impl Game {
+    pub fn new(puzzle: State) -> Result<Self, SolveError> {
+        let mut solution = puzzle.clone();
+        solution.solve()?;
+
+        Ok(Game {
+            values: puzzle.values(),
+            marks: vec![0; puzzle.values().len()],
+            solution: solution.values(),
+            puzzle,
+            cursor: 0,
+            pencil: false,
+            checked: false,
+            message: String::new(),
+        })
+    }
+
+    fn size(&self) -> usize {
+        self.puzzle.size()
+    }
+
+    pub fn values(&self) -> &[u8] {
+        &self.values
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    // Moves the cursor, wrapping around the edges of the grid.
+    pub fn move_cursor(&mut self, rows: isize, cols: isize) {
+        let size = self.size() as isize;
+        let row = (self.cursor as isize / size + rows).rem_euclid(size);
+        let col = (self.cursor as isize % size + cols).rem_euclid(size);
+        self.cursor = (row * size + col) as usize;
+    }
+
+    pub fn toggle_pencil(&mut self) {
+        self.pencil = !self.pencil;
+    }
+
+    fn is_given(&self, idx: usize) -> bool {
+        self.puzzle.is_given_at(idx)
+    }
+
+    // Writes `val` into the cell under the cursor, or toggles it as a pencil
+    // mark in pencil mode.
+    pub fn enter(&mut self, val: u8) {
+        if self.is_given(self.cursor) {
+            self.message = format!("{} is a given", cell_name(self.cursor, self.size()));
+        } else if self.pencil {
+            self.marks[self.cursor] ^= 1 << val;
+        } else {
+            self.values[self.cursor] = val;
+            self.marks[self.cursor] = 0;
+            self.checked = false;
+            self.message = match self.is_solved() {
+                true => "solved!".to_string(),
+                false => String::new(),
+            };
+        }
+    }
+
+    pub fn clear(&mut self) {
+        if !self.is_given(self.cursor) {
+            self.values[self.cursor] = 0;
+            self.marks[self.cursor] = 0;
+        }
+    }
+
+    // Entered digits that differ from the solution.
+    pub fn mistakes(&self) -> Vec<usize> {
+        (0..self.values.len())
+            .filter(|i| self.values[*i] != 0 && self.values[*i] != self.solution[*i])
+            .collect()
+    }
+
+    pub fn check(&mut self) {
+        let mistakes = self.mistakes().len();
+        self.checked = true;
+        self.message = match mistakes {
+            0 => "no mistakes so far".to_string(),
+            1 => "1 mistake".to_string(),
+            n => format!("{n} mistakes"),
+        };
+    }
+
+    // Describes the next logical step from the digits entered so far and
+    // moves the cursor to the first cell it affects.
+    pub fn hint(&mut self) {
+        if !self.mistakes().is_empty() {
+            self.check();
+            self.message.push_str(", fix them before asking for a hint");
+            return;
+        }
+
+        let state =
+            State::from_values(&self.values).with_constraints(self.puzzle.constraints().clone());
+        self.message = match Solver::new().next_hint(&state) {
+            Some(hint) => {
+                let step = &hint.step;
+                if let Some(idx) = step
+                    .placements
+                    .iter()
+                    .chain(&step.eliminations)
+                    .map(|(idx, _)| *idx)
+                    .next()
+                {
+                    self.cursor = idx;
+                }
+                format!("hint: {hint}")
+            }
+            None => "no logical step found".to_string(),
+        };
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.values == self.solution
+    }
+
+    // Applies a key press, returning false once the player quits.
+    fn handle(&mut self, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        match code {
+            KeyCode::Esc => return false,
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyCode::Up => self.move_cursor(-1, 0),
+            KeyCode::Down => self.move_cursor(1, 0),
+            KeyCode::Left => self.move_cursor(0, -1),
+            KeyCode::Right => self.move_cursor(0, 1),
+            KeyCode::Backspace | KeyCode::Delete | KeyCode::Char('0' | '.') => self.clear(),
+            KeyCode::Char(' ') => self.toggle_pencil(),
+            KeyCode::Char('?') => self.hint(),
+            KeyCode::Char('!') => self.check(),
+            KeyCode::Char(c) => match digit_value(c) {
+                Some(v) if v as usize <= self.size() => self.enter(v),
+                _ => {}
+            },
+            _ => {}
+        }
+
+        true
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let size = self.size();
+        let (box_w, box_h) = self.puzzle.constraints().block_shape();
+        let mistakes = match self.checked {
+            true => self.mistakes(),
+            false => vec![],
+        };
+
+        let mut lines = vec![];
+        for row in 0..size {
+            if row > 0 && row % box_h == 0 {
+                lines.push(Line::from(""));
+            }
+
+            let mut spans = vec![];
+            for col in 0..size {
+                let idx = row * size + col;
+                if col > 0 && col % box_w == 0 {
+                    spans.push(Span::raw("  "));
+                }
+
+                let mut style = Style::default();
+                if self.is_given(idx) {
+                    style = style.add_modifier(Modifier::BOLD);
+                } else if mistakes.contains(&idx) {
+                    style = style.fg(Color::Red);
+                } else {
+                    style = style.fg(Color::Cyan);
+                }
+                if idx == self.cursor {
+                    style = style.add_modifier(Modifier::REVERSED);
+                }
+
+                let c = match self.values[idx] {
+                    0 => '·',
+                    v => digit_char(v),
+                };
+                spans.push(Span::styled(format!("{c} "), style));
+            }
+            lines.push(Line::from(spans));
+        }
+
+        let marks: Vec<String> = (1..=size as u8)
+            .filter(|v| self.marks[self.cursor] & (1 << v) != 0)
+            .map(|v| digit_char(v).to_string())
+            .collect();
+        lines.push(Line::from(""));
+        lines.push(Line::from(format!(
+            "{} {}  marks: {}",
+            cell_name(self.cursor, size),
+            match self.pencil {
+                true => "[pencil]",
+                false => "[pen]",
+            },
+            marks.join(" ")
+        )));
+        lines.push(Line::from(self.message.clone()));
+        lines.push(Line::from(
+            "arrows move, digits enter, space toggles pencil, ? hint, ! check, esc quits",
+        ));
+
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title("sudoku")),
+            frame.area(),
+        );
+    }
+}
+
+// Plays `puzzle` in the terminal until the player quits.
+pub fn play(puzzle: State) -> anyhow::Result<()> {
+    let mut game = Game::new(puzzle)?;
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut game);
+    ratatui::restore();
+
+    Ok(result?)
+}
+
+fn run(terminal: &mut DefaultTerminal, game: &mut Game) -> io::Result<()> {
+    loop {
+        terminal.draw(|frame| game.draw(frame))?;
+
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !game.handle(key.code, key.modifiers) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Game;
+    use crate::state::State;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    const PUZZLE: &str =
+        "000030007480960501063570820009610203350097006000005094000000005804706910001040070";
+
+    #[test]
+    fn can_move_cursor() {
+        let mut game = Game::new(State::from(PUZZLE)).unwrap();
+
+        game.move_cursor(0, -1);
+        assert_eq!(game.cursor(), 8);
+        game.move_cursor(-1, 1);
+        assert_eq!(game.cursor(), 72);
+    }
+
+    #[test]
+    fn can_enter_digits() {
+        let mut game = Game::new(State::from(PUZZLE)).unwrap();
+
+        game.enter(2);
+        assert_eq!(game.values()[0], 2);
+        game.move_cursor(0, 4);
+        game.enter(5);
+        assert_eq!(game.values()[4], 3);
+        assert_eq!(game.message(), "r1c5 is a given");
+
+        game.move_cursor(0, -4);
+        game.toggle_pencil();
+        game.enter(7);
+        assert_eq!(game.values()[0], 2);
+        assert_eq!(game.marks[0], 1 << 7);
+    }
+
+    #[test]
+    fn can_check_mistakes() {
+        let mut game = Game::new(State::from(PUZZLE)).unwrap();
+
+        game.enter(game.solution[0] % 9 + 1);
+        game.move_cursor(0, 1);
+        game.enter(game.solution[1]);
+        game.check();
+        assert_eq!(game.mistakes(), vec![0]);
+        assert_eq!(game.message(), "1 mistake");
+
+        game.hint();
+        assert!(game
+            .message()
+            .ends_with("fix them before asking for a hint"));
+    }
+
+    #[test]
+    fn can_draw_grid() {
+        let game = Game::new(State::from(PUZZLE)).unwrap();
+        let mut terminal = Terminal::new(TestBackend::new(40, 20)).unwrap();
+        terminal.draw(|frame| game.draw(frame)).unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let line: String = (0..40).map(|x| buffer[(x, 1)].symbol()).collect();
+        assert!(line.starts_with("│· · ·   · 3 ·   · · 7 "));
+    }
+
+    #[test]
+    fn can_play_with_hints() {
+        let mut game = Game::new(State::from(PUZZLE)).unwrap();
+
+        while !game.is_solved() {
+            game.hint();
+            let idx = game.cursor();
+            if game.values()[idx] == 0 && game.message().contains('=') {
+                game.enter(game.solution[idx]);
+            } else {
+                // an elimination; fill in the cell it points at instead
+                let idx = (0..81).find(|i| game.values()[*i] == 0).unwrap();
+                game.cursor = idx;
+                game.enter(game.solution[idx]);
+            }
+        }
+        assert_eq!(game.message(), "solved!");
+    }
+}
@@ -0,0 +1,185 @@
+use crate::generator::{self, Symmetry};
+use crate::rating::{self, Difficulty};
+use crate::solver::Solver;
+use crate::state::{PlacementError, SolveError, State};
+use pyo3::exceptions::{PyIndexError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::collections::BTreeMap;
+
+// Bindings for Python, built as a module with maturin. Puzzles are passed as
+// strings in any format `State::from_str` accepts, and errors are raised as
+// `ValueError`s, or `IndexError`s for cells outside the grid. As with the wasm
+// bindings the work is done by plain functions returning `Result`, so it can be
+// tested without an interpreter.
+
+// Solves a puzzle, returning the solution as one line of digits.
+#[pyfunction]
+fn solve(puzzle: &str) -> PyResult<String> {
+    solve_line(puzzle).map_err(value_error)
+}
+
+// Generates a puzzle of the given difficulty and symmetry, e.g. "medium" and
+// "rotational", returning it as one line of digits.
+#[pyfunction]
+#[pyo3(signature = (difficulty, symmetry = "none"))]
+fn generate(difficulty: &str, symmetry: &str) -> PyResult<String> {
+    generate_line(difficulty, symmetry).map_err(value_error)
+}
+
+// Rates a puzzle as a dict of its difficulty, score, hardest technique, step
+// count and the steps taken with each technique.
+#[pyfunction]
+fn rate<'py>(py: Python<'py>, puzzle: &str) -> PyResult<Bound<'py, PyDict>> {
+    let rating = rating::rate(&puzzle.parse().map_err(value_error)?).map_err(value_error)?;
+    let techniques: BTreeMap<String, usize> = rating
+        .techniques
+        .iter()
+        .map(|(technique, count)| (technique.to_string(), *count))
+        .collect();
+
+    let dict = PyDict::new(py);
+    dict.set_item("difficulty", rating.difficulty.to_string())?;
+    dict.set_item("score", rating.score)?;
+    dict.set_item("hardest", rating.hardest.map(|t| t.to_string()))?;
+    dict.set_item("steps", rating.steps)?;
+    dict.set_item("techniques", techniques)?;
+
+    Ok(dict)
+}
+
+// A puzzle being worked on, with rows and columns counting from zero.
+#[pyclass]
+pub struct Grid {
+    state: State,
+}
+
+#[pymethods]
+impl Grid {
+    #[new]
+    fn new(puzzle: &str) -> PyResult<Self> {
+        let state = puzzle.parse().map_err(value_error)?;
+
+        Ok(Grid { state })
+    }
+
+    #[getter]
+    fn size(&self) -> usize {
+        self.state.size()
+    }
+
+    // The digit in a cell, or `None` if it is still open.
+    fn get(&self, row: usize, col: usize) -> PyResult<Option<u8>> {
+        self.check_bounds(row, col)?;
+
+        Ok(self.state.get(row, col))
+    }
+
+    // Widened from `u8`, which PyO3 would turn into `bytes` rather than a list.
+    fn candidates(&self, row: usize, col: usize) -> PyResult<Vec<u32>> {
+        self.check_bounds(row, col)?;
+
+        Ok(self.state.candidates(row, col).map(u32::from).collect())
+    }
+
+    fn set(&mut self, row: usize, col: usize, value: u8) -> PyResult<()> {
+        self.check_bounds(row, col)?;
+
+        self.state.set(row, col, value).map_err(value_error)
+    }
+
+    fn clear(&mut self, row: usize, col: usize) -> PyResult<()> {
+        self.check_bounds(row, col)?;
+        self.state.clear(row, col);
+
+        Ok(())
+    }
+
+    fn solve(&mut self) -> PyResult<()> {
+        self.state.solve().map_err(value_error)
+    }
+
+    fn is_solved(&self) -> bool {
+        self.state.is_solved()
+    }
+
+    #[pyo3(signature = (limit = 2))]
+    fn count_solutions(&self, limit: usize) -> usize {
+        self.state.count_solutions(limit)
+    }
+
+    // The next logical step, described, or `None` if there is none.
+    fn hint(&self) -> Option<String> {
+        Solver::new()
+            .next_hint(&self.state)
+            .map(|hint| hint.to_string())
+    }
+
+    fn __str__(&self) -> String {
+        self.state.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Grid('{}')", self.state)
+    }
+}
+
+impl Grid {
+    fn check_bounds(&self, row: usize, col: usize) -> PyResult<()> {
+        let size = self.state.size();
+        if row >= size || col >= size {
+            let error = PlacementError::OutOfBounds { row, col, size };
+            return Err(PyIndexError::new_err(error.to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+#[pymodule]
+fn sudoku_solver(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(solve, m)?)?;
+    m.add_function(wrap_pyfunction!(generate, m)?)?;
+    m.add_function(wrap_pyfunction!(rate, m)?)?;
+    m.add_class::<Grid>()?;
+
+    Ok(())
+}
+
+fn value_error(error: impl ToString) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
+
+fn solve_line(puzzle: &str) -> Result<String, SolveError> {
+    let mut state: State = puzzle.parse()?;
+    state.solve()?;
+
+    Ok(state.to_string())
+}
+
+fn generate_line(difficulty: &str, symmetry: &str) -> Result<String, String> {
+    let difficulty: Difficulty = difficulty.parse()?;
+    let symmetry: Symmetry = symmetry.parse()?;
+
+    Ok(generator::generate(difficulty, symmetry).to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{generate_line, solve_line};
+    use crate::state::State;
+
+    #[test]
+    fn can_solve_and_generate() {
+        let solution = solve_line(
+            "000030007480960501063570820009610203350097006000005094000000005804706910001040070",
+        )
+        .unwrap();
+        assert!(State::from(solution.as_str()).is_solved());
+        assert!(solve_line("1100000000000000").is_err());
+
+        let puzzle = generate_line("easy", "rotational").unwrap();
+        assert!(State::from(puzzle.as_str()).has_unique_solution());
+        assert!(generate_line("easy", "sideways").is_err());
+    }
+}
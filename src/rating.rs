@@ -0,0 +1,161 @@
+use crate::state::{SolveError, State};
+use crate::strategies::{self, Technique};
+use std::collections::BTreeMap;
+use std::{fmt::Display, str::FromStr};
+
+const GUESS_PENALTY: u32 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+    Expert,
+    Diabolical,
+}
+
+impl Difficulty {
+    fn from_technique(technique: Option<Technique>) -> Self {
+        match technique {
+            None | Some(Technique::NakedSingle) | Some(Technique::HiddenSingle) => Difficulty::Easy,
+            Some(Technique::LockedCandidates)
+            | Some(Technique::NakedPair)
+            | Some(Technique::HiddenPair) => Difficulty::Medium,
+            Some(Technique::NakedTriple) | Some(Technique::HiddenTriple) => Difficulty::Hard,
+            Some(Technique::XWing)
+            | Some(Technique::Swordfish)
+            | Some(Technique::XyWing)
+            | Some(Technique::XyzWing)
+            | Some(Technique::SimpleColoring) => Difficulty::Expert,
+        }
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "easy" => Ok(Difficulty::Easy),
+            "medium" => Ok(Difficulty::Medium),
+            "hard" => Ok(Difficulty::Hard),
+            "expert" => Ok(Difficulty::Expert),
+            "diabolical" => Ok(Difficulty::Diabolical),
+            _ => Err(format!("unknown difficulty: {s}")),
+        }
+    }
+}
+
+impl Display for Difficulty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+            Difficulty::Expert => "expert",
+            Difficulty::Diabolical => "diabolical",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rating {
+    pub difficulty: Difficulty,
+    pub score: u32,
+    pub hardest: Option<Technique>,
+    pub steps: usize,
+    // how many steps used each technique
+    pub techniques: BTreeMap<Technique, usize>,
+}
+
+impl Display for Rating {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (score {}", self.difficulty, self.score)?;
+        match (self.difficulty, self.hardest) {
+            (Difficulty::Diabolical, _) => write!(f, ", requires guessing")?,
+            (_, Some(technique)) => write!(f, ", hardest technique: {technique}")?,
+            (_, None) => {}
+        }
+
+        write!(f, ", {} steps)", self.steps)
+    }
+}
+
+pub fn rate(state: &State) -> Result<Rating, SolveError> {
+    let mut state = state.clone();
+    let mut hardest = None;
+    let mut score = 0;
+    let mut steps = 0;
+    let mut techniques = BTreeMap::new();
+
+    let solved = strategies::solve_logically(&mut state, &mut |step| {
+        hardest = hardest.max(Some(step.technique));
+        score += step.technique.weight();
+        steps += 1;
+        *techniques.entry(step.technique).or_insert(0) += 1;
+    })
+    .map_err(|e| e.in_grid(state.constraints()))?;
+
+    let difficulty = if solved {
+        Difficulty::from_technique(hardest)
+    } else {
+        score += GUESS_PENALTY;
+        Difficulty::Diabolical
+    };
+
+    Ok(Rating {
+        difficulty,
+        score,
+        hardest,
+        steps,
+        techniques,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{rate, Difficulty};
+    use crate::state::State;
+
+    #[test]
+    fn can_rate_easy() {
+        let state = State::from(
+            "301086504046521070500000001400800002080347900009050038004090200008734090007208103",
+        );
+        let rating = rate(&state).unwrap();
+
+        assert_eq!(rating.difficulty, Difficulty::Easy);
+        assert_eq!(rating.steps, 42);
+    }
+
+    #[test]
+    fn can_rate_diabolical() {
+        let state = State::from(
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+        );
+        let rating = rate(&state).unwrap();
+
+        assert_eq!(rating.difficulty, Difficulty::Diabolical);
+        assert!(rating.score >= 500);
+    }
+
+    #[test]
+    fn can_reject_conflicting_puzzle() {
+        let state = State::from(
+            "000040007480960501063570820009610203350097006000005094000000005804706910001040070",
+        );
+
+        assert!(rate(&state).is_err());
+    }
+
+    #[test]
+    fn can_order_difficulties() {
+        assert!(Difficulty::Easy < Difficulty::Hard);
+        assert_eq!(
+            "Diabolical".parse::<Difficulty>(),
+            Ok(Difficulty::Diabolical)
+        );
+    }
+}
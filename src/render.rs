@@ -0,0 +1,213 @@
+use crate::state::{digit_char, State};
+use std::fmt::Write;
+
+const GIVEN_COLOUR: &str = "#000000";
+const SOLVED_COLOUR: &str = "#4a6fa5";
+const MARK_COLOUR: &str = "#777777";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderOptions {
+    // Width and height of a cell, in pixels.
+    pub cell_size: u32,
+    // Whether to write each open cell's candidates in small digits.
+    pub pencil_marks: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            cell_size: 40,
+            pencil_marks: false,
+        }
+    }
+}
+
+// Draws `puzzle` as an SVG image with its givens in bold and any digits solved
+// since drawn lighter. `progress`, such as a solution, adds the digits it has
+// filled in and its candidates for the pencil marks; without it the marks are
+// the digits no given rules out. Lines between blocks, or jigsaw regions, are drawn thicker
+// than lines between cells.
+pub fn svg(puzzle: &State, progress: Option<&State>, options: &RenderOptions) -> String {
+    let size = puzzle.size();
+    let cell = options.cell_size as f64;
+    let margin = cell / 10.0;
+    let width = cell * size as f64 + 2.0 * margin;
+    let (box_w, box_h) = puzzle.constraints().block_shape();
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{width}" viewBox="0 0 {width} {width}" font-family="sans-serif">"#
+    );
+    let _ = writeln!(
+        out,
+        r#"<rect width="{width}" height="{width}" fill="white"/>"#
+    );
+
+    let givens = puzzle.given_values();
+    let solved = progress.unwrap_or(puzzle).values();
+    let marks = |idx: usize| match progress {
+        Some(progress) => progress.candidates_at(idx),
+        None => (1..=size as u8)
+            .filter(|v| {
+                let peers = puzzle.constraints().get_constrained_inds(idx);
+                peers.iter().all(|p| givens[*p] != *v)
+            })
+            .collect(),
+    };
+    for idx in 0..size * size {
+        let (x, y) = (
+            margin + (idx % size) as f64 * cell,
+            margin + (idx / size) as f64 * cell,
+        );
+        let text = |out: &mut String, x: f64, y: f64, font: f64, style: &str, digit: u8| {
+            let _ = writeln!(
+                out,
+                r#"<text x="{x}" y="{}" font-size="{font}" text-anchor="middle" {style}>{}</text>"#,
+                y + font * 0.35,
+                digit_char(digit)
+            );
+        };
+
+        match (givens[idx], solved[idx]) {
+            (0, v) if v != 0 => text(
+                &mut out,
+                x + cell / 2.0,
+                y + cell / 2.0,
+                cell * 0.6,
+                &format!(r#"fill="{SOLVED_COLOUR}""#),
+                v,
+            ),
+            (0, _) if options.pencil_marks => {
+                let font = cell / (box_w.max(box_h) as f64 + 1.0);
+                for v in marks(idx) {
+                    let (col, row) = ((v as usize - 1) % box_w, (v as usize - 1) / box_w);
+                    text(
+                        &mut out,
+                        x + cell * (col as f64 + 0.5) / box_w as f64,
+                        y + cell * (row as f64 + 0.5) / box_h as f64,
+                        font,
+                        &format!(r#"fill="{MARK_COLOUR}""#),
+                        v,
+                    );
+                }
+            }
+            (0, _) => {}
+            (v, _) => text(
+                &mut out,
+                x + cell / 2.0,
+                y + cell / 2.0,
+                cell * 0.6,
+                &format!(r#"fill="{GIVEN_COLOUR}" font-weight="bold""#),
+                v,
+            ),
+        }
+    }
+
+    let at = |i: usize| margin + i as f64 * cell;
+    for ((col1, row1), (col2, row2), thick) in edges(puzzle) {
+        let _ = writeln!(
+            out,
+            r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="black" stroke-width="{}" stroke-linecap="square"/>"#,
+            at(col1),
+            at(row1),
+            at(col2),
+            at(row2),
+            if thick { 3 } else { 1 }
+        );
+    }
+
+    out.push_str("</svg>\n");
+    out
+}
+
+// A cell edge as the `(col, row)` corners it joins, and whether it lies
+// between blocks or around the grid and so should be drawn thick.
+pub(crate) type Edge = ((usize, usize), (usize, usize), bool);
+
+pub(crate) fn edges(state: &State) -> Vec<Edge> {
+    let size = state.size();
+    let mut block = vec![0; size * size];
+    for (b, cells) in state.constraints().blocks().iter().enumerate() {
+        for idx in cells {
+            block[*idx] = b;
+        }
+    }
+
+    let mut edges = vec![];
+    for row in 0..=size {
+        for col in 0..size {
+            let thick =
+                row == 0 || row == size || block[(row - 1) * size + col] != block[row * size + col];
+            edges.push(((col, row), (col + 1, row), thick));
+        }
+    }
+    for col in 0..=size {
+        for row in 0..size {
+            let thick =
+                col == 0 || col == size || block[row * size + col - 1] != block[row * size + col];
+            edges.push(((col, row), (col, row + 1), thick));
+        }
+    }
+
+    edges
+}
+
+#[cfg(test)]
+mod test {
+    use super::{svg, RenderOptions};
+    use crate::state::State;
+
+    #[test]
+    fn can_render_solution() {
+        let puzzle = State::from("1000002000030000");
+        let mut solution = puzzle.clone();
+        solution.solve().unwrap();
+        let image = svg(&puzzle, Some(&solution), &RenderOptions::default());
+
+        assert!(image.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="168""#));
+        assert_eq!(image.matches("font-weight=\"bold\"").count(), 3);
+        assert_eq!(image.matches("<text").count(), 16);
+        // 40 cell edges, of which the 16 around the grid and 8 between blocks
+        // are thick
+        assert_eq!(image.matches("<line").count(), 40);
+        assert_eq!(image.matches(r#"stroke-width="3""#).count(), 24);
+        assert!(image.ends_with("</svg>\n"));
+    }
+
+    #[test]
+    fn can_render_pencil_marks() {
+        let puzzle = State::from("1000000000000000");
+        let options = RenderOptions {
+            pencil_marks: true,
+            ..RenderOptions::default()
+        };
+        let image = svg(&puzzle, None, &options);
+
+        // 7 peers with three candidates left and 8 other cells with four
+        assert_eq!(image.matches("<text").count(), 1 + 7 * 3 + 8 * 4);
+        assert_eq!(
+            svg(&puzzle, None, &RenderOptions::default())
+                .matches("<text")
+                .count(),
+            1
+        );
+
+        let mut progress = puzzle.clone();
+        progress.propagate_constraints().unwrap();
+        progress.collapse(1, 2);
+        let image = svg(&puzzle, Some(&progress), &options);
+        assert_eq!(image.matches(super::SOLVED_COLOUR).count(), 1);
+        assert_eq!(image.matches(super::MARK_COLOUR).count(), 6 * 3 + 8 * 4);
+    }
+
+    #[test]
+    fn can_render_jigsaw_regions() {
+        let puzzle = State::from("0000000000000000")
+            .with_regions("1112 1322 3342 3444")
+            .unwrap();
+        let image = svg(&puzzle, None, &RenderOptions::default());
+
+        assert_eq!(image.matches(r#"stroke-width="3""#).count(), 16 + 12);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::analysis;
+use crate::solver::Solver;
+use crate::state::{digit_char, State};
+use crate::trace::cell_name;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+// Repairs touching more givens than this are not searched for, since each
+// extra given multiplies the work by the number of givens.
+const MAX_EDITS: usize = 2;
+
+// Solutions enumerated once givens are dropped. Changes picked out of a
+// partial list are checked again before they are suggested.
+const MAX_ENUMERATED: usize = 64;
+
+// A change to one given of a puzzle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    Remove { cell: usize, value: u8 },
+    Change { cell: usize, from: u8, to: u8 },
+}
+
+// The fewest edits to a puzzle's givens that leave it with a unique
+// solution, and the puzzle once they are made.
+#[derive(Debug, Clone)]
+pub struct Repair {
+    pub edits: Vec<Edit>,
+    pub puzzle: State,
+}
+
+impl Display for Repair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let size = self.puzzle.size();
+        if self.edits.is_empty() {
+            return write!(f, "puzzle already has a unique solution");
+        }
+
+        let edits: Vec<String> = self
+            .edits
+            .iter()
+            .map(|edit| match *edit {
+                Edit::Remove { cell, value } => {
+                    format!(
+                        "remove {} from {}",
+                        digit_char(value),
+                        cell_name(cell, size)
+                    )
+                }
+                Edit::Change { cell, from, to } => format!(
+                    "change {} from {} to {}",
+                    cell_name(cell, size),
+                    digit_char(from),
+                    digit_char(to)
+                ),
+            })
+            .collect();
+        write!(f, "{}", edits.join("\n"))
+    }
+}
+
+// Finds the fewest givens to remove or change, up to `MAX_EDITS`, that make
+// a broken puzzle, with no solution or several, uniquely solvable. Of
+// repairs touching the same givens, the one changing fewest digits is
+// preferred. Returns `None` if no such repair was found; a puzzle with
+// several solutions often needs clues added instead.
+pub fn repair(puzzle: &State) -> Option<Repair> {
+    if puzzle.has_unique_solution() {
+        return Some(Repair {
+            edits: vec![],
+            puzzle: puzzle.clone(),
+        });
+    }
+
+    let values = puzzle.given_values();
+    let givens: Vec<usize> = (0..values.len()).filter(|i| values[*i] != 0).collect();
+    (1..=MAX_EDITS).find_map(|n| {
+        analysis::combinations(&givens, n).find_map(|cells| repair_cells(puzzle, &values, &cells))
+    })
+}
+
+// Tries every way of removing or changing all of `cells`, by dropping them
+// and looking for digits for some of them that only one solution of what is
+// left has.
+fn repair_cells(puzzle: &State, values: &[u8], cells: &[usize]) -> Option<Repair> {
+    let mut dropped = values.to_vec();
+    for cell in cells {
+        dropped[*cell] = 0;
+    }
+    let solutions: Vec<Vec<u8>> = Solver::new()
+        .solutions(&puzzle.with_values(&dropped))
+        .take(MAX_ENUMERATED)
+        .map(|s| s.values())
+        .collect();
+
+    // each subset of `cells` to change rather than remove, fewest first
+    let mut subsets: Vec<u32> = (0..1 << cells.len()).collect();
+    subsets.sort_by_key(|s| s.count_ones());
+    subsets.into_iter().find_map(|subset| {
+        let changed: Vec<usize> = (0..cells.len())
+            .filter(|i| subset & 1 << i != 0)
+            .map(|i| cells[i])
+            .collect();
+
+        let mut groups: BTreeMap<Vec<u8>, usize> = BTreeMap::new();
+        for solution in &solutions {
+            let digits = changed.iter().map(|c| solution[*c]).collect();
+            *groups.entry(digits).or_insert(0) += 1;
+        }
+
+        groups
+            .into_iter()
+            .filter(|(digits, count)| {
+                *count == 1 && changed.iter().zip(digits).all(|(c, d)| values[*c] != *d)
+            })
+            .find_map(|(digits, _)| {
+                let mut repaired = dropped.clone();
+                for (cell, digit) in changed.iter().zip(&digits) {
+                    repaired[*cell] = *digit;
+                }
+                let repaired = puzzle.with_values(&repaired);
+                repaired.has_unique_solution().then(|| Repair {
+                    edits: cells
+                        .iter()
+                        .map(|cell| match changed.iter().position(|c| c == cell) {
+                            Some(i) => Edit::Change {
+                                cell: *cell,
+                                from: values[*cell],
+                                to: digits[i],
+                            },
+                            None => Edit::Remove {
+                                cell: *cell,
+                                value: values[*cell],
+                            },
+                        })
+                        .collect(),
+                    puzzle: repaired,
+                })
+            })
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{repair, Edit};
+    use crate::state::State;
+
+    const PUZZLE: &str =
+        "301086504046521070500000001400800002080347900009050038004090200008734090007208103";
+
+    #[test]
+    fn can_repair_conflicting_givens() {
+        // a second 3 in the first row
+        let broken = State::from(PUZZLE.replacen("301", "331", 1).as_str());
+        let repaired = repair(&broken).unwrap();
+
+        assert_eq!(repaired.edits, vec![Edit::Remove { cell: 1, value: 3 }]);
+        assert!(repaired.puzzle.has_unique_solution());
+        assert_eq!(repaired.to_string(), "remove 3 from r1c2");
+
+        assert!(repair(&State::from(PUZZLE)).unwrap().edits.is_empty());
+    }
+
+    #[test]
+    fn can_repair_wrong_givens() {
+        // 9 given where the solution has 7, which leaves no solution unless
+        // it is changed back, as the puzzle needs the clue
+        let minimal = State::from(
+            "000000500040021000500000001400000002080047000009000038000090200008030090007208103",
+        );
+        assert!(minimal.is_minimal());
+        let mut values = minimal.to_string().into_bytes();
+        values[13] = b'9';
+        let broken = State::from(String::from_utf8(values).unwrap().as_str());
+
+        let repaired = repair(&broken).unwrap();
+        assert_eq!(
+            repaired.edits,
+            vec![Edit::Change {
+                cell: 13,
+                from: 9,
+                to: 2
+            }]
+        );
+        assert_eq!(repaired.puzzle.to_string(), minimal.to_string());
+    }
+}
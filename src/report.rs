@@ -0,0 +1,99 @@
+use crate::solver::Solver;
+use crate::state::State;
+use crate::strategies::{self, Technique};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum SolveStatus {
+    Solved,
+    Failed,
+}
+
+// Outcome of solving a single puzzle, along with how many times each logical
+// technique fired before the solver had to fall back to search.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SolveReport {
+    pub puzzle: String,
+    pub status: SolveStatus,
+    pub solution: Option<String>,
+    pub error: Option<String>,
+    pub elapsed_us: u128,
+    pub techniques: BTreeMap<Technique, usize>,
+}
+
+impl SolveReport {
+    pub fn new(solver: &Solver, puzzle: &State) -> Self {
+        let mut techniques = BTreeMap::new();
+        let _ = strategies::solve_logically(&mut puzzle.clone(), &mut |step| {
+            *techniques.entry(step.technique).or_insert(0) += 1;
+        });
+
+        let mut state = puzzle.clone();
+        let start = Instant::now();
+        let outcome = solver.solve(&mut state);
+        let elapsed_us = start.elapsed().as_micros();
+
+        let (status, solution, error) = match outcome {
+            Ok(_) => (SolveStatus::Solved, Some(state.to_string()), None),
+            Err(e) => (SolveStatus::Failed, None, Some(e.to_string())),
+        };
+
+        SolveReport {
+            puzzle: puzzle.to_string(),
+            status,
+            solution,
+            error,
+            elapsed_us,
+            techniques,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SolveReport, SolveStatus};
+    use crate::solver::Solver;
+    use crate::state::State;
+    use crate::strategies::Technique;
+
+    #[test]
+    fn can_report_solve() {
+        let puzzle = State::from(
+            "301086504046521070500000001400800002080347900009050038004090200008734090007208103",
+        );
+        let report = SolveReport::new(&Solver::new(), &puzzle);
+
+        assert_eq!(report.status, SolveStatus::Solved);
+        assert!(report.solution.unwrap().starts_with("3719865"));
+        assert!(report.techniques[&Technique::NakedSingle] > 0);
+
+        let report = SolveReport::new(
+            &Solver::new(),
+            &State::from(
+                "110000000000000000000000000000000000000000000000000000000000000000000000000000000",
+            ),
+        );
+        assert_eq!(report.status, SolveStatus::Failed);
+        assert!(report.error.is_some());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn can_serialize_report() {
+        let report = SolveReport::new(&Solver::new(), &State::from("1000002000030000"));
+        let json = serde_json::to_string(&report).unwrap();
+
+        assert!(json.contains(r#""status":"solved""#));
+        assert!(json.contains(r#""techniques":{"naked_single":"#));
+        assert_eq!(serde_json::from_str::<SolveReport>(&json).unwrap(), report);
+    }
+}
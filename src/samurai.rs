@@ -0,0 +1,379 @@
+use crate::parse;
+use crate::state::{digit_char, digit_value, ConstraintError, SolveError, State};
+use crate::strategies::{self, Unit};
+use std::fmt::Display;
+
+// Top-left corners of the five 9×9 grids of a samurai puzzle on its 21×21
+// board: four corner grids, each sharing a block with the centre grid.
+const SAMURAI_GRIDS: [(usize, usize); 5] = [(0, 0), (0, 12), (6, 6), (12, 0), (12, 12)];
+const SAMURAI_SIDE: usize = 21;
+
+// Several grids laid out on one square board. Grids that overlap share the
+// cells they both cover, so each grid propagates its own units and the shared
+// cells carry candidates between them.
+#[derive(Debug, Clone)]
+pub struct MultiGridState {
+    side: usize,
+    offsets: Vec<(usize, usize)>,
+    grids: Vec<State>,
+    // pairs of (grid, index) that are the same board cell
+    shared: Vec<((usize, usize), (usize, usize))>,
+}
+
+impl MultiGridState {
+    // Places grids of `size` digits with their top-left corners at `offsets` on
+    // a board of `side` × `side` cells. `values` holds every board cell row by
+    // row, 0 for blanks; cells outside every grid are ignored.
+    pub fn new(
+        side: usize,
+        size: usize,
+        offsets: &[(usize, usize)],
+        values: &[u8],
+    ) -> Result<Self, SolveError> {
+        if values.len() != side * side {
+            return Err(SolveError::InvalidInput(format!(
+                "board has {} cells but a side of {side} needs {}",
+                values.len(),
+                side * side
+            )));
+        }
+        if let Some((row, col)) = offsets
+            .iter()
+            .find(|(row, col)| row + size > side || col + size > side)
+        {
+            return Err(SolveError::InvalidInput(format!(
+                "grid at r{}c{} does not fit on the board",
+                row + 1,
+                col + 1
+            )));
+        }
+
+        let mut owners: Vec<Vec<(usize, usize)>> = vec![vec![]; side * side];
+        let mut grids = vec![];
+        for (g, (top, left)) in offsets.iter().enumerate() {
+            let mut grid_values = vec![];
+            for row in 0..size {
+                for col in 0..size {
+                    let cell = (top + row) * side + left + col;
+                    owners[cell].push((g, row * size + col));
+                    grid_values.push(values[cell]);
+                }
+            }
+            grids.push(State::parse_with_size(
+                &grid_values.into_iter().map(digit_char).collect::<String>(),
+                size,
+            )?);
+        }
+
+        let shared = owners
+            .iter()
+            .flat_map(|o| o.iter().zip(o.iter().skip(1)).map(|(a, b)| (*a, *b)))
+            .collect();
+
+        Ok(MultiGridState {
+            side,
+            offsets: offsets.to_vec(),
+            grids,
+            shared,
+        })
+    }
+
+    // Reads the 21×21 board of a samurai puzzle, either with every board cell
+    // (blanks for the gaps between corner grids) or with only the 369 cells
+    // covered by a grid, row by row.
+    pub fn samurai(input: &str) -> Result<Self, SolveError> {
+        let input = parse::normalize(input);
+        let values = input
+            .chars()
+            .map(|c| match digit_value(c) {
+                Some(v) if v <= 9 => Ok(v),
+                _ => Err(SolveError::InvalidInput(format!(
+                    "invalid digit '{c}' for a samurai puzzle"
+                ))),
+            })
+            .collect::<Result<Vec<u8>, SolveError>>()?;
+
+        let covered: Vec<usize> = (0..SAMURAI_SIDE * SAMURAI_SIDE)
+            .filter(|cell| {
+                let (row, col) = (cell / SAMURAI_SIDE, cell % SAMURAI_SIDE);
+                SAMURAI_GRIDS.iter().any(|(top, left)| {
+                    (*top..top + 9).contains(&row) && (*left..left + 9).contains(&col)
+                })
+            })
+            .collect();
+
+        let values = if values.len() == covered.len() {
+            let mut board = vec![0; SAMURAI_SIDE * SAMURAI_SIDE];
+            for (cell, value) in covered.iter().zip(values) {
+                board[*cell] = value;
+            }
+            board
+        } else if values.len() == SAMURAI_SIDE * SAMURAI_SIDE {
+            values
+        } else {
+            return Err(SolveError::InvalidInput(format!(
+                "samurai puzzle has {} cells but needs {} or {}",
+                values.len(),
+                covered.len(),
+                SAMURAI_SIDE * SAMURAI_SIDE
+            )));
+        };
+
+        MultiGridState::new(SAMURAI_SIDE, 9, &SAMURAI_GRIDS, &values)
+    }
+
+    pub fn grids(&self) -> &[State] {
+        &self.grids
+    }
+
+    // The value of every board cell row by row, 0 for blanks and gaps.
+    pub fn values(&self) -> Vec<u8> {
+        let mut values = vec![0; self.side * self.side];
+        for ((top, left), grid) in self.offsets.iter().zip(&self.grids) {
+            let size = grid.size();
+            for (idx, value) in grid.values().into_iter().enumerate() {
+                values[(top + idx / size) * self.side + left + idx % size] = value;
+            }
+        }
+
+        values
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.grids.iter().all(|g| g.is_solved())
+    }
+
+    pub fn solve(&mut self) -> Result<(), SolveError> {
+        self.propagate().map_err(|(g, e)| self.locate(g, e))?;
+
+        if !self.is_solved() {
+            let mut solution = None;
+            search(self.clone(), &mut |s| {
+                solution = Some(s.clone());
+                false
+            });
+            *self = solution.ok_or(SolveError::Unsolvable)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn count_solutions(&self, limit: usize) -> usize {
+        let mut count = 0;
+        if limit == 0 {
+            return count;
+        }
+
+        search(self.clone(), &mut |_| {
+            count += 1;
+            count < limit
+        });
+
+        count
+    }
+
+    // Propagates within each grid, then narrows every shared cell to the
+    // candidates both of its grids allow, until the shared cells settle.
+    fn propagate(&mut self) -> Result<(), (usize, ConstraintError)> {
+        loop {
+            for (g, grid) in self.grids.iter_mut().enumerate() {
+                grid.propagate_constraints()
+                    .and_then(|_| strategies::place_hidden_singles(grid))
+                    .map_err(|e| (g, e))?;
+            }
+
+            let masks: Vec<Vec<u32>> = self.grids.iter().map(|g| g.candidate_masks()).collect();
+            let mut changed = false;
+            for ((g1, i1), (g2, i2)) in &self.shared {
+                let mask = masks[*g1][*i1] & masks[*g2][*i2];
+                changed |= self.grids[*g1].restrict(*i1, mask).map_err(|e| (*g1, e))?;
+                changed |= self.grids[*g2].restrict(*i2, mask).map_err(|e| (*g2, e))?;
+            }
+
+            if !changed {
+                return Ok(());
+            }
+        }
+    }
+
+    // Moves an error within grid `g` to board coordinates. Blocks are
+    // numbered within their grid, so only a row or column is kept.
+    fn locate(&self, g: usize, error: ConstraintError) -> SolveError {
+        let (top, left) = self.offsets[g];
+        match error.in_grid(self.grids[g].constraints()) {
+            SolveError::Contradiction {
+                row,
+                col,
+                value,
+                unit,
+            } => SolveError::Contradiction {
+                row: top + row,
+                col: left + col,
+                value,
+                unit: match unit {
+                    Some(Unit::Row(r)) => Some(Unit::Row(top + r)),
+                    Some(Unit::Column(c)) => Some(Unit::Column(left + c)),
+                    _ => None,
+                },
+            },
+            SolveError::NoCandidates { row, col } => SolveError::NoCandidates {
+                row: top + row,
+                col: left + col,
+            },
+            error => error,
+        }
+    }
+
+    // The undetermined cell with the fewest candidates across all grids.
+    fn most_constrained(&self) -> Option<(usize, usize)> {
+        self.grids
+            .iter()
+            .enumerate()
+            .filter_map(|(g, grid)| grid.min_entropy_index().map(|idx| (g, idx)))
+            .min_by_key(|(g, idx)| self.grids[*g].candidates_at(*idx).len())
+    }
+}
+
+// Depth-first search like `search::search`, branching on the most
+// constrained cell of the whole board.
+fn search<F>(mut board: MultiGridState, on_solution: &mut F) -> bool
+where
+    F: FnMut(&MultiGridState) -> bool,
+{
+    if board.propagate().is_err() {
+        return true;
+    }
+
+    let Some((g, idx)) = board.most_constrained() else {
+        return on_solution(&board);
+    };
+
+    for val in board.grids[g].candidates_at(idx) {
+        let mut branch = board.clone();
+        branch.grids[g].collapse(idx, val);
+
+        if !search(branch, on_solution) {
+            return false;
+        }
+    }
+
+    true
+}
+
+// One line per board row, with spaces for the cells outside every grid.
+impl Display for MultiGridState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut covered = vec![false; self.side * self.side];
+        for ((top, left), grid) in self.offsets.iter().zip(&self.grids) {
+            let size = grid.size();
+            for idx in 0..size * size {
+                covered[(top + idx / size) * self.side + left + idx % size] = true;
+            }
+        }
+
+        let lines: Vec<String> = self
+            .values()
+            .chunks(self.side)
+            .zip(covered.chunks(self.side))
+            .map(|(values, covered)| {
+                values
+                    .iter()
+                    .zip(covered)
+                    .map(|(v, c)| if *c { digit_char(*v) } else { ' ' })
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect();
+
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MultiGridState;
+    use crate::state::SolveError;
+    use crate::strategies::Unit;
+
+    #[test]
+    fn can_parse_samurai() {
+        let board = MultiGridState::samurai(&"0".repeat(369)).unwrap();
+        assert_eq!(board.grids().len(), 5);
+        assert_eq!(board.shared.len(), 36);
+
+        let mut input = "0".repeat(441);
+        input.replace_range(0..1, "5");
+        let board = MultiGridState::samurai(&input).unwrap();
+        assert_eq!(board.values()[0], 5);
+
+        assert!(MultiGridState::samurai(&"0".repeat(400)).is_err());
+        assert!(MultiGridState::samurai(&"A".repeat(369)).is_err());
+    }
+
+    #[test]
+    fn can_solve_samurai() {
+        let mut board = MultiGridState::samurai(&"0".repeat(369)).unwrap();
+        board.solve().unwrap();
+
+        assert!(board.is_solved());
+        assert!(board.grids().iter().all(|g| g.satisfies_rules()));
+        let values = board.values();
+        for ((g1, i1), (g2, i2)) in &board.shared {
+            assert_eq!(
+                board.grids[*g1].values()[*i1],
+                board.grids[*g2].values()[*i2]
+            );
+        }
+
+        // blank every other cell and solve again from what is left
+        let puzzle: Vec<u8> = values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| if i % 2 == 0 { 0 } else { *v })
+            .collect();
+        let mut board = MultiGridState::new(21, 9, &super::SAMURAI_GRIDS, &puzzle).unwrap();
+        board.solve().unwrap();
+        assert!(board.is_solved());
+        assert!(board
+            .values()
+            .iter()
+            .zip(&puzzle)
+            .all(|(v, given)| *given == 0 || v == given));
+    }
+
+    #[test]
+    fn can_propagate_across_grids() {
+        // a 1 in the centre grid's first row rules out 1 from the cells of that
+        // row the top-left grid shares
+        let mut input = "0".repeat(441);
+        input.replace_range(6 * 21 + 10..6 * 21 + 11, "1");
+        let mut board = MultiGridState::samurai(&input).unwrap();
+        board.propagate().unwrap();
+
+        assert!(!board.grids()[0].candidates_at(6 * 9 + 6).contains(&1));
+        assert!(board.grids()[0].candidates_at(5 * 9 + 6).contains(&1));
+
+        // errors are placed on the board rather than within the grid
+        input.replace_range(6 * 21 + 12..6 * 21 + 13, "1");
+        assert_eq!(
+            MultiGridState::samurai(&input).unwrap().solve(),
+            Err(SolveError::Contradiction {
+                row: 6,
+                col: 12,
+                value: 1,
+                unit: Some(Unit::Row(6))
+            })
+        );
+    }
+
+    #[test]
+    fn can_display_samurai() {
+        let board = MultiGridState::samurai(&"0".repeat(369)).unwrap();
+        let lines: Vec<String> = board.to_string().lines().map(String::from).collect();
+
+        assert_eq!(lines.len(), 21);
+        assert_eq!(lines[0], format!("{}   {}", "0".repeat(9), "0".repeat(9)));
+        assert_eq!(lines[10], format!("{}{}", " ".repeat(6), "0".repeat(9)));
+    }
+}
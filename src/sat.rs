@@ -0,0 +1,178 @@
+use crate::search::Interrupt;
+use crate::state::State;
+use varisat::{ExtendFormula, Lit, Solver};
+
+// Encodes `state` as CNF with one variable per (cell, digit): every cell holds
+// exactly one of its candidates, peers never share a digit and every unit
+// holds each digit somewhere. Each killer cage adds a variable per set of
+// digits adding up to its sum, one of which must hold: its digits then appear
+// in the cage and no others do. Shapes other than sandwiches likewise get a
+// variable per completion, one of which must hold and puts its digits in the
+// shape's cells. Sandwiches are not encoded, and `Solver` refuses them before
+// they get here; models are still checked against every rule.
+// `on_solution` returns whether the search should continue, and the return
+// value reports the same, or false once `interrupt` stops it. Each solution
+// found is blocked so the next solve finds a different one; the interrupt is
+// checked before each solve, as varisat cannot be stopped within one.
+pub(crate) fn search<F>(state: &State, interrupt: &Interrupt, on_solution: &mut F) -> bool
+where
+    F: FnMut(&State) -> bool,
+{
+    let size = state.size();
+    let lit = |idx: usize, val: u8| Lit::from_index(idx * size + val as usize - 1, true);
+    let mut solver = Solver::new();
+
+    for idx in 0..size * size {
+        let candidates = state.candidates_at(idx);
+        let clause: Vec<Lit> = candidates.iter().map(|v| lit(idx, *v)).collect();
+        solver.add_clause(&clause);
+
+        for val in 1..=size as u8 {
+            if !candidates.contains(&val) {
+                solver.add_clause(&[!lit(idx, val)]);
+            }
+        }
+        for (i, a) in candidates.iter().enumerate() {
+            for b in &candidates[i + 1..] {
+                solver.add_clause(&[!lit(idx, *a), !lit(idx, *b)]);
+            }
+        }
+
+        for peer in state.constraints().get_constrained_inds(idx) {
+            if *peer > idx {
+                for val in 1..=size as u8 {
+                    solver.add_clause(&[!lit(idx, val), !lit(*peer, val)]);
+                }
+            }
+        }
+    }
+
+    for unit in state.constraints().units() {
+        for val in 1..=size as u8 {
+            let clause: Vec<Lit> = unit.iter().map(|i| lit(*i, val)).collect();
+            solver.add_clause(&clause);
+        }
+    }
+
+    let digits = size * size * size;
+    let mut next = digits;
+    for cage in state.constraints().cages() {
+        let sets = cage.digit_sets(size);
+        let picks: Vec<Lit> = (next..next + sets.len())
+            .map(|i| Lit::from_index(i, true))
+            .collect();
+        next += sets.len();
+        solver.add_clause(&picks);
+
+        for (set, pick) in sets.iter().zip(&picks) {
+            for val in 1..=size as u8 {
+                if set & 1 << val != 0 {
+                    let clause: Vec<Lit> = std::iter::once(!*pick)
+                        .chain(cage.cells.iter().map(|i| lit(*i, val)))
+                        .collect();
+                    solver.add_clause(&clause);
+                } else {
+                    for idx in &cage.cells {
+                        solver.add_clause(&[!*pick, !lit(*idx, val)]);
+                    }
+                }
+            }
+        }
+    }
+
+    for shape in state.constraints().shapes() {
+        let Some(completions) = shape.completions(size) else {
+            continue;
+        };
+        let picks: Vec<Lit> = (next..next + completions.len())
+            .map(|i| Lit::from_index(i, true))
+            .collect();
+        next += completions.len();
+        solver.add_clause(&picks);
+
+        for (completion, pick) in completions.iter().zip(&picks) {
+            for (idx, val) in shape.cells().into_iter().zip(completion) {
+                solver.add_clause(&[!*pick, lit(idx, *val)]);
+            }
+        }
+    }
+
+    loop {
+        if interrupt.check().is_err() {
+            return false;
+        }
+        let (Ok(true), Some(model)) = (solver.solve(), solver.model()) else {
+            return true;
+        };
+
+        // the digits alone, as the variables added for cages and shapes follow
+        // from them
+        let chosen: Vec<Lit> = model
+            .into_iter()
+            .filter(|l| l.is_positive() && l.index() < digits)
+            .collect();
+
+        let mut solution = state.clone();
+        for l in &chosen {
+            solution.collapse(l.index() / size, (l.index() % size) as u8 + 1);
+        }
+        if solution.satisfies_rules() && !on_solution(&solution) {
+            return false;
+        }
+
+        let blocking: Vec<Lit> = chosen.iter().map(|l| !*l).collect();
+        solver.add_clause(&blocking);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::search;
+    use crate::search::Interrupt;
+    use crate::state::State;
+
+    #[test]
+    fn can_solve_with_sat() {
+        let state = State::from(
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+        );
+        let mut solutions = vec![];
+        search(&state, &Interrupt::default(), &mut |s| {
+            solutions.push(format!("{s}"));
+            true
+        });
+
+        assert_eq!(
+            solutions,
+            vec![
+                "812753649943682175675491283154237896369845721287169534521974368438526917796318452"
+            ]
+        );
+    }
+
+    #[test]
+    fn can_count_with_sat() {
+        let mut count = 0;
+        search(
+            &State::from(&"0".repeat(16) as &str),
+            &Interrupt::default(),
+            &mut |_| {
+                count += 1;
+                true
+            },
+        );
+
+        assert_eq!(count, 288);
+    }
+
+    #[test]
+    fn can_detect_no_solution() {
+        let state = State::from(
+            "110000000000000000000000000000000000000000000000000000000000000000000000000000000",
+        );
+
+        assert!(search(&state, &Interrupt::default(), &mut |_| panic!(
+            "should have no solution"
+        )));
+    }
+}
@@ -0,0 +1,243 @@
+use crate::constraints::Constraints;
+use crate::search::Interrupt;
+use crate::state::{ConstraintError, SolveError, State};
+
+// Buffers for solving on bare candidate masks, kept between solves so that
+// once they have grown to fit a grid, solving allocates nothing. Each level
+// of the search saves the masks it may have to restore in `saved`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Scratch {
+    masks: Vec<u32>,
+    saved: Vec<u32>,
+    queue: Vec<usize>,
+    next: Vec<usize>,
+}
+
+// Why propagation stopped: a cell lost its last candidate, or a unit has
+// nowhere left for some digit.
+enum DeadEnd {
+    Cell(ConstraintError),
+    Unit,
+}
+
+impl Scratch {
+    // Solves `state` by propagating naked and hidden singles and guessing on
+    // the cell with the fewest candidates. Puzzles with cages or shapes,
+    // which are not handled here, are solved by `State::solve`.
+    pub(crate) fn solve(
+        &mut self,
+        state: &mut State,
+        interrupt: &Interrupt,
+    ) -> Result<(), SolveError> {
+        let constraints = state.constraints();
+        if !constraints.cages().is_empty() || !constraints.shapes().is_empty() {
+            return state.solve_until(interrupt);
+        }
+
+        let size = state.size();
+        let full = ((1u64 << (size + 1)) - 2) as u32;
+        state.copy_masks_into(&mut self.masks);
+
+        self.queue.clear();
+        self.queue
+            .extend((0..self.masks.len()).filter(|i| self.masks[*i].is_power_of_two()));
+        let constraints = state.constraints();
+        match self.propagate(constraints, full) {
+            Err(DeadEnd::Cell(e)) => return Err(e.in_grid(constraints)),
+            Err(DeadEnd::Unit) => return Err(SolveError::Unsolvable),
+            Ok(()) => {}
+        }
+        if !self.search(constraints, full, interrupt, 0) {
+            return Err(interrupt.reason());
+        }
+
+        state.set_masks(&self.masks);
+        Ok(())
+    }
+
+    fn propagate(&mut self, constraints: &Constraints, full: u32) -> Result<(), DeadEnd> {
+        self.next.clear();
+        loop {
+            // singles are applied in rounds and in order, so a contradiction
+            // is reported at the same cell as `State::solve` reports it
+            while !self.queue.is_empty() {
+                self.queue.sort_unstable();
+                for &idx in &self.queue {
+                    let bit = self.masks[idx];
+                    for peer in constraints.get_constrained_inds(idx) {
+                        let mask = self.masks[*peer];
+                        if mask & bit == 0 {
+                            continue;
+                        }
+                        if mask == bit {
+                            let value = bit.trailing_zeros() as u8;
+                            return Err(DeadEnd::Cell(ConstraintError::Conflict(
+                                *peer,
+                                value,
+                                Some(idx),
+                            )));
+                        }
+
+                        self.masks[*peer] = mask & !bit;
+                        if self.masks[*peer].is_power_of_two() {
+                            self.next.push(*peer);
+                        }
+                    }
+                }
+                self.queue.clear();
+                std::mem::swap(&mut self.queue, &mut self.next);
+            }
+
+            // digits seen once in a unit are hidden singles
+            for unit in constraints.units() {
+                let (mut once, mut twice) = (0, 0);
+                for idx in unit {
+                    twice |= once & self.masks[*idx];
+                    once |= self.masks[*idx];
+                }
+                if once != full {
+                    return Err(DeadEnd::Unit);
+                }
+
+                let singles = once & !twice;
+                for idx in unit {
+                    let single = self.masks[*idx] & singles;
+                    if single == 0 || single == self.masks[*idx] {
+                        continue;
+                    }
+                    if !single.is_power_of_two() {
+                        return Err(DeadEnd::Unit);
+                    }
+
+                    self.masks[*idx] = single;
+                    self.queue.push(*idx);
+                }
+            }
+
+            if self.queue.is_empty() {
+                return Ok(());
+            }
+        }
+    }
+
+    fn search(
+        &mut self,
+        constraints: &Constraints,
+        full: u32,
+        interrupt: &Interrupt,
+        depth: usize,
+    ) -> bool {
+        if interrupt.check().is_err() {
+            return false;
+        }
+
+        let mut best = None;
+        let mut fewest = u32::MAX;
+        for (idx, mask) in self.masks.iter().enumerate() {
+            let count = mask.count_ones();
+            if count > 1 && count < fewest {
+                best = Some(idx);
+                fewest = count;
+                if count == 2 {
+                    break;
+                }
+            }
+        }
+        let Some(idx) = best else {
+            return true;
+        };
+
+        let cells = self.masks.len();
+        let level = depth * cells..(depth + 1) * cells;
+        if self.saved.len() < level.end {
+            self.saved.resize(level.end, 0);
+        }
+        self.saved[level.clone()].copy_from_slice(&self.masks);
+
+        let mut options = self.masks[idx];
+        while options != 0 {
+            let bit = options & options.wrapping_neg();
+            options &= !bit;
+
+            self.masks[idx] = bit;
+            self.queue.clear();
+            self.queue.push(idx);
+            if self.propagate(constraints, full).is_ok()
+                && self.search(constraints, full, interrupt, depth + 1)
+            {
+                return true;
+            }
+            self.masks.copy_from_slice(&self.saved[level.clone()]);
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Scratch;
+    use crate::search::Interrupt;
+    use crate::state::{SolveError, State};
+    use crate::variant::Variant;
+
+    #[test]
+    fn can_solve_with_scratch() {
+        let mut scratch = Scratch::default();
+        let puzzles = [
+            "301086504046521070500000001400800002080347900009050038004090200008734090007208103",
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+            "000000010400000000020000000000050407008000300001090000300400200050100000000806000",
+        ];
+
+        for puzzle in puzzles {
+            let mut expected = State::from(puzzle);
+            expected.solve().unwrap();
+
+            let mut state = State::from(puzzle);
+            scratch.solve(&mut state, &Interrupt::default()).unwrap();
+            assert_eq!(state.values(), expected.values());
+        }
+
+        let mut state = State::from(&"0".repeat(81) as &str).with_variant(Variant::AntiKnight);
+        scratch.solve(&mut state, &Interrupt::default()).unwrap();
+        assert!(state.is_solved() && state.satisfies_rules());
+    }
+
+    #[test]
+    fn can_reuse_buffers() {
+        let mut scratch = Scratch::default();
+        let puzzle =
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400";
+        scratch
+            .solve(&mut State::from(puzzle), &Interrupt::default())
+            .unwrap();
+        let capacity = scratch.saved.capacity();
+
+        scratch
+            .solve(&mut State::from(puzzle), &Interrupt::default())
+            .unwrap();
+        assert_eq!(scratch.saved.capacity(), capacity);
+    }
+
+    #[test]
+    fn can_report_errors() {
+        let mut scratch = Scratch::default();
+
+        for puzzle in ["1100000000000000", "1200003000030000", "1000000000000234"] {
+            assert_eq!(
+                scratch.solve(&mut State::from(puzzle), &Interrupt::default()),
+                State::from(puzzle).solve()
+            );
+        }
+        assert_eq!(
+            scratch.solve(
+                &mut State::from(
+                    "123456000000000007000000000000000000000000000000000000000000000000000000000000000"
+                ),
+                &Interrupt::default()
+            ),
+            Err(SolveError::Unsolvable)
+        );
+    }
+}
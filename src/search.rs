@@ -0,0 +1,264 @@
+use crate::state::{SolveError, State};
+use crate::stats::SolveStats;
+use crate::strategies;
+use crate::trace::SolveEvent;
+#[cfg(feature = "std")]
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "std")]
+use std::sync::Arc;
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
+use tracing::trace_span;
+
+// When a search should give up early: once `deadline` has passed, or once
+// `cancel` has been set, usually from another thread. The default never
+// interrupts, and without `std` neither can be set.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Interrupt {
+    #[cfg(feature = "std")]
+    deadline: Option<Instant>,
+    #[cfg(feature = "std")]
+    cancel: Option<Arc<AtomicBool>>,
+}
+
+impl Interrupt {
+    #[cfg(feature = "std")]
+    pub(crate) fn new(timeout: Option<Duration>, cancel: Option<Arc<AtomicBool>>) -> Self {
+        Interrupt {
+            deadline: timeout.map(|t| Instant::now() + t),
+            cancel,
+        }
+    }
+
+    pub(crate) fn check(&self) -> Result<(), SolveError> {
+        #[cfg(feature = "std")]
+        if self
+            .cancel
+            .as_ref()
+            .is_some_and(|c| c.load(Ordering::Relaxed))
+        {
+            return Err(SolveError::Cancelled);
+        }
+        #[cfg(feature = "std")]
+        if self.deadline.is_some_and(|d| Instant::now() >= d) {
+            return Err(SolveError::TimedOut);
+        }
+
+        Ok(())
+    }
+
+    // Why a search that stopped without a solution did: it was interrupted,
+    // or there is no solution.
+    pub(crate) fn reason(&self) -> SolveError {
+        self.check().err().unwrap_or(SolveError::Unsolvable)
+    }
+}
+
+pub(crate) fn search<F>(state: State, interrupt: &Interrupt, on_solution: &mut F) -> bool
+where
+    F: FnMut(&State) -> bool,
+{
+    search_ordered(state, &mut |_, _, _| {}, interrupt, on_solution)
+}
+
+// Depth-first search over the candidates of the most constrained cell.
+// `order` may reorder the candidates tried at each branch, given the grid
+// and the cell branched on, and `on_solution`
+// is called for every solution found and returns whether the search should
+// continue; the return value reports the same. An interrupted search stops
+// as if `on_solution` had returned false.
+pub(crate) fn search_ordered<O, F>(
+    mut state: State,
+    order: &mut O,
+    interrupt: &Interrupt,
+    on_solution: &mut F,
+) -> bool
+where
+    O: FnMut(&State, usize, &mut Vec<u8>),
+    F: FnMut(&State) -> bool,
+{
+    state.start_trail();
+    search_counted(
+        &mut state,
+        order,
+        &mut SolveStats::default(),
+        0,
+        interrupt,
+        None,
+        on_solution,
+    )
+}
+
+// `search_ordered` that also counts its work in `stats`, starting at `depth`
+// guesses deep, and reports what it does to `on_event` if given. Branches
+// are undone through the trail, which must have been started, and `state`
+// is left as it was given.
+pub(crate) fn search_counted<O, F>(
+    state: &mut State,
+    order: &mut O,
+    stats: &mut SolveStats,
+    depth: usize,
+    interrupt: &Interrupt,
+    on_event: Option<&mut (dyn FnMut(SolveEvent) + '_)>,
+    on_solution: &mut F,
+) -> bool
+where
+    O: FnMut(&State, usize, &mut Vec<u8>),
+    F: FnMut(&State) -> bool,
+{
+    if interrupt.check().is_err() {
+        return false;
+    }
+
+    let mark = state.mark();
+    let keep_going = search_node(state, order, stats, depth, interrupt, on_event, on_solution);
+    state.undo_to(mark);
+
+    keep_going
+}
+
+fn search_node<O, F>(
+    state: &mut State,
+    order: &mut O,
+    stats: &mut SolveStats,
+    depth: usize,
+    interrupt: &Interrupt,
+    mut on_event: Option<&mut (dyn FnMut(SolveEvent) + '_)>,
+    on_solution: &mut F,
+) -> bool
+where
+    O: FnMut(&State, usize, &mut Vec<u8>),
+    F: FnMut(&State) -> bool,
+{
+    let _span = trace_span!("search_node", depth).entered();
+    stats.propagations += 1;
+    stats.max_depth = stats.max_depth.max(depth);
+    let mark = state.mark();
+    if state.propagate_constraints().is_err() || strategies::place_hidden_singles(state).is_err() {
+        stats.backtracks += 1;
+        return true;
+    }
+    if let Some(on_event) = on_event.as_deref_mut() {
+        report_changes(state, mark, on_event);
+    }
+
+    let Some(index) = state.min_entropy_index() else {
+        return on_solution(state);
+    };
+
+    let mut candidates = state.candidates_at(index);
+    order(state, index, &mut candidates);
+
+    let size = state.size();
+    let mark = state.mark();
+    for val in candidates {
+        state.collapse(index, val);
+        stats.guesses += 1;
+        if let Some(on_event) = on_event.as_deref_mut() {
+            on_event(SolveEvent::Guessed {
+                row: index / size,
+                col: index % size,
+                value: val,
+                depth: depth + 1,
+            });
+        }
+
+        let keep_going = search_counted(
+            state,
+            order,
+            stats,
+            depth + 1,
+            interrupt,
+            on_event.as_deref_mut(),
+            on_solution,
+        );
+        state.undo_to(mark);
+        if !keep_going {
+            return false;
+        }
+        if let Some(on_event) = on_event.as_deref_mut() {
+            on_event(SolveEvent::Backtracked { depth: depth + 1 });
+        }
+    }
+
+    true
+}
+
+// Every solution of a puzzle, found lazily by the same depth-first search as
+// `search`, so solutions come in the same order and each call to `next`
+// picks up where the last one left off. Grids waiting to be explored are kept
+// on a stack, each with its guess placed but not yet propagated. Iteration
+// ends early if `interrupt` fires.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub(crate) struct Solutions {
+    stack: Vec<State>,
+    interrupt: Interrupt,
+}
+
+#[cfg(feature = "std")]
+impl Solutions {
+    pub(crate) fn new(state: &State, interrupt: Interrupt) -> Self {
+        Solutions {
+            stack: vec![state.clone()],
+            interrupt,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Iterator for Solutions {
+    type Item = State;
+
+    fn next(&mut self) -> Option<State> {
+        while let Some(mut state) = self.stack.pop() {
+            if self.interrupt.check().is_err() {
+                self.stack.clear();
+                return None;
+            }
+            if state.propagate_constraints().is_err()
+                || strategies::place_hidden_singles(&mut state).is_err()
+            {
+                continue;
+            }
+
+            let Some(index) = state.min_entropy_index() else {
+                return Some(state);
+            };
+            // pushed in reverse so the smallest candidate is tried first
+            for val in state.candidates_at(index).into_iter().rev() {
+                let mut guess = state.clone();
+                guess.collapse(index, val);
+                self.stack.push(guess);
+            }
+        }
+
+        None
+    }
+}
+
+// Reports the cells changed since `mark` as placements, or as the
+// candidates they lost if they are still open.
+pub(crate) fn report_changes(state: &State, mark: usize, on_event: &mut dyn FnMut(SolveEvent)) {
+    let size = state.size();
+    for (idx, before, after) in state.changes_since(mark) {
+        let (row, col) = (idx / size, idx % size);
+        if after.is_power_of_two() {
+            if !before.is_power_of_two() {
+                let value = after.trailing_zeros() as u8;
+                on_event(SolveEvent::Placed { row, col, value });
+            }
+            continue;
+        }
+
+        let mut lost = before & !after;
+        while lost != 0 {
+            let value = lost.trailing_zeros() as u8;
+            lost &= lost - 1;
+            on_event(SolveEvent::Eliminated { row, col, value });
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use crate::generator::{self, Symmetry};
+use crate::rating::{self, Difficulty};
+use crate::report::{SolveReport, SolveStatus};
+use crate::solver::Solver;
+use crate::state::State;
+use crate::trace::HintJson;
+use crate::validate::Solutions;
+use serde::{Deserialize, Serialize};
+use tiny_http::{Header, Method, Response, Server};
+use tracing::{info, warn};
+
+// A small JSON API over HTTP. `POST /solve`, `/hint` and `/validate` take a
+// body of `{"puzzle": "..."}` in any format `State::from_str` accepts, and
+// `GET /generate?difficulty=hard&symmetry=rotational` makes a new puzzle.
+// Failures are answered with `{"error": "..."}` and a 4xx status.
+
+#[derive(Deserialize)]
+struct PuzzleRequest {
+    puzzle: String,
+}
+
+#[derive(Serialize)]
+struct ValidationJson {
+    valid: bool,
+    solutions: &'static str,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct GeneratedJson {
+    puzzle: String,
+    difficulty: String,
+}
+
+#[derive(Serialize)]
+struct ErrorJson {
+    error: String,
+}
+
+// Answers requests on `addr`, e.g. "127.0.0.1:8080", until the process is
+// stopped.
+pub fn serve(addr: &str, solver: &Solver) -> anyhow::Result<()> {
+    let server = Server::http(addr).map_err(|e| anyhow::anyhow!("cannot listen on {addr}: {e}"))?;
+    info!("listening on {addr}");
+
+    for mut request in server.incoming_requests() {
+        let mut body = String::new();
+        let (status, json) = match request.as_reader().read_to_string(&mut body) {
+            Ok(_) => respond(solver, request.method(), request.url(), &body),
+            Err(e) => error(400, e),
+        };
+
+        let header = Header::from_bytes("Content-Type", "application/json")
+            .expect("content type should be a valid header");
+        let response = Response::from_string(json)
+            .with_status_code(status)
+            .with_header(header);
+        if let Err(e) = request.respond(response) {
+            warn!("failed to send response: {e}");
+        }
+    }
+
+    Ok(())
+}
+
+// Routes a request, returning the status code and JSON body to answer with.
+fn respond(solver: &Solver, method: &Method, url: &str, body: &str) -> (u16, String) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+
+    match (method, path) {
+        (Method::Post, "/solve") => with_puzzle(body, |state| {
+            let report = SolveReport::new(solver, &state);
+            match report.status {
+                SolveStatus::Solved => (200, to_json(&report)),
+                SolveStatus::Failed => (422, to_json(&report)),
+            }
+        }),
+        (Method::Post, "/hint") => with_puzzle(body, |state| {
+            (200, to_json(&solver.next_hint(&state).map(HintJson::from)))
+        }),
+        (Method::Post, "/validate") => with_puzzle(body, |state| {
+            let validation = state.validate();
+            let json = ValidationJson {
+                valid: validation.is_valid(),
+                solutions: match validation.solutions {
+                    Solutions::None => "none",
+                    Solutions::Unique => "unique",
+                    Solutions::Multiple => "multiple",
+                },
+                message: validation.to_string(),
+            };
+            (200, to_json(&json))
+        }),
+        (Method::Get, "/generate") => generate(query),
+        (_, "/solve" | "/hint" | "/validate" | "/generate") => {
+            error(405, format!("{method} is not allowed on {path}"))
+        }
+        _ => error(404, format!("no such endpoint: {path}")),
+    }
+}
+
+fn with_puzzle(body: &str, f: impl FnOnce(State) -> (u16, String)) -> (u16, String) {
+    let request: PuzzleRequest = match serde_json::from_str(body) {
+        Ok(request) => request,
+        Err(e) => return error(400, format!("expected {{\"puzzle\": \"...\"}}: {e}")),
+    };
+
+    match request.puzzle.parse() {
+        Ok(state) => f(state),
+        Err(e) => error(400, e),
+    }
+}
+
+fn generate(query: &str) -> (u16, String) {
+    let mut difficulty = Difficulty::Medium;
+    let mut symmetry = Symmetry::None;
+    for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+        let parsed = match key {
+            "difficulty" => value.parse().map(|d| difficulty = d),
+            "symmetry" => value.parse().map(|s| symmetry = s),
+            _ => Ok(()),
+        };
+        if let Err(e) = parsed {
+            return error(400, e);
+        }
+    }
+
+    // the generator settles for the closest difficulty it can make
+    let puzzle = generator::generate(difficulty, symmetry);
+    let rated = rating::rate(&puzzle).map_or(difficulty, |r| r.difficulty);
+
+    (
+        200,
+        to_json(&GeneratedJson {
+            puzzle: puzzle.to_string(),
+            difficulty: rated.to_string(),
+        }),
+    )
+}
+
+fn error(status: u16, error: impl std::fmt::Display) -> (u16, String) {
+    (
+        status,
+        to_json(&ErrorJson {
+            error: error.to_string(),
+        }),
+    )
+}
+
+fn to_json(value: &impl Serialize) -> String {
+    serde_json::to_string(value).expect("responses serialize to json")
+}
+
+#[cfg(test)]
+mod test {
+    use super::respond;
+    use crate::solver::Solver;
+    use tiny_http::Method;
+
+    const PUZZLE: &str = r#"{"puzzle": "301086504046521070500000001400800002080347900009050038004090200008734090007208103"}"#;
+
+    fn post(url: &str, body: &str) -> (u16, String) {
+        respond(&Solver::new(), &Method::Post, url, body)
+    }
+
+    #[test]
+    fn can_solve_and_hint() {
+        let (status, json) = post("/solve", PUZZLE);
+        assert_eq!(status, 200);
+        assert!(json.contains(r#""solution":"3719865"#));
+
+        let (status, json) = post("/solve", r#"{"puzzle": "1100000000000000"}"#);
+        assert_eq!(status, 422);
+        assert!(json.contains(r#""status":"failed""#));
+
+        let (status, json) = post("/hint", PUZZLE);
+        assert_eq!(status, 200);
+        assert!(json.starts_with(r#"{"technique":"naked_single""#));
+    }
+
+    #[test]
+    fn can_validate_and_generate() {
+        let (status, json) = post("/validate", r#"{"puzzle": "0000000000000000"}"#);
+        assert_eq!(status, 200);
+        assert!(json.starts_with(r#"{"valid":false,"solutions":"multiple""#));
+
+        let (status, json) = respond(
+            &Solver::new(),
+            &Method::Get,
+            "/generate?difficulty=easy&symmetry=rotational",
+            "",
+        );
+        assert_eq!(status, 200);
+        assert!(json.ends_with(r#""difficulty":"easy"}"#));
+    }
+
+    #[test]
+    fn can_reject_bad_requests() {
+        assert_eq!(post("/solve", "1100").0, 400);
+        assert_eq!(post("/solve", r#"{"puzzle": "12"}"#).0, 400);
+        assert_eq!(post("/generate", "").0, 405);
+        assert_eq!(post("/missing", "").0, 404);
+
+        let (status, json) = respond(
+            &Solver::new(),
+            &Method::Get,
+            "/generate?difficulty=trivial",
+            "",
+        );
+        assert_eq!(status, 400);
+        assert_eq!(json, r#"{"error":"unknown difficulty: trivial"}"#);
+    }
+}
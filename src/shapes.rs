@@ -0,0 +1,994 @@
+use crate::constraints::{self, Peers};
+use crate::killer::parse_cell;
+use crate::state::{GridCell, SolveError, State};
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+// A variant shape drawn over the grid, constraining the digits along it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    // Digits strictly increase from the bulb, the first cell, to the tip.
+    Thermometer(Vec<usize>),
+    // The digit in `circle` is the sum of the digits along `cells`, which
+    // may repeat.
+    Arrow {
+        circle: usize,
+        cells: Vec<usize>,
+    },
+    // Two orthogonally adjacent cells whose digits follow `relation`.
+    Pair {
+        cells: [usize; 2],
+        relation: Relation,
+    },
+    // The digits strictly between the 1 and the largest digit along `cells`,
+    // a whole row or column, add up to `sum`, as in sandwich sudoku.
+    Sandwich {
+        cells: Vec<usize>,
+        sum: u32,
+    },
+}
+
+// How the digits of a `Shape::Pair` relate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    // A white kropki dot: the digits differ by one.
+    Consecutive,
+    // A black kropki dot: one digit is double the other.
+    Double,
+    // The digits do not differ by one, as across every edge in
+    // non-consecutive sudoku.
+    NonConsecutive,
+    // The first digit is less than the second, as in greater-than sudoku.
+    Less,
+}
+
+impl Relation {
+    fn holds(self, a: u8, b: u8) -> bool {
+        match self {
+            Relation::Consecutive => a.abs_diff(b) == 1,
+            Relation::Double => a == 2 * b || b == 2 * a,
+            Relation::NonConsecutive => a.abs_diff(b) != 1,
+            Relation::Less => a < b,
+        }
+    }
+
+    // Whether the cells of a pair can be swapped without changing it.
+    fn is_symmetric(self) -> bool {
+        self != Relation::Less
+    }
+}
+
+impl Peers for Shape {
+    // Only a thermometer's digits must all differ; an arrow's may repeat,
+    // pairs are pruned by their relation instead and a sandwich lies along a
+    // row or column whose cells already see each other.
+    fn add_peers(&self, peers: &mut [Vec<usize>]) {
+        if let Shape::Thermometer(cells) = self {
+            constraints::link_all(cells, peers);
+        }
+    }
+}
+
+impl Shape {
+    // Every cell the shape covers, bulb or circle first.
+    pub fn cells(&self) -> Vec<usize> {
+        match self {
+            Shape::Thermometer(cells) => cells.clone(),
+            Shape::Arrow { circle, cells } => [*circle].into_iter().chain(cells.clone()).collect(),
+            Shape::Pair { cells, .. } => cells.to_vec(),
+            Shape::Sandwich { cells, .. } => cells.clone(),
+        }
+    }
+
+    // Narrows each cell's candidate mask to the digits that appear in some
+    // completion of the shape. Returns the narrowed masks in the order of
+    // `cells`.
+    pub(crate) fn supported(&self, masks: &[u32]) -> Vec<u32> {
+        let masks: Vec<u32> = self.cells().iter().map(|i| masks[*i]).collect();
+
+        match self {
+            Shape::Thermometer(_) => thermometer_supported(&masks),
+            Shape::Arrow { .. } => arrow_supported(masks[0], &masks[1..]),
+            // a two-cell thermometer, pruned by the other cell's bounds
+            Shape::Pair {
+                relation: Relation::Less,
+                ..
+            } => thermometer_supported(&masks),
+            Shape::Pair { relation, .. } => vec![
+                pair_supported(masks[0], masks[1], *relation),
+                pair_supported(masks[1], masks[0], *relation),
+            ],
+            Shape::Sandwich { sum, .. } => sandwich_supported(&masks, *sum),
+        }
+    }
+
+    // Every way of filling the shape's cells, in the order of `cells`, with
+    // digits up to `size` that holds, for the exact cover and SAT backends to
+    // pick one of. `None` for sandwiches, whose completions are whole lines.
+    #[cfg(feature = "std")]
+    pub(crate) fn completions(&self, size: usize) -> Option<Vec<Vec<u8>>> {
+        let size = size as u8;
+        let mut completions = vec![];
+
+        match self {
+            Shape::Thermometer(cells) => {
+                add_increasing(1, size, cells.len(), &mut vec![], &mut completions)
+            }
+            Shape::Arrow { cells, .. } => {
+                add_arrow(size, cells.len(), &mut vec![0], &mut completions)
+            }
+            Shape::Pair { relation, .. } => {
+                for a in 1..=size {
+                    completions.extend(
+                        (1..=size)
+                            .filter(|b| relation.holds(a, *b))
+                            .map(|b| vec![a, b]),
+                    );
+                }
+            }
+            Shape::Sandwich { .. } => return None,
+        }
+
+        Some(completions)
+    }
+
+    // How far the digits in `values` are from following the shape: the
+    // steps of a thermometer that fail to increase, the distance of an arrow
+    // from its circle, one for a pair that breaks its relation, or the
+    // distance of a sandwich from its clue. Zero when the shape holds.
+    pub(crate) fn error(&self, values: &[u8]) -> i64 {
+        match self {
+            Shape::Thermometer(cells) => cells
+                .windows(2)
+                .filter(|w| values[w[0]] >= values[w[1]])
+                .count() as i64,
+            Shape::Arrow { circle, cells } => {
+                let sum: i64 = cells.iter().map(|i| values[*i] as i64).sum();
+                (sum - values[*circle] as i64).abs()
+            }
+            Shape::Pair {
+                cells: [a, b],
+                relation,
+            } => !relation.holds(values[*a], values[*b]) as i64,
+            Shape::Sandwich { cells, sum } => {
+                let line: Vec<u8> = cells.iter().map(|i| values[*i]).collect();
+                let crust = |digit: u8| line.iter().position(|v| *v == digit);
+                match (crust(1), crust(line.len() as u8)) {
+                    (Some(a), Some(b)) => {
+                        let filling: i64 =
+                            line[a.min(b) + 1..a.max(b)].iter().map(|v| *v as i64).sum();
+                        (filling - *sum as i64).abs()
+                    }
+                    // further off than any filling
+                    _ => *sum as i64 + 1,
+                }
+            }
+        }
+    }
+
+    // The same shape with each cell `idx` moved to `map(idx)`.
+    pub(crate) fn moved(&self, map: impl Fn(usize) -> usize) -> Shape {
+        match self {
+            Shape::Thermometer(cells) => {
+                Shape::Thermometer(cells.iter().map(|i| map(*i)).collect())
+            }
+            Shape::Arrow { circle, cells } => {
+                let mut cells: Vec<usize> = cells.iter().map(|i| map(*i)).collect();
+                cells.sort_unstable();
+                Shape::Arrow {
+                    circle: map(*circle),
+                    cells,
+                }
+            }
+            Shape::Pair { cells, relation } => {
+                let mut cells = cells.map(&map);
+                if relation.is_symmetric() {
+                    cells.sort_unstable();
+                }
+                Shape::Pair {
+                    cells,
+                    relation: *relation,
+                }
+            }
+            // read either way, a sandwich is the same, but its cells must
+            // stay in order
+            Shape::Sandwich { cells, sum } => {
+                let cells: Vec<usize> = cells.iter().map(|i| map(*i)).collect();
+                let reversed: Vec<usize> = cells.iter().rev().copied().collect();
+                Shape::Sandwich {
+                    cells: cells.min(reversed),
+                    sum: *sum,
+                }
+            }
+        }
+    }
+}
+
+// A digit is possible in a thermometer cell if some smaller digit is
+// possible before it and some larger one after it. The lowest possible digit
+// of each cell is found walking up from the bulb, and the highest walking
+// down from the tip.
+fn thermometer_supported(masks: &[u32]) -> Vec<u32> {
+    let lowest = |mask: u32| mask.trailing_zeros();
+    let highest = |mask: u32| u32::BITS - 1 - mask.leading_zeros();
+    let above = |floor: u32| u32::MAX.checked_shl(floor + 1).unwrap_or(0);
+    let below = |ceiling: u32| (1u32 << ceiling) - 1;
+
+    let mut supported = masks.to_vec();
+    let mut floor = 0;
+    for mask in supported.iter_mut() {
+        *mask &= above(floor);
+        if *mask == 0 {
+            return vec![0; masks.len()];
+        }
+        floor = lowest(*mask);
+    }
+
+    let mut ceiling = u32::BITS - 1;
+    for mask in supported.iter_mut().rev() {
+        *mask &= below(ceiling);
+        if *mask == 0 {
+            return vec![0; masks.len()];
+        }
+        ceiling = highest(*mask);
+    }
+
+    supported
+}
+
+// The digits of `mask` that relate to some digit of `other`.
+fn pair_supported(mask: u32, other: u32, relation: Relation) -> u32 {
+    (1..u32::BITS as u8)
+        .filter(|a| mask & 1 << a != 0)
+        .filter(|a| (1..u32::BITS as u8).any(|b| other & 1 << b != 0 && relation.holds(*a, b)))
+        .fold(0, |supported, a| supported | 1 << a)
+}
+
+// Every pair of orthogonally adjacent cells in a grid of `size` digits, with
+// `relation` between them.
+pub(crate) fn adjacent_pairs(size: usize, relation: Relation) -> Vec<Shape> {
+    (0..size * size)
+        .flat_map(|idx| {
+            let right = (idx % size + 1 < size).then_some(idx + 1);
+            let down = (idx + size < size * size).then_some(idx + size);
+            [right, down]
+                .into_iter()
+                .flatten()
+                .map(move |other| Shape::Pair {
+                    cells: [idx, other],
+                    relation,
+                })
+        })
+        .collect()
+}
+
+// Sums reachable along an arrow, as flags indexed by sum: before each cell
+// walking forwards, and from each cell to the end walking backwards. A digit
+// is possible in a cell if some sum before it and some sum after it make a
+// total the circle can hold, and a digit is possible in the circle if the
+// whole arrow can add up to it.
+fn arrow_supported(circle: u32, cells: &[u32]) -> Vec<u32> {
+    let digits = |mask: u32| (1..u32::BITS).filter(move |d| mask & 1 << d != 0);
+    let max = u32::BITS as usize * (cells.len() + 1);
+    let step = |sums: &[bool], mask: u32| {
+        let mut next = vec![false; max];
+        for (sum, _) in sums.iter().enumerate().filter(|(_, s)| **s) {
+            for d in digits(mask) {
+                next[sum + d as usize] = true;
+            }
+        }
+        next
+    };
+
+    let mut start = vec![false; max];
+    start[0] = true;
+    let mut before = vec![start.clone()];
+    for mask in cells {
+        let next = step(before.last().expect("starts non-empty"), *mask);
+        before.push(next);
+    }
+    let mut after = vec![start];
+    for mask in cells.iter().rev() {
+        let next = step(after.last().expect("starts non-empty"), *mask);
+        after.push(next);
+    }
+    after.reverse();
+
+    let totals: Vec<usize> = digits(circle).map(|d| d as usize).collect();
+    let mut supported = vec![digits(circle)
+        .filter(|d| before[cells.len()][*d as usize])
+        .fold(0, |mask, d| mask | 1 << d)];
+    for (i, mask) in cells.iter().enumerate() {
+        let possible = |d: u32| {
+            before[i].iter().enumerate().any(|(head, reached)| {
+                *reached
+                    && totals
+                        .iter()
+                        .any(|t| *t >= head + d as usize && after[i + 1][t - head - d as usize])
+            })
+        };
+        supported.push(
+            digits(*mask)
+                .filter(|d| possible(*d))
+                .fold(0, |m, d| m | 1 << d),
+        );
+    }
+
+    supported
+}
+
+// Adds every strictly increasing run of `left` more digits from `from` up to
+// `size` after `picked` to `completions`.
+#[cfg(feature = "std")]
+fn add_increasing(
+    from: u8,
+    size: u8,
+    left: usize,
+    picked: &mut Vec<u8>,
+    completions: &mut Vec<Vec<u8>>,
+) {
+    if left == 0 {
+        completions.push(picked.clone());
+        return;
+    }
+
+    for digit in from..=size {
+        picked.push(digit);
+        add_increasing(digit + 1, size, left - 1, picked, completions);
+        picked.pop();
+    }
+}
+
+// Adds every way of picking `left` more digits along an arrow after `picked`,
+// whose first digit is the circle and holds the sum so far, that keeps the
+// sum a digit up to `size`.
+#[cfg(feature = "std")]
+fn add_arrow(size: u8, left: usize, picked: &mut Vec<u8>, completions: &mut Vec<Vec<u8>>) {
+    if left == 0 {
+        if picked[0] > 0 {
+            completions.push(picked.clone());
+        }
+        return;
+    }
+
+    for digit in 1..=size - picked[0] {
+        picked[0] += digit;
+        picked.push(digit);
+        add_arrow(size, left - 1, picked, completions);
+        picked.pop();
+        picked[0] -= digit;
+    }
+}
+
+// A digit is possible in a sandwich cell if it takes part in some placement
+// of the 1 and the largest digit, the crusts, with distinct digits between
+// them adding up to the clue, and every cell outside them left a digit that
+// is neither a crust nor used between them.
+fn sandwich_supported(masks: &[u32], sum: u32) -> Vec<u32> {
+    let (low, high) = (1 << 1, 1 << masks.len());
+    let filling = |mask: u32| mask & !(low | high);
+
+    let mut supported = vec![0; masks.len()];
+    for a in (0..masks.len()).filter(|a| masks[*a] & low != 0) {
+        for b in (0..masks.len()).filter(|b| *b != a && masks[*b] & high != 0) {
+            let (first, last) = (a.min(b), a.max(b));
+            let between: Vec<GridCell> = masks[first + 1..last]
+                .iter()
+                .map(|m| GridCell::from_mask(filling(*m)))
+                .collect();
+            let outside: Vec<usize> = (0..first).chain(last + 1..masks.len()).collect();
+
+            for combination in GridCell::sum_combinations(&between, sum, true) {
+                let used = combination.iter().fold(0, |used, d| used | 1 << d);
+                if outside.iter().any(|i| filling(masks[*i]) & !used == 0) {
+                    continue;
+                }
+
+                supported[a] |= low;
+                supported[b] |= high;
+                for (i, digit) in combination.iter().enumerate() {
+                    supported[first + 1 + i] |= 1 << digit;
+                }
+                for i in &outside {
+                    supported[*i] |= filling(masks[*i]) & !used;
+                }
+            }
+        }
+    }
+
+    supported
+}
+
+// Reads greater-than markers, one per line as `r1c1<r1c2` or `r1c1>r1c2`
+// between orthogonally adjacent cells. Blank lines and lines starting with
+// `#` are skipped.
+pub fn parse_inequalities(input: &str, size: usize) -> Result<Vec<Shape>, SolveError> {
+    let invalid = |line: usize, message: String| {
+        SolveError::InvalidInput(format!("inequality on line {line}: {message}"))
+    };
+    let mut inequalities = vec![];
+
+    for (n, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (cells, flipped) = match (line.split_once('<'), line.split_once('>')) {
+            (Some(cells), None) => (cells, false),
+            (None, Some(cells)) => (cells, true),
+            _ => return Err(invalid(n + 1, "expected one of < or >".to_string())),
+        };
+        let cell = |name: &str| {
+            let name = name.trim();
+            parse_cell(name, size).ok_or_else(|| invalid(n + 1, format!("bad cell '{name}'")))
+        };
+        let (a, b) = (cell(cells.0)?, cell(cells.1)?);
+        if !adjacent(a, b, size) {
+            return Err(invalid(n + 1, "cells are not adjacent".to_string()));
+        }
+
+        inequalities.push(Shape::Pair {
+            cells: if flipped { [b, a] } else { [a, b] },
+            relation: Relation::Less,
+        });
+    }
+
+    Ok(inequalities)
+}
+
+// Reads sandwich clues, one per line as `r1=15` for a row or `c3=0` for a
+// column. Blank lines and lines starting with `#` are skipped.
+pub fn parse_sandwiches(input: &str, size: usize) -> Result<Vec<Shape>, SolveError> {
+    let invalid = |line: usize, message: String| {
+        SolveError::InvalidInput(format!("sandwich on line {line}: {message}"))
+    };
+    // every digit but the crusts
+    let most = (2..size as u32).sum::<u32>();
+    let mut sandwiches = vec![];
+
+    for (n, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (name, sum) = line
+            .split_once('=')
+            .ok_or_else(|| invalid(n + 1, "expected a line and a clue, as r1=15".to_string()))?;
+        let name = name.trim().to_lowercase();
+        let index = |prefix: char| {
+            name.strip_prefix(prefix)
+                .and_then(|i| i.parse::<usize>().ok())
+                .filter(|i| (1..=size).contains(i))
+                .map(|i| i - 1)
+        };
+        let cells: Vec<usize> = match (index('r'), index('c')) {
+            (Some(row), _) => (0..size).map(|col| row * size + col).collect(),
+            (_, Some(col)) => (0..size).map(|row| row * size + col).collect(),
+            _ => return Err(invalid(n + 1, format!("bad row or column '{name}'"))),
+        };
+        let sum: u32 = sum
+            .trim()
+            .parse()
+            .map_err(|_| invalid(n + 1, format!("bad clue '{}'", sum.trim())))?;
+        if sum > most {
+            return Err(invalid(
+                n + 1,
+                format!("clue {sum} is more than the digits between 1 and {size} add up to"),
+            ));
+        }
+        if sandwiches
+            .iter()
+            .any(|s| matches!(s, Shape::Sandwich { cells: c, .. } if *c == cells))
+        {
+            return Err(invalid(n + 1, format!("{name} already has a clue")));
+        }
+
+        sandwiches.push(Shape::Sandwich { cells, sum });
+    }
+
+    Ok(sandwiches)
+}
+
+// Whether two cells of a grid of `size` digits share an edge.
+fn adjacent(a: usize, b: usize, size: usize) -> bool {
+    let [a, b] = [a, b].map(|i| (i / size, i % size));
+    a.0.abs_diff(b.0) + a.1.abs_diff(b.1) == 1
+}
+
+// The JSON layout of a shapes file, naming cells as `r1c1`:
+// `{"thermometers": [["r1c1", "r1c2"]], "arrows": [{"circle": "r5c5", "cells": ["r4c4"]}],
+// "white_dots": [["r1c1", "r2c1"]], "black_dots": [["r9c8", "r9c9"]]}`.
+// Any list may be left out.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ShapesFile {
+    #[serde(default)]
+    thermometers: Vec<Vec<String>>,
+    #[serde(default)]
+    arrows: Vec<ArrowEntry>,
+    #[serde(default)]
+    white_dots: Vec<Vec<String>>,
+    #[serde(default)]
+    black_dots: Vec<Vec<String>>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ArrowEntry {
+    circle: String,
+    cells: Vec<String>,
+}
+
+// Reads thermometers, arrows and kropki dots from JSON, in the layout of
+// `ShapesFile`. Dots must join two orthogonally adjacent cells.
+#[cfg(feature = "serde")]
+pub fn parse_shapes(input: &str, size: usize) -> Result<Vec<Shape>, SolveError> {
+    let invalid = |message: String| SolveError::InvalidInput(format!("shapes: {message}"));
+    let file: ShapesFile = serde_json::from_str(input).map_err(|e| invalid(e.to_string()))?;
+    let cells = |names: &[String]| {
+        names
+            .iter()
+            .map(|name| parse_cell(name, size).ok_or_else(|| invalid(format!("bad cell '{name}'"))))
+            .collect::<Result<Vec<usize>, SolveError>>()
+    };
+
+    let mut shapes = vec![];
+    for (n, thermometer) in file.thermometers.iter().enumerate() {
+        if thermometer.is_empty() {
+            return Err(invalid(format!("thermometer {} has no cells", n + 1)));
+        }
+        shapes.push(Shape::Thermometer(cells(thermometer)?));
+    }
+    for (n, arrow) in file.arrows.iter().enumerate() {
+        if arrow.cells.is_empty() {
+            return Err(invalid(format!("arrow {} has no cells", n + 1)));
+        }
+        shapes.push(Shape::Arrow {
+            circle: cells(core::slice::from_ref(&arrow.circle))?[0],
+            cells: cells(&arrow.cells)?,
+        });
+    }
+
+    let dots = [
+        (&file.white_dots, Relation::Consecutive, "white"),
+        (&file.black_dots, Relation::Double, "black"),
+    ];
+    for (dots, relation, colour) in dots {
+        for (n, dot) in dots.iter().enumerate() {
+            let cells: [usize; 2] = cells(dot)?
+                .try_into()
+                .map_err(|_| invalid(format!("{colour} dot {} should join two cells", n + 1)))?;
+            if !adjacent(cells[0], cells[1], size) {
+                return Err(invalid(format!(
+                    "{colour} dot {} joins cells that are not adjacent",
+                    n + 1
+                )));
+            }
+            shapes.push(Shape::Pair { cells, relation });
+        }
+    }
+
+    for shape in &shapes {
+        let cells = shape.cells();
+        if let Some(cell) = cells
+            .iter()
+            .enumerate()
+            .find(|(i, c)| cells[..*i].contains(c))
+        {
+            return Err(invalid(format!(
+                "cell index {} appears twice in one shape",
+                cell.1
+            )));
+        }
+    }
+
+    Ok(shapes)
+}
+
+impl State {
+    pub fn with_shapes(self, shapes: Vec<Shape>) -> Self {
+        let constraints = self.constraints().clone().with_shapes(shapes);
+        self.with_constraints(constraints)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{adjacent_pairs, parse_inequalities, parse_sandwiches, Relation, Shape};
+    use crate::solver::{Backend, SolveOptions, Solver};
+    use crate::state::{SolveError, State};
+    use std::time::Duration;
+
+    const ALL: u32 = 0b11_1111_1110;
+
+    #[test]
+    fn can_prune_thermometer_candidates() {
+        let thermo = Shape::Thermometer(vec![0, 1, 2]);
+
+        assert_eq!(
+            thermo.supported(&[ALL, ALL, ALL]),
+            vec![0b00_1111_1110, 0b01_1111_1100, 0b11_1111_1000]
+        );
+        assert_eq!(
+            thermo.supported(&[ALL, 1 << 3, ALL]),
+            vec![0b0110, 0b1000, 0b11_1111_0000]
+        );
+        assert_eq!(thermo.supported(&[1 << 5, 1 << 3, ALL]), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn can_prune_arrow_candidates() {
+        let arrow = Shape::Arrow {
+            circle: 0,
+            cells: vec![1, 2],
+        };
+
+        assert_eq!(
+            arrow.supported(&[ALL, ALL, ALL]),
+            vec![0b11_1111_1100, 0b01_1111_1110, 0b01_1111_1110]
+        );
+        assert_eq!(
+            arrow.supported(&[1 << 4, 1 << 1 | 1 << 2, ALL]),
+            vec![1 << 4, 1 << 1 | 1 << 2, 1 << 2 | 1 << 3]
+        );
+        assert_eq!(arrow.supported(&[1 << 2, 1 << 5, ALL]), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn can_prune_sandwich_candidates() {
+        let all = 0b1_1110;
+        let crusts = 1 << 1 | 1 << 4;
+        let sandwich = |sum| Shape::Sandwich {
+            cells: vec![0, 1, 2, 3],
+            sum,
+        };
+
+        // only 2 and 3 add up to 5, so they fill the middle of a 4×4 row
+        assert_eq!(
+            sandwich(5).supported(&[all; 4]),
+            vec![crusts, 1 << 2 | 1 << 3, 1 << 2 | 1 << 3, crusts]
+        );
+        assert_eq!(sandwich(3).supported(&[all; 4])[0], all & !(1 << 3));
+        assert_eq!(sandwich(5).supported(&[1 << 2, all, all, all]), vec![0; 4]);
+    }
+
+    #[test]
+    fn can_prune_pair_candidates() {
+        let pair = |relation| Shape::Pair {
+            cells: [0, 1],
+            relation,
+        };
+
+        assert_eq!(
+            pair(Relation::Consecutive).supported(&[1 << 5, ALL]),
+            vec![1 << 5, 1 << 4 | 1 << 6]
+        );
+        assert_eq!(
+            pair(Relation::Double).supported(&[ALL, ALL]),
+            vec![0b1_0101_1110; 2]
+        );
+        assert_eq!(
+            pair(Relation::NonConsecutive).supported(&[1 << 2, 1 << 1 | 1 << 3]),
+            vec![0, 0]
+        );
+        assert_eq!(
+            pair(Relation::NonConsecutive).supported(&[1 << 2, 1 << 1 | 1 << 5]),
+            vec![1 << 2, 1 << 5]
+        );
+    }
+
+    #[test]
+    fn can_prune_inequality_candidates() {
+        let less = Shape::Pair {
+            cells: [0, 1],
+            relation: Relation::Less,
+        };
+
+        assert_eq!(
+            less.supported(&[ALL, 1 << 2 | 1 << 6]),
+            vec![0b11_1110, 1 << 2 | 1 << 6]
+        );
+        assert_eq!(
+            less.supported(&[1 << 4 | 1 << 7, 1 << 2 | 1 << 6]),
+            vec![1 << 4, 1 << 6]
+        );
+    }
+
+    #[test]
+    fn can_parse_inequalities() {
+        let inequalities = parse_inequalities("# markers\nr1c1 < r1c2\n\nR2C1>r1c1\n", 4).unwrap();
+
+        assert_eq!(
+            inequalities,
+            vec![
+                Shape::Pair {
+                    cells: [0, 1],
+                    relation: Relation::Less
+                },
+                Shape::Pair {
+                    cells: [0, 4],
+                    relation: Relation::Less
+                }
+            ]
+        );
+        assert!(parse_inequalities("r1c1<r2c2", 4).is_err());
+        assert!(parse_inequalities("r1c1<r1c2>r1c3", 4).is_err());
+        assert!(parse_inequalities("r1c1=r1c2", 4).is_err());
+    }
+
+    #[test]
+    fn can_parse_sandwiches() {
+        let sandwiches = parse_sandwiches("# clues\nr1=5\n\nC2 = 0\n", 4).unwrap();
+
+        assert_eq!(
+            sandwiches,
+            vec![
+                Shape::Sandwich {
+                    cells: vec![0, 1, 2, 3],
+                    sum: 5
+                },
+                Shape::Sandwich {
+                    cells: vec![1, 5, 9, 13],
+                    sum: 0
+                }
+            ]
+        );
+        assert!(parse_sandwiches("r5=1", 4).is_err());
+        assert!(parse_sandwiches("r1=6", 4).is_err());
+        assert!(parse_sandwiches("r1=5\nr1=0", 4).is_err());
+        assert!(parse_sandwiches("r1", 4).is_err());
+        assert!(parse_sandwiches("x1=3", 4).is_err());
+    }
+
+    #[test]
+    fn can_solve_sandwich() {
+        let clues = "r1=15\nr2=4\nr3=0\nr4=0\nr5=0\nr6=5\nr7=0\nr8=12\nr9=15\n\
+            c1=11\nc2=14\nc3=0\nc4=16\nc5=19\nc6=0\nc7=14\nc8=10\nc9=35";
+        let mut state = State::from(
+            "000000000000500000000000002000000000000000005000000000009000000000000006003000009",
+        )
+        .with_shapes(parse_sandwiches(clues, 9).unwrap());
+
+        assert!(state.has_unique_solution());
+        let refuses = |backend| {
+            let solver = Solver::with_backend(backend);
+            matches!(
+                solver.solve(&mut state.clone()),
+                Err(SolveError::InvalidInput(_))
+            ) && matches!(
+                solver.count_solutions(&state, 2),
+                Err(SolveError::InvalidInput(_))
+            )
+        };
+        assert!(refuses(Backend::Dlx));
+        #[cfg(feature = "sat")]
+        assert!(refuses(Backend::Sat));
+        assert_eq!(Solver::new().count_solutions(&state, 2), Ok(1));
+        Solver::new().solve(&mut state).unwrap();
+        assert!(state.satisfies_rules());
+        assert_eq!(
+            state.to_string(),
+            "435269781682571493197834562826195347374682915951743628519326874248957136763418259"
+        );
+    }
+
+    #[test]
+    fn can_solve_greater_than() {
+        // The first column of a 4×4 grid increases downwards, and each of
+        // the top two rows from its first cell to its second.
+        let inequalities =
+            parse_inequalities("r1c1<r2c1\nr2c1<r3c1\nr3c1<r4c1\nr1c1<r1c2\nr2c1<r2c2", 4).unwrap();
+
+        for backend in [
+            Backend::Propagation,
+            Backend::Dlx,
+            #[cfg(feature = "sat")]
+            Backend::Sat,
+        ] {
+            let mut state = State::from(&"0".repeat(16) as &str).with_shapes(inequalities.clone());
+            Solver::with_backend(backend).solve(&mut state).unwrap();
+            let values = state.values();
+
+            assert!(state.satisfies_rules());
+            assert_eq!(values[0], 1);
+            assert!((0..2).all(|r| values[r * 4] < values[r * 4 + 1]));
+        }
+    }
+
+    #[test]
+    fn can_list_adjacent_pairs() {
+        let pairs = adjacent_pairs(3, Relation::NonConsecutive);
+
+        assert_eq!(pairs.len(), 12);
+        assert!(pairs.iter().all(|p| match p {
+            Shape::Pair { cells: [a, b], .. } => *b == a + 1 && a % 3 != 2 || *b == a + 3,
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn can_measure_error() {
+        let values = [1, 2, 2, 5];
+
+        assert_eq!(Shape::Thermometer(vec![0, 1, 3]).error(&values), 0);
+        assert_eq!(Shape::Thermometer(vec![3, 1, 2]).error(&values), 2);
+        assert_eq!(
+            Shape::Arrow {
+                circle: 3,
+                cells: vec![0, 1]
+            }
+            .error(&values),
+            2
+        );
+
+        let sandwich = Shape::Sandwich {
+            cells: vec![0, 1, 2, 3],
+            sum: 5,
+        };
+        assert_eq!(sandwich.error(&[2, 1, 3, 4]), 2);
+        assert_eq!(sandwich.error(&[1, 2, 3, 4]), 0);
+        assert_eq!(sandwich.error(&values), 6);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn can_parse_shapes() {
+        use super::parse_shapes;
+
+        let shapes = parse_shapes(
+            r#"{"thermometers": [["r1c1", "R1C2"]], "arrows": [{"circle": "r4c4", "cells": ["r3c3"]}]}"#,
+            4,
+        )
+        .unwrap();
+
+        assert_eq!(
+            shapes,
+            vec![
+                Shape::Thermometer(vec![0, 1]),
+                Shape::Arrow {
+                    circle: 15,
+                    cells: vec![10]
+                }
+            ]
+        );
+        assert!(parse_shapes(r#"{"thermometers": [["r1c1", "r5c1"]]}"#, 4).is_err());
+        assert!(parse_shapes(r#"{"arrows": [{"circle": "r1c1", "cells": ["r1c1"]}]}"#, 4).is_err());
+        assert!(parse_shapes(r#"{"cages": []}"#, 4).is_err());
+
+        assert_eq!(
+            parse_shapes(
+                r#"{"white_dots": [["r1c1", "r2c1"]], "black_dots": [["r4c3", "r4c4"]]}"#,
+                4
+            ),
+            Ok(vec![
+                Shape::Pair {
+                    cells: [0, 4],
+                    relation: Relation::Consecutive
+                },
+                Shape::Pair {
+                    cells: [14, 15],
+                    relation: Relation::Double
+                }
+            ])
+        );
+        assert!(parse_shapes(r#"{"white_dots": [["r1c1", "r2c2"]]}"#, 4).is_err());
+        assert!(parse_shapes(r#"{"black_dots": [["r1c1"]]}"#, 4).is_err());
+    }
+
+    #[test]
+    fn can_solve_with_shapes() {
+        // A thermometer along the top row fixes it to 1 2 3 4, and an arrow
+        // from r2c1 through r3c2 and r3c3 leaves one way to finish the grid.
+        let shapes = vec![
+            Shape::Thermometer(vec![0, 1, 2, 3]),
+            Shape::Arrow {
+                circle: 4,
+                cells: vec![9, 10],
+            },
+        ];
+
+        for backend in [
+            Backend::Propagation,
+            Backend::Dlx,
+            #[cfg(feature = "sat")]
+            Backend::Sat,
+            Backend::Annealing,
+        ] {
+            let mut state = State::from(&"0".repeat(16) as &str).with_shapes(shapes.clone());
+            let solver = Solver::with_backend(backend);
+
+            assert_eq!(solver.count_solutions(&state, 2), Ok(1));
+            solver.solve(&mut state).unwrap();
+            assert!(state.satisfies_rules());
+            assert_eq!(&state.values()[..4], &[1, 2, 3, 4]);
+        }
+    }
+
+    #[test]
+    fn can_solve_shapes_on_every_backend() {
+        // An empty 9×9 grid with a thermometer down the first column, an
+        // arrow, white and black dots and a row of inequalities, which the
+        // exact cover and SAT backends must prune rather than check on
+        // finished grids.
+        let shapes = vec![
+            Shape::Thermometer(vec![0, 10, 20, 30]),
+            Shape::Arrow {
+                circle: 40,
+                cells: vec![41, 42, 43],
+            },
+            Shape::Pair {
+                cells: [60, 61],
+                relation: Relation::Consecutive,
+            },
+            Shape::Pair {
+                cells: [70, 79],
+                relation: Relation::Double,
+            },
+            Shape::Pair {
+                cells: [80, 71],
+                relation: Relation::Less,
+            },
+        ];
+        let options = SolveOptions::default().timeout(Duration::from_secs(20));
+
+        for backend in [
+            Backend::Propagation,
+            Backend::Dlx,
+            #[cfg(feature = "sat")]
+            Backend::Sat,
+        ] {
+            let mut state = State::from(&"0".repeat(81) as &str).with_shapes(shapes.clone());
+            Solver::with_backend(backend)
+                .with_options(options)
+                .solve(&mut state)
+                .unwrap();
+
+            assert!(state.satisfies_rules(), "{backend}");
+        }
+    }
+
+    #[test]
+    fn can_list_shape_completions() {
+        let arrow = Shape::Arrow {
+            circle: 0,
+            cells: vec![1, 2],
+        };
+
+        assert_eq!(
+            Shape::Thermometer(vec![0, 1, 2]).completions(4),
+            Some(vec![
+                vec![1, 2, 3],
+                vec![1, 2, 4],
+                vec![1, 3, 4],
+                vec![2, 3, 4]
+            ])
+        );
+        assert_eq!(
+            arrow.completions(4),
+            Some(vec![
+                vec![2, 1, 1],
+                vec![3, 1, 2],
+                vec![4, 1, 3],
+                vec![3, 2, 1],
+                vec![4, 2, 2],
+                vec![4, 3, 1]
+            ])
+        );
+        assert_eq!(
+            Shape::Sandwich {
+                cells: vec![0, 1, 2, 3],
+                sum: 0
+            }
+            .completions(4),
+            None
+        );
+    }
+}
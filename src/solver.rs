@@ -0,0 +1,880 @@
+use crate::annealing::{self, Annealing};
+use crate::dlx;
+#[cfg(feature = "sat")]
+use crate::sat;
+use crate::scratch::Scratch;
+use crate::search::{self, Interrupt, Solutions};
+use crate::shapes::Shape;
+use crate::state::{SolveError, State};
+use crate::stats::SolveStats;
+use crate::strategies::{self, Technique};
+use crate::trace::{Hint, LogicalSolve, SolveEvent, SolveTrace};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{fmt::Display, str::FromStr};
+use tracing::debug_span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    #[default]
+    Propagation,
+    Dlx,
+    Annealing,
+    #[cfg(feature = "sat")]
+    Sat,
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "propagation" => Ok(Backend::Propagation),
+            "dlx" => Ok(Backend::Dlx),
+            "annealing" => Ok(Backend::Annealing),
+            #[cfg(feature = "sat")]
+            "sat" => Ok(Backend::Sat),
+            #[cfg(not(feature = "sat"))]
+            "sat" => Err("the sat backend needs the `sat` feature".to_string()),
+            _ => Err(format!("unknown backend: {s}")),
+        }
+    }
+}
+
+impl Display for Backend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Backend::Propagation => "propagation",
+            Backend::Dlx => "dlx",
+            Backend::Annealing => "annealing",
+            #[cfg(feature = "sat")]
+            Backend::Sat => "sat",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+// The order the propagation backend tries a cell's candidates in when it
+// has to guess. The cell guessed on is always one with the fewest
+// candidates left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Heuristic {
+    #[default]
+    Ascending,
+    // candidates that rule out the fewest candidates of open peers first
+    LeastConstraining,
+    Random,
+}
+
+impl FromStr for Heuristic {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ascending" => Ok(Heuristic::Ascending),
+            "least-constraining" | "lcv" => Ok(Heuristic::LeastConstraining),
+            "random" => Ok(Heuristic::Random),
+            _ => Err(format!("unknown heuristic: {s}")),
+        }
+    }
+}
+
+impl Display for Heuristic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Heuristic::Ascending => "ascending",
+            Heuristic::LeastConstraining => "least-constraining",
+            Heuristic::Random => "random",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+// Requirements every solve must meet, whichever backend runs it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SolveOptions {
+    // Fail with `SolveError::MultipleSolutions` rather than return one of
+    // several solutions.
+    pub require_unique: bool,
+    // Give up with `SolveError::TimedOut` once a solve has taken this long.
+    // The SAT backend only stops once its solver returns.
+    pub timeout: Option<Duration>,
+    pub heuristic: Heuristic,
+    // Seeds `Heuristic::Random`, so that puzzles with several solutions are
+    // solved to a different but reproducible one. Without a seed the order
+    // differs from run to run.
+    pub seed: Option<u64>,
+}
+
+impl SolveOptions {
+    pub fn timeout(self, timeout: Duration) -> Self {
+        SolveOptions {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    pub fn heuristic(self, heuristic: Heuristic) -> Self {
+        SolveOptions { heuristic, ..self }
+    }
+
+    pub fn seed(self, seed: u64) -> Self {
+        SolveOptions {
+            seed: Some(seed),
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Solver {
+    backend: Backend,
+    annealing: Annealing,
+    options: SolveOptions,
+    cancel: Option<Arc<AtomicBool>>,
+    scratch: Scratch,
+}
+
+impl Solver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_backend(backend: Backend) -> Self {
+        Solver {
+            backend,
+            ..Self::default()
+        }
+    }
+
+    // Settings used by `Backend::Annealing`.
+    pub fn with_annealing(self, annealing: Annealing) -> Self {
+        Solver { annealing, ..self }
+    }
+
+    pub fn with_options(self, options: SolveOptions) -> Self {
+        Solver { options, ..self }
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    pub fn options(&self) -> SolveOptions {
+        self.options
+    }
+
+    // Makes a solve stop with `SolveError::Cancelled` once `cancel` is set,
+    // usually from another thread.
+    pub fn with_cancel(self, cancel: Arc<AtomicBool>) -> Self {
+        Solver {
+            cancel: Some(cancel),
+            ..self
+        }
+    }
+
+    pub fn solve(&self, state: &mut State) -> Result<(), SolveError> {
+        let _span = debug_span!("solve", backend = %self.backend).entered();
+        let interrupt = self.interrupt();
+        self.check_unique(state, &interrupt)?;
+        self.solve_any(state, &interrupt)
+    }
+
+    // Starts the clock on `options.timeout` for one solve.
+    fn interrupt(&self) -> Interrupt {
+        Interrupt::new(self.options.timeout, self.cancel.clone())
+    }
+
+    // Orders the candidates tried at each guess by `options.heuristic`.
+    fn order(&self) -> impl FnMut(&State, usize, &mut Vec<u8>) {
+        let heuristic = self.options.heuristic;
+        let mut rng = (heuristic == Heuristic::Random).then(|| match self.options.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        });
+
+        move |state, idx, candidates| match (heuristic, &mut rng) {
+            (Heuristic::LeastConstraining, _) => {
+                candidates.sort_by_cached_key(|val| state.peers_with(idx, *val))
+            }
+            (Heuristic::Random, Some(rng)) => candidates.shuffle(rng),
+            _ => {}
+        }
+    }
+
+    // Counting to two is the cheapest proof a solution is unique, and is only
+    // paid for when it is asked for.
+    fn check_unique(&self, state: &State, interrupt: &Interrupt) -> Result<(), SolveError> {
+        if self.options.require_unique && self.count_until(state, 2, interrupt)? > 1 {
+            return Err(SolveError::MultipleSolutions);
+        }
+
+        Ok(())
+    }
+
+    // The exact cover and SAT backends encode every shape but sandwich clues,
+    // which they could only check on finished grids, leaving them searching
+    // for hours, so they turn such puzzles away instead.
+    fn check_supported(&self, state: &State) -> Result<(), SolveError> {
+        let sandwiches = state
+            .constraints()
+            .shapes()
+            .iter()
+            .any(|shape| matches!(shape, Shape::Sandwich { .. }));
+
+        match self.backend {
+            Backend::Propagation | Backend::Annealing => Ok(()),
+            backend if sandwiches => Err(SolveError::InvalidInput(format!(
+                "the {backend} backend cannot solve sandwich clues, use the propagation backend"
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    fn solve_any(&self, state: &mut State, interrupt: &Interrupt) -> Result<(), SolveError> {
+        self.check_supported(state)?;
+        match self.backend {
+            Backend::Propagation => state.solve_ordered(&mut self.order(), interrupt),
+            Backend::Dlx => {
+                let mut solution = None;
+                dlx::search(state, interrupt, &mut |s| {
+                    solution = Some(s.clone());
+                    false
+                });
+                *state = solution.ok_or_else(|| interrupt.reason())?;
+
+                Ok(())
+            }
+            Backend::Annealing => annealing::solve(state, &self.annealing, interrupt),
+            #[cfg(feature = "sat")]
+            Backend::Sat => {
+                let mut solution = None;
+                sat::search(state, interrupt, &mut |s| {
+                    solution = Some(s.clone());
+                    false
+                });
+                *state = solution.ok_or_else(|| interrupt.reason())?;
+
+                Ok(())
+            }
+        }
+    }
+
+    // Like `solve`, but the propagation backend reuses buffers kept in the
+    // solver, so solving many puzzles of one size allocates nothing per call.
+    // Only the ascending heuristic is supported without allocating, so the
+    // others take the same path as `solve`.
+    pub fn solve_in_place(&mut self, state: &mut State) -> Result<(), SolveError> {
+        let interrupt = self.interrupt();
+        self.check_unique(state, &interrupt)?;
+        match self.backend {
+            Backend::Propagation if self.options.heuristic == Heuristic::Ascending => {
+                self.scratch.solve(state, &interrupt)
+            }
+            _ => self.solve_any(state, &interrupt),
+        }
+    }
+
+    // Solves `state` by logic for as long as possible, recording each step,
+    // then finishes with the backend.
+    pub fn solve_with_trace(&self, state: &mut State) -> Result<SolveTrace, SolveError> {
+        let interrupt = self.interrupt();
+        self.check_unique(state, &interrupt)?;
+        let mut steps = vec![];
+        let solved = strategies::solve_logically(state, &mut |step| steps.push(step.clone()))
+            .map_err(|e| e.in_grid(state.constraints()))?;
+
+        let mut searched = 0;
+        if !solved {
+            searched = state.size() * state.size() - state.filled_count();
+            self.solve_any(state, &interrupt)?;
+        }
+
+        Ok(SolveTrace {
+            size: state.size(),
+            steps,
+            searched,
+        })
+    }
+
+    // Solves `state` by logic alone, never guessing, using no technique
+    // harder than `max_technique`, and reports how far it got. `state` is
+    // left with every deduction made, solved or not.
+    pub fn solve_logical_only(
+        &self,
+        state: &mut State,
+        max_technique: Technique,
+    ) -> Result<LogicalSolve, SolveError> {
+        let size = state.size();
+        let mut steps = vec![];
+        strategies::solve_logically_upto(state, max_technique, &mut |step| {
+            steps.push(step.clone())
+        })
+        .map_err(|e| e.in_grid(state.constraints()))?;
+
+        Ok(LogicalSolve {
+            size,
+            steps,
+            unsolved: size * size - state.filled_count(),
+        })
+    }
+
+    // Solves `state` like `solve_with_trace`, counting the deductions made by
+    // each technique and, with the propagation backend, the search's work.
+    pub fn solve_with_stats(&self, state: &mut State) -> Result<SolveStats, SolveError> {
+        let interrupt = self.interrupt();
+        self.check_unique(state, &interrupt)?;
+        let start = Instant::now();
+        let mut stats = SolveStats::default();
+        let solved = strategies::solve_logically(state, &mut |step| {
+            *stats.techniques.entry(step.technique).or_insert(0) += 1;
+        })
+        .map_err(|e| e.in_grid(state.constraints()))?;
+
+        if !solved {
+            match self.backend {
+                Backend::Propagation => {
+                    let mut solution = None;
+                    let mut search = state.clone();
+                    search.start_trail();
+                    search::search_counted(
+                        &mut search,
+                        &mut self.order(),
+                        &mut stats,
+                        0,
+                        &interrupt,
+                        None,
+                        &mut |s| {
+                            solution = Some(s.clone());
+                            false
+                        },
+                    );
+                    *state = solution.ok_or_else(|| interrupt.reason())?;
+                }
+                _ => self.solve_any(state, &interrupt)?,
+            }
+        }
+
+        stats.elapsed = start.elapsed();
+        Ok(stats)
+    }
+
+    // Solves `state` like `solve`, reporting every placement, elimination,
+    // guess and backtrack to `observer` as it happens, so a frontend can
+    // replay the solve. Other backends than propagation report only the
+    // placements of the solution they find.
+    pub fn solve_with_observer<F>(
+        &self,
+        state: &mut State,
+        mut observer: F,
+    ) -> Result<(), SolveError>
+    where
+        F: FnMut(SolveEvent),
+    {
+        let interrupt = self.interrupt();
+        self.check_unique(state, &interrupt)?;
+        let size = state.size();
+        if self.backend != Backend::Propagation {
+            let before = state.values();
+            self.solve_any(state, &interrupt)?;
+            for (idx, value) in state.values().into_iter().enumerate() {
+                if before[idx] == 0 {
+                    let (row, col) = (idx / size, idx % size);
+                    observer(SolveEvent::Placed { row, col, value });
+                }
+            }
+            return Ok(());
+        }
+
+        let mut search = state.clone();
+        search.start_trail();
+        // propagated first so that contradictions are reported like `solve`
+        search
+            .propagate_constraints()
+            .map_err(|e| e.in_grid(search.constraints()))?;
+        search::report_changes(&search, 0, &mut observer);
+
+        let mut solution = None;
+        search::search_counted(
+            &mut search,
+            &mut self.order(),
+            &mut SolveStats::default(),
+            0,
+            &interrupt,
+            Some(&mut observer),
+            &mut |s| {
+                solution = Some(s.clone());
+                false
+            },
+        );
+        *state = solution.ok_or_else(|| interrupt.reason())?;
+
+        Ok(())
+    }
+
+    // Finds the easiest single deduction available in `state` without
+    // changing it. Returns `None` if the grid is solved, contradictory or
+    // needs guessing.
+    pub fn next_hint(&self, state: &State) -> Option<Hint> {
+        let step = match strategies::naked_single(state) {
+            Some(step) => step,
+            None => {
+                let mut state = state.clone();
+                state.propagate_constraints().ok()?;
+                strategies::find_step(&state)?
+            }
+        };
+
+        Some(Hint {
+            step,
+            size: state.size(),
+        })
+    }
+
+    // Every solution of `state`, found lazily one at a time; take as many as
+    // needed with `take`. Only the propagation search can enumerate them, so
+    // it is used whatever the backend, and iteration stops early once the
+    // timeout passes or the solver is cancelled.
+    pub fn solutions(&self, state: &State) -> impl Iterator<Item = State> {
+        Solutions::new(state, self.interrupt())
+    }
+
+    // Annealing can only find one solution, so counts with propagation.
+    // Puzzles the backend cannot solve fail with `SolveError::InvalidInput`,
+    // as they do in `solve`.
+    pub fn count_solutions(&self, state: &State, limit: usize) -> Result<usize, SolveError> {
+        self.count_until(state, limit, &Interrupt::default())
+    }
+
+    fn count_until(
+        &self,
+        state: &State,
+        limit: usize,
+        interrupt: &Interrupt,
+    ) -> Result<usize, SolveError> {
+        self.check_supported(state)?;
+
+        match self.backend {
+            Backend::Propagation | Backend::Annealing => state.count_until(limit, interrupt),
+            Backend::Dlx => {
+                let mut count = 0;
+                if limit == 0 {
+                    return Ok(count);
+                }
+
+                let finished = dlx::search(state, interrupt, &mut |_| {
+                    count += 1;
+                    count < limit
+                });
+                if !finished && count < limit {
+                    interrupt.check()?;
+                }
+
+                Ok(count)
+            }
+            #[cfg(feature = "sat")]
+            Backend::Sat => {
+                let mut count = 0;
+                if limit == 0 {
+                    return Ok(count);
+                }
+
+                let finished = sat::search(state, interrupt, &mut |_| {
+                    count += 1;
+                    count < limit
+                });
+                if !finished && count < limit {
+                    interrupt.check()?;
+                }
+
+                Ok(count)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Backend, Heuristic, SolveOptions, Solver};
+    use crate::state::{SolveError, State};
+    use crate::strategies::Technique;
+    use crate::trace::SolveEvent;
+    use proptest::prelude::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn backends_agree() {
+        let puzzles = [
+            "301086504046521070500000001400800002080347900009050038004090200008734090007208103",
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+            "000000010400000000020000000000050407008000300001090000300400200050100000000806000",
+        ];
+
+        for puzzle in puzzles {
+            let mut propagated = State::from(puzzle);
+            let mut dlx = State::from(puzzle);
+
+            assert_eq!(Solver::new().solve(&mut propagated), Ok(()));
+            assert_eq!(Solver::with_backend(Backend::Dlx).solve(&mut dlx), Ok(()));
+            assert_eq!(format!("{propagated}"), format!("{dlx}"));
+
+            let mut in_place = State::from(puzzle);
+            assert_eq!(Solver::new().solve_in_place(&mut in_place), Ok(()));
+            assert_eq!(format!("{propagated}"), format!("{in_place}"));
+
+            #[cfg(feature = "sat")]
+            {
+                let mut sat = State::from(puzzle);
+                assert_eq!(Solver::with_backend(Backend::Sat).solve(&mut sat), Ok(()));
+                assert_eq!(format!("{propagated}"), format!("{sat}"));
+            }
+        }
+    }
+
+    #[test]
+    fn can_require_unique_solution() {
+        let options = SolveOptions {
+            require_unique: true,
+            ..SolveOptions::default()
+        };
+        let mut ambiguous = State::from(&"0".repeat(16) as &str);
+
+        for backend in [Backend::Propagation, Backend::Dlx] {
+            let solver = Solver::with_backend(backend).with_options(options);
+            assert_eq!(
+                solver.solve(&mut ambiguous.clone()),
+                Err(SolveError::MultipleSolutions)
+            );
+            assert_eq!(
+                solver.solve_with_trace(&mut ambiguous.clone()).unwrap_err(),
+                SolveError::MultipleSolutions
+            );
+
+            let mut unique = State::from("1200340000430021");
+            assert_eq!(solver.solve(&mut unique), Ok(()));
+        }
+
+        assert_eq!(
+            Solver::new()
+                .with_options(options)
+                .solve_in_place(&mut ambiguous),
+            Err(SolveError::MultipleSolutions)
+        );
+        assert_eq!(Solver::new().solve(&mut ambiguous), Ok(()));
+    }
+
+    #[test]
+    fn can_interrupt_search() {
+        let puzzle = State::from(
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+        );
+        let options = SolveOptions::default().timeout(Duration::ZERO);
+
+        for backend in [
+            Backend::Propagation,
+            Backend::Dlx,
+            #[cfg(feature = "sat")]
+            Backend::Sat,
+            Backend::Annealing,
+        ] {
+            let mut solver = Solver::with_backend(backend).with_options(options);
+            assert_eq!(solver.solve(&mut puzzle.clone()), Err(SolveError::TimedOut));
+            assert_eq!(
+                solver.solve_in_place(&mut puzzle.clone()),
+                Err(SolveError::TimedOut)
+            );
+        }
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        let solver = Solver::new()
+            .with_options(SolveOptions {
+                require_unique: true,
+                ..SolveOptions::default()
+            })
+            .with_cancel(cancel.clone());
+        assert_eq!(solver.solve(&mut puzzle.clone()), Ok(()));
+        cancel.store(true, Ordering::Relaxed);
+        assert_eq!(
+            solver.solve(&mut puzzle.clone()),
+            Err(SolveError::Cancelled)
+        );
+        assert_eq!(
+            solver.solve_with_stats(&mut puzzle.clone()),
+            Err(SolveError::Cancelled)
+        );
+    }
+
+    #[test]
+    fn can_seed_search() {
+        let empty = State::from(&"0".repeat(16) as &str);
+        let solve = |seed| {
+            let mut state = empty.clone();
+            Solver::new()
+                .with_options(
+                    SolveOptions::default()
+                        .heuristic(Heuristic::Random)
+                        .seed(seed),
+                )
+                .solve_in_place(&mut state)
+                .unwrap();
+            state.values()
+        };
+
+        let solution = solve(3);
+        assert_eq!(solution, solve(3));
+        assert!((4..8).any(|seed| solve(seed) != solution));
+    }
+
+    #[test]
+    fn can_order_least_constraining_first() {
+        // r1c1 can be 2, 3 or 4, but the 3 in r2c4 already rules 3 out for
+        // three of its peers
+        let mut state = State::from("0010000300000000");
+        state.propagate_constraints().unwrap();
+        let mut candidates = state.candidates_at(0);
+        Solver::new()
+            .with_options(SolveOptions::default().heuristic(Heuristic::LeastConstraining))
+            .order()(&state, 0, &mut candidates);
+        assert_eq!(candidates, vec![3, 2, 4]);
+        assert_eq!((state.peers_with(0, 3), state.peers_with(0, 2)), (3, 6));
+
+        for heuristic in [Heuristic::LeastConstraining, Heuristic::Random] {
+            let mut state = State::from(
+                "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+            );
+            Solver::new()
+                .with_options(SolveOptions::default().heuristic(heuristic))
+                .solve(&mut state)
+                .unwrap();
+            assert!(state.is_solved() && state.satisfies_rules());
+        }
+        assert_eq!("lcv".parse(), Ok(Heuristic::LeastConstraining));
+    }
+
+    #[test]
+    fn can_count_with_dlx() {
+        let solver = Solver::with_backend(Backend::Dlx);
+
+        assert_eq!(
+            solver.count_solutions(&State::from(&"0".repeat(16) as &str), 1000),
+            Ok(288)
+        );
+        assert_eq!(
+            solver.count_solutions(&State::from(&"0".repeat(81) as &str), 0),
+            Ok(0)
+        );
+    }
+
+    #[test]
+    fn can_enumerate_solutions() {
+        let solver = Solver::new();
+        let empty = State::from(&"0".repeat(16) as &str);
+        assert_eq!(solver.solutions(&empty).count(), 288);
+
+        let mut first = empty.clone();
+        solver.solve(&mut first).unwrap();
+        let mut solutions = solver.solutions(&empty);
+        assert_eq!(solutions.next().unwrap().to_string(), first.to_string());
+        let second = solutions.next().unwrap();
+        assert!(second.is_solved() && second.satisfies_rules());
+
+        let puzzle = State::from(
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+        );
+        assert_eq!(solver.solutions(&puzzle).take(5).count(), 1);
+        assert!(solver
+            .solutions(&State::from("1100000000000000"))
+            .next()
+            .is_none());
+
+        let cancel = Arc::new(AtomicBool::new(true));
+        let cancelled = Solver::new().with_cancel(cancel);
+        assert!(cancelled.solutions(&empty).next().is_none());
+    }
+
+    #[test]
+    fn can_collect_stats() {
+        let solver = Solver::new();
+        let mut state = State::from(
+            "301086504046521070500000001400800002080347900009050038004090200008734090007208103",
+        );
+        let stats = solver.solve_with_stats(&mut state).unwrap();
+        assert!(state.is_solved());
+        // one placement for each of the 42 blanks
+        assert_eq!(stats.deductions(), 42);
+        assert_eq!((stats.guesses, stats.max_depth), (0, 0));
+
+        let mut state = State::from(
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+        );
+        let stats = solver.solve_with_stats(&mut state).unwrap();
+        assert!(state.is_solved());
+        assert!(stats.guesses > 0 && stats.backtracks > 0);
+        assert!(stats.max_depth > 0 && stats.propagations > stats.guesses);
+    }
+
+    #[test]
+    fn can_observe_solve() {
+        let puzzle = State::from(
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+        );
+        let mut state = puzzle.clone();
+
+        // replay the events, keeping the grid as it was before each guess
+        let mut values = puzzle.values();
+        let mut before_guesses: Vec<Vec<u8>> = vec![];
+        let (mut eliminations, mut backtracks) = (0, 0);
+        Solver::new()
+            .solve_with_observer(&mut state, |event| match event {
+                SolveEvent::Placed { row, col, value } => values[row * 9 + col] = value,
+                SolveEvent::Eliminated { .. } => eliminations += 1,
+                SolveEvent::Guessed {
+                    row,
+                    col,
+                    value,
+                    depth,
+                } => {
+                    assert_eq!(before_guesses.len(), depth - 1);
+                    before_guesses.push(values.clone());
+                    values[row * 9 + col] = value;
+                }
+                SolveEvent::Backtracked { depth } => {
+                    values = before_guesses[depth - 1].clone();
+                    before_guesses.truncate(depth - 1);
+                    backtracks += 1;
+                }
+            })
+            .unwrap();
+
+        assert!(state.is_solved());
+        assert_eq!(values, state.values());
+        assert!(eliminations > 0 && backtracks > 0);
+
+        let mut placed = 0;
+        Solver::with_backend(Backend::Dlx)
+            .solve_with_observer(&mut puzzle.clone(), |event| {
+                assert!(matches!(event, SolveEvent::Placed { .. }));
+                placed += 1;
+            })
+            .unwrap();
+        assert_eq!(placed, 81 - 21);
+    }
+
+    #[test]
+    fn can_solve_logical_only() {
+        let solver = Solver::new();
+        let puzzle = State::from(
+            "000002076030000002709060050200040000861300500000001000086019300100070040000000000",
+        );
+
+        let mut state = puzzle.clone();
+        let singles = solver
+            .solve_logical_only(&mut state, Technique::HiddenSingle)
+            .unwrap();
+        assert!(!singles.is_solved() && !state.is_solved());
+        assert_eq!(singles.unsolved, 81 - state.filled_count());
+        assert!(singles
+            .steps
+            .iter()
+            .all(|s| s.technique <= Technique::HiddenSingle));
+        assert!(singles.to_string().ends_with("cells open\n"));
+
+        // the x-wing it needs is one step too far for hidden triples
+        let mut state = puzzle.clone();
+        let triples = solver
+            .solve_logical_only(&mut state, Technique::HiddenTriple)
+            .unwrap();
+        assert!(!triples.is_solved() && triples.steps.len() > singles.steps.len());
+
+        let mut state = puzzle.clone();
+        assert!(solver
+            .solve_logical_only(&mut state, Technique::XWing)
+            .unwrap()
+            .is_solved());
+        assert!(state.is_solved());
+
+        let mut state = State::from(
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+        );
+        assert!(!solver
+            .solve_logical_only(&mut state, Technique::Swordfish)
+            .unwrap()
+            .is_solved());
+    }
+
+    #[test]
+    fn can_give_hints() {
+        let solver = Solver::new();
+        let mut state = State::from(
+            "000030007480960501063570820009610203350097006000005094000000005804706910001040070",
+        );
+        let mut solution = state.clone();
+        solution.solve().unwrap();
+        let solution = solution.values();
+
+        let hint = solver.next_hint(&state).unwrap();
+        assert_eq!(hint.step.technique, Technique::NakedSingle);
+
+        while let Some(hint) = solver.next_hint(&state) {
+            for (idx, val) in &hint.step.placements {
+                assert_eq!(solution[*idx], *val);
+            }
+            hint.step.apply(&mut state).unwrap();
+        }
+        assert_eq!(state.values(), solution);
+    }
+
+    #[test]
+    fn can_report_unsolvable() {
+        let mut state = State::from(
+            "110000000000000000000000000000000000000000000000000000000000000000000000000000000",
+        );
+
+        assert_eq!(
+            Solver::with_backend(Backend::Dlx).solve(&mut state),
+            Err(SolveError::Unsolvable)
+        );
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        // Blanks out cells of a random full grid and solves it again.
+        #[test]
+        fn solutions_verify(
+            seed in any::<u64>(),
+            blanks in prop::collection::vec(any::<bool>(), 81),
+        ) {
+            let options = SolveOptions {
+                heuristic: Heuristic::Random,
+                seed: Some(seed),
+                ..SolveOptions::default()
+            };
+            let mut grid = State::from(&"0".repeat(81) as &str);
+            Solver::new().with_options(options).solve(&mut grid).unwrap();
+            let puzzle: String = grid
+                .to_string()
+                .chars()
+                .zip(&blanks)
+                .map(|(c, blank)| if *blank { '0' } else { c })
+                .collect();
+            let puzzle = State::from(puzzle.as_str());
+
+            for backend in [Backend::Propagation, Backend::Dlx] {
+                let mut state = puzzle.clone();
+                Solver::with_backend(backend).solve(&mut state).unwrap();
+
+                prop_assert_eq!(state.verify_solution(), Ok(()));
+                for (given, (_, _, value)) in puzzle.iter_cells().zip(state.iter_cells()) {
+                    prop_assert!(given.2.is_none() || given.2 == value);
+                }
+            }
+        }
+    }
+}
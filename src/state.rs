@@ -1,98 +1,596 @@
-use crate::constraints::Constraints;
-use anyhow::Result;
-use log::info;
-use std::{collections::HashSet, fmt::Display};
+use crate::canonical;
+use crate::constraints::{self, Constraints};
+use crate::parse;
+use crate::search::{self, Interrupt};
+use crate::strategies::{self, Unit};
+#[cfg(feature = "std")]
+use alloc::collections::BTreeSet;
+use alloc::{collections::VecDeque, format, string::String, vec, vec::Vec};
+use core::{fmt::Display, ops::RangeBounds, str::FromStr};
 use thiserror::Error;
+use tracing::{trace, trace_span};
 
-#[derive(Error, Debug)]
-enum ConstraintError {
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// A cell that can't hold the one digit left to it and, when it is known, the
+// peer already holding that digit.
+#[derive(Error, Debug, PartialEq)]
+pub(crate) enum ConstraintError {
     #[error("cell at index {0} is already fully constrained as {1}")]
-    Conflict(usize, u8),
+    Conflict(usize, u8, Option<usize>),
+    #[error("cell at index {0} has no candidates left")]
+    NoCandidates(usize),
+}
+
+impl ConstraintError {
+    // Locates the error within the grid, along with the unit a conflict
+    // happened in.
+    pub(crate) fn in_grid(self, constraints: &Constraints) -> SolveError {
+        let size = constraints.size();
+        match self {
+            ConstraintError::Conflict(idx, value, peer) => SolveError::Contradiction {
+                row: idx / size,
+                col: idx % size,
+                value,
+                unit: peer.and_then(|p| strategies::shared_unit(constraints, idx, p)),
+            },
+            ConstraintError::NoCandidates(idx) => SolveError::NoCandidates {
+                row: idx / size,
+                col: idx % size,
+            },
+        }
+    }
+}
+
+// Why a puzzle could not be parsed or solved. Rows and columns count from
+// zero, but are shown counting from one. A contradiction names the unit the
+// digit is already in, unless it clashes through another rule such as a
+// knight's move.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum SolveError {
+    #[error("cell r{}c{} {}", .row + 1, .col + 1, contradiction(*.value, .unit))]
+    Contradiction {
+        row: usize,
+        col: usize,
+        value: u8,
+        unit: Option<Unit>,
+    },
+    #[error("cell r{}c{} has no candidates left", .row + 1, .col + 1)]
+    NoCandidates { row: usize, col: usize },
+    #[error("puzzle has no solution")]
+    Unsolvable,
+    #[error("puzzle has multiple solutions")]
+    MultipleSolutions,
+    #[error("no solution found after {0} iterations")]
+    IterationLimit(usize),
+    #[error("solving timed out")]
+    TimedOut,
+    #[error("solving was cancelled")]
+    Cancelled,
+    #[error("{0}")]
+    InvalidInput(String),
+}
+
+fn contradiction(value: u8, unit: &Option<Unit>) -> String {
+    match unit {
+        Some(unit) => format!("can only hold {value}, which is already in {unit}"),
+        None => format!("is already fully constrained as {value}"),
+    }
+}
+
+// Why a digit could not be written into a cell.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum PlacementError {
+    #[error("r{}c{} is outside a grid of size {size}", .row + 1, .col + 1)]
+    OutOfBounds { row: usize, col: usize, size: usize },
+    #[error("{value} is not a digit in a grid of size {size}")]
+    InvalidDigit { value: u8, size: usize },
+    #[error("r{}c{} already holds {value}", .row + 1, .col + 1)]
+    Conflict { row: usize, col: usize, value: u8 },
 }
 
 #[derive(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(into = "StateRepr", try_from = "StateRepr")
+)]
 pub struct State {
     cells: Vec<GridCell>,
     constraints: Constraints,
+    // While a search is running, the index and previous mask of every cell
+    // it changes, so a branch can be undone without cloning the grid.
+    trail: Option<Vec<(usize, u32)>>,
+    // Which cells were filled in the puzzle as given, before any solving.
+    givens: Vec<bool>,
+}
+
+// The trail belongs to the search that started it, so clones start without.
+impl Clone for State {
+    fn clone(&self) -> Self {
+        State {
+            cells: self.cells.clone(),
+            constraints: self.constraints.clone(),
+            trail: None,
+            givens: self.givens.clone(),
+        }
+    }
+}
+
+// The candidates of every cell at some point, to go back to with
+// `State::restore`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateSnapshot {
+    cells: Vec<GridCell>,
+}
+
+pub(crate) const MAX_SIZE: usize = u32::BITS as usize - 1;
+
+impl FromStr for State {
+    type Err = SolveError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        // pencil marks are bracketed too, but never have commas or nested
+        // brackets
+        if value.contains('[') && !value.contains(',') && !parse::is_nested_array(value) {
+            return State::parse_marks(value);
+        }
+
+        let value = parse::normalize(value);
+        let len = value.chars().count();
+        let size = (1..=len).find(|n| n * n >= len).unwrap_or(0);
+        if size == 0 || size * size != len {
+            return Err(SolveError::InvalidInput(format!(
+                "puzzle length {len} is not a square number"
+            )));
+        }
+
+        State::parse_with_size(&value, size)
+    }
 }
 
 impl From<&str> for State {
     fn from(value: &str) -> Self {
-        let mut cells = vec![];
-        for char in value.chars() {
-            let digit = char.to_digit(10).expect("input should be digits only");
-            if digit == 0 {
-                cells.push(GridCell::new())
-            } else {
-                cells.push(GridCell::new_collapsed(digit as u8))
-            }
+        value.parse().expect("puzzle should be valid")
+    }
+}
+
+impl State {
+    pub fn with_size(value: &str, size: usize) -> Self {
+        State::parse_with_size(value, size).expect("puzzle should be valid")
+    }
+
+    // Parses a puzzle for a grid of `size` digits. Digits above 9 are written
+    // as letters starting from `A`, and blanks as `0`, `.` or `_`, ignoring
+    // whitespace and grid decorations. In a 16×16 grid that uses `.` for
+    // blanks, `0` is read as 16 so hex grids written 0-F parse as expected.
+    pub fn parse_with_size(value: &str, size: usize) -> Result<Self, SolveError> {
+        if size == 0 || size > MAX_SIZE {
+            return Err(SolveError::InvalidInput(format!(
+                "grid size must be between 1 and {MAX_SIZE}"
+            )));
+        }
+
+        let value = parse::normalize(value);
+        let hex = size == 16 && value.contains('.');
+        let values = value
+            .chars()
+            .map(|c| match (c, digit_value(c)) {
+                ('0', _) if hex => Ok(16),
+                (_, Some(v)) if v as usize <= size => Ok(v),
+                _ => Err(SolveError::InvalidInput(format!(
+                    "invalid digit '{c}' for a grid of size {size}"
+                ))),
+            })
+            .collect::<Result<Vec<u8>, SolveError>>()?;
+
+        if values.len() != size * size {
+            return Err(SolveError::InvalidInput(format!(
+                "puzzle has {} cells but a grid of size {size} needs {}",
+                values.len(),
+                size * size
+            )));
         }
 
+        Ok(State::from_values(&values))
+    }
+
+    pub(crate) fn from_values(values: &[u8]) -> Self {
+        let size = (1..=values.len())
+            .find(|n| n * n >= values.len())
+            .unwrap_or(0);
+        let cells = values
+            .iter()
+            .map(|&v| match v {
+                0 => GridCell::new(size),
+                v => GridCell::new_collapsed(v),
+            })
+            .collect();
+
         State {
-            cells: cells,
-            constraints: Constraints::new(),
+            cells,
+            constraints: Constraints::for_size(size),
+            trail: None,
+            givens: values.iter().map(|v| *v != 0).collect(),
         }
     }
-}
 
-impl State {
-    fn apply_constraints(&mut self, val: u8, idx: usize) -> Result<(), ConstraintError> {
+    // A puzzle with `values` as its givens under the same rules.
+    pub(crate) fn with_values(&self, values: &[u8]) -> State {
+        State::from_values(values).with_constraints(self.constraints().clone())
+    }
+
+    pub fn size(&self) -> usize {
+        self.constraints.size()
+    }
+
+    // Puts the puzzle under rules built elsewhere, which are shared rather
+    // than copied, so many puzzles with the same variants can use one table.
+    // Panics if they are for another size of grid.
+    pub fn with_constraints(self, constraints: Constraints) -> Self {
+        assert_eq!(
+            constraints.size().pow(2),
+            self.cells.len(),
+            "constraints are for another size of grid"
+        );
+        State {
+            constraints,
+            ..self
+        }
+    }
+
+    // The digit in a cell, or `None` if it is still open. Rows and columns
+    // count from zero.
+    pub fn get(&self, row: usize, col: usize) -> Option<u8> {
+        self.cells[self.index(row, col)].determined_value()
+    }
+
+    // A copy of a cell and its candidates.
+    pub fn cell(&self, row: usize, col: usize) -> GridCell {
+        self.cells[self.index(row, col)]
+    }
+
+    // The digits still possible in a cell, in increasing order.
+    pub fn candidates(&self, row: usize, col: usize) -> impl Iterator<Item = u8> {
+        let mask = self.cells[self.index(row, col)].mask;
+
+        (1..u32::BITS as u8).filter(move |n| mask & 1 << n != 0)
+    }
+
+    // Every cell as its row, column and digit, row by row.
+    pub fn iter_cells(&self) -> impl Iterator<Item = (usize, usize, Option<u8>)> + '_ {
+        let size = self.size();
+
+        self.cells
+            .iter()
+            .enumerate()
+            .map(move |(idx, c)| (idx / size, idx % size, c.determined_value()))
+    }
+
+    // Writes `value` into a cell and removes it from the candidates of the
+    // cell's peers. Fails without changing anything if a peer already holds
+    // it. Overwriting another digit clears that digit first.
+    pub fn set(&mut self, row: usize, col: usize, value: u8) -> Result<(), PlacementError> {
+        let size = self.size();
+        if row >= size || col >= size {
+            return Err(PlacementError::OutOfBounds { row, col, size });
+        }
+        if value == 0 || value as usize > size {
+            return Err(PlacementError::InvalidDigit { value, size });
+        }
+
+        let idx = row * size + col;
+        if let Some(peer) = self
+            .constraints
+            .get_constrained_inds(idx)
+            .iter()
+            .find(|p| self.cells[**p].determined_value() == Some(value))
+        {
+            return Err(PlacementError::Conflict {
+                row: peer / size,
+                col: peer % size,
+                value,
+            });
+        }
+
+        if self.cells[idx]
+            .determined_value()
+            .is_some_and(|v| v != value)
+        {
+            self.clear(row, col);
+        }
+        self.set_mask(idx, 1 << value);
+        for peer in self.constraints.get_constrained_inds(idx) {
+            let mask = self.cells[*peer].mask & !(1 << value);
+            record(&mut self.trail, &mut self.cells, *peer, mask);
+        }
+
+        Ok(())
+    }
+
+    // Empties a cell and works out every open cell's candidates again from
+    // the digits left, since removing a digit can make any of them possible
+    // again. A cleared given is no longer a given.
+    pub fn clear(&mut self, row: usize, col: usize) {
+        let idx = self.index(row, col);
+        let full = GridCell::new(self.size()).mask;
+        self.set_mask(idx, full);
+        self.givens[idx] = false;
+
+        let values = self.values();
+        for idx in 0..self.cells.len() {
+            if values[idx] != 0 {
+                continue;
+            }
+
+            let taken = self
+                .constraints
+                .get_constrained_inds(idx)
+                .iter()
+                .fold(0, |taken, p| taken | 1 << values[*p]);
+            record(&mut self.trail, &mut self.cells, idx, full & !taken);
+        }
+    }
+
+    fn index(&self, row: usize, col: usize) -> usize {
+        let size = self.size();
+        assert!(
+            row < size && col < size,
+            "r{}c{} is outside a grid of size {size}",
+            row + 1,
+            col + 1
+        );
+
+        row * size + col
+    }
+
+    // Whether a cell was filled in the puzzle as given, rather than by
+    // solving or by `set`.
+    pub fn is_given(&self, row: usize, col: usize) -> bool {
+        self.givens[self.index(row, col)]
+    }
+
+    pub(crate) fn is_given_at(&self, idx: usize) -> bool {
+        self.givens[idx]
+    }
+
+    // The digits of the given cells, with every other cell 0.
+    pub(crate) fn given_values(&self) -> Vec<u8> {
+        self.cells
+            .iter()
+            .zip(&self.givens)
+            .map(|(c, given)| match given {
+                true => c.determined_value().unwrap_or(0),
+                false => 0,
+            })
+            .collect()
+    }
+
+    // Moves the given mark of each cell `idx` to `map(idx)`.
+    pub(crate) fn move_givens(&mut self, map: impl Fn(usize) -> usize) {
+        let mut givens = vec![false; self.givens.len()];
+        for (idx, given) in self.givens.iter().enumerate() {
+            givens[map(idx)] = *given;
+        }
+        self.givens = givens;
+    }
+
+    pub(crate) fn values(&self) -> Vec<u8> {
+        self.cells
+            .iter()
+            .map(|c| c.determined_value().unwrap_or(0))
+            .collect()
+    }
+
+    // Removes `val` from the candidates of `idx`'s peers, adding any peer
+    // left with a single candidate to `collapsed`.
+    fn apply_constraints(
+        &mut self,
+        val: u8,
+        idx: usize,
+        collapsed: &mut VecDeque<usize>,
+    ) -> Result<(), ConstraintError> {
         let inds = self.constraints.get_constrained_inds(idx);
 
         for ind in inds {
-            let cell = self
-                .cells
-                .get_mut(*ind)
-                .expect("ind should always be valid");
-
-            if !cell.deny(val) {
-                return Err(ConstraintError::Conflict(
-                    *ind,
-                    cell.determined_value().expect("should be determined"),
-                ));
+            let mut cell = self.cells[*ind];
+            match cell.deny(val) {
+                Denial::Absent => continue,
+                Denial::Last => return Err(ConstraintError::Conflict(*ind, val, Some(idx))),
+                Denial::Removed => {}
+                Denial::Collapsed(_) => collapsed.push_back(*ind),
             }
+
+            record(&mut self.trail, &mut self.cells, *ind, cell.mask);
         }
 
         Ok(())
     }
 
-    pub fn solve(&mut self) -> Result<(), String> {
-        self.propagate_constraints().map_err(|e| e.to_string())?;
+    pub fn solve(&mut self) -> Result<(), SolveError> {
+        self.solve_until(&Interrupt::default())
+    }
+
+    pub(crate) fn solve_until(&mut self, interrupt: &Interrupt) -> Result<(), SolveError> {
+        self.solve_ordered(&mut |_, _, _| {}, interrupt)
+    }
+
+    // Solves like `solve`, letting `order` reorder the candidates tried at
+    // each guess as `search::search_ordered` does.
+    pub(crate) fn solve_ordered<O>(
+        &mut self,
+        order: &mut O,
+        interrupt: &Interrupt,
+    ) -> Result<(), SolveError>
+    where
+        O: FnMut(&State, usize, &mut Vec<u8>),
+    {
+        self.propagate_constraints()
+            .map_err(|e| e.in_grid(self.constraints()))?;
+
+        if !self.is_solved() {
+            let mut solution = None;
+            search::search_ordered(self.clone(), order, interrupt, &mut |s| {
+                solution = Some(s.clone());
+                false
+            });
+            *self = solution.ok_or_else(|| interrupt.reason())?;
+        }
 
         Ok(())
     }
 
-    fn propagate_constraints(&mut self) -> Result<(), ConstraintError> {
-        let mut applied_inds: HashSet<usize> = HashSet::new();
-        let mut iteration = 0;
+    pub fn count_solutions(&self, limit: usize) -> usize {
+        self.count_until(limit, &Interrupt::default())
+            .expect("counting without an interrupt finishes")
+    }
 
-        while applied_inds.len() != 81 {
-            let inds: Vec<usize> = self
-                .find_fully_constrained_inds()
-                .into_iter()
-                .filter(|x| !applied_inds.contains(x))
-                .collect();
-            let mut inds = inds.into_iter();
-
-            info!(
-                "beginning iteration {}, entropy: {}, applied: {}",
-                iteration,
-                self.total_entropy(),
-                applied_inds.len()
-            );
-
-            while let Some(index) = inds.next() {
-                let val = self
-                    .cells
-                    .get(index)
-                    .expect("should be valid")
-                    .determined_value()
-                    .expect("should be determined");
-                self.apply_constraints(val, index)?;
-
-                applied_inds.insert(index);
+    // Counts solutions up to `limit`, failing if interrupted first.
+    pub(crate) fn count_until(
+        &self,
+        limit: usize,
+        interrupt: &Interrupt,
+    ) -> Result<usize, SolveError> {
+        let mut count = 0;
+        if limit == 0 {
+            return Ok(count);
+        }
+
+        let finished = search::search(self.clone(), interrupt, &mut |_| {
+            count += 1;
+            count < limit
+        });
+        if !finished && count < limit {
+            interrupt.check()?;
+        }
+
+        Ok(count)
+    }
+
+    pub fn has_unique_solution(&self) -> bool {
+        self.count_solutions(2) == 1
+    }
+
+    // Eliminates the values of determined cells from their peers, then
+    // narrows cage candidates to feasible sums and shape candidates to
+    // digits that fit the shape, until none of them makes progress. Cells
+    // determined along the way are queued rather than found by rescanning.
+    pub(crate) fn propagate_constraints(&mut self) -> Result<(), ConstraintError> {
+        let _span = trace_span!("propagate").entered();
+        let mut pending = VecDeque::from(self.find_fully_constrained_inds());
+        let mut applied = vec![false; self.cells.len()];
+        loop {
+            self.propagate_peers(&mut pending, &mut applied)?;
+            if !(self.prune_cages(&mut pending)? | self.prune_shapes(&mut pending)?) {
+                return Ok(());
             }
-            iteration += 1;
+        }
+    }
+
+    // Returns whether any candidate was removed, adding cells left with a
+    // single candidate to `collapsed`.
+    fn prune_cages(&mut self, collapsed: &mut VecDeque<usize>) -> Result<bool, ConstraintError> {
+        let mut changed = false;
+        if self.constraints.cages().is_empty() {
+            return Ok(changed);
+        }
+
+        let mut masks = self.candidate_masks();
+        for cage in self.constraints.cages() {
+            let narrowed = cage.supported(&masks);
+            changed |= narrow(
+                &mut self.trail,
+                &mut self.cells,
+                &mut masks,
+                &cage.cells,
+                narrowed,
+                collapsed,
+            )?;
+        }
+
+        Ok(changed)
+    }
+
+    // Returns whether any candidate was removed, adding cells left with a
+    // single candidate to `collapsed`.
+    fn prune_shapes(&mut self, collapsed: &mut VecDeque<usize>) -> Result<bool, ConstraintError> {
+        let mut changed = false;
+        if self.constraints.shapes().is_empty() {
+            return Ok(changed);
+        }
+
+        let mut masks = self.candidate_masks();
+        for shape in self.constraints.shapes() {
+            let narrowed = shape.supported(&masks);
+            changed |= narrow(
+                &mut self.trail,
+                &mut self.cells,
+                &mut masks,
+                &shape.cells(),
+                narrowed,
+                collapsed,
+            )?;
+        }
+
+        Ok(changed)
+    }
+
+    // Whether no two peers share a value, every cage adds up and every shape
+    // holds. Used to check solutions from searches that only know about
+    // units.
+    #[cfg(feature = "std")]
+    pub(crate) fn satisfies_rules(&self) -> bool {
+        let values = self.values();
+
+        let peers_differ = values.iter().enumerate().all(|(idx, v)| {
+            *v == 0
+                || self
+                    .constraints
+                    .get_constrained_inds(idx)
+                    .iter()
+                    .all(|p| values[*p] != *v)
+        });
+
+        peers_differ
+            && self.constraints.cages().iter().all(|cage| {
+                let digits: BTreeSet<u8> = cage.cells.iter().map(|i| values[*i]).collect();
+                digits.len() == cage.cells.len()
+                    && !digits.contains(&0)
+                    && digits.iter().map(|v| *v as u32).sum::<u32>() == cage.sum
+            })
+            && self
+                .constraints
+                .shapes()
+                .iter()
+                .all(|shape| shape.error(&values) == 0)
+    }
+
+    // Eliminates the value of each pending cell from its peers, in the order
+    // they were queued, queueing any peer left with a single candidate until
+    // none are pending. `applied`
+    // marks the cells already eliminated, so each is only done once.
+    fn propagate_peers(
+        &mut self,
+        pending: &mut VecDeque<usize>,
+        applied: &mut [bool],
+    ) -> Result<(), ConstraintError> {
+        trace!(
+            "propagating {} cells, entropy: {}",
+            pending.len(),
+            self.total_entropy()
+        );
+
+        while let Some(idx) = pending.pop_front() {
+            if applied[idx] {
+                continue;
+            }
+
+            let val = self.cells[idx]
+                .determined_value()
+                .expect("pending cells should be determined");
+            self.apply_constraints(val, idx, pending)?;
+            applied[idx] = true;
         }
 
         Ok(())
@@ -110,99 +608,603 @@ impl State {
     fn total_entropy(&self) -> u32 {
         self.cells.iter().map(|x| x.entropy() as u32).sum()
     }
+
+    pub fn is_solved(&self) -> bool {
+        self.cells.iter().all(|c| c.entropy() == 1)
+    }
+
+    pub(crate) fn min_entropy_index(&self) -> Option<usize> {
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.entropy() > 1)
+            .min_by_key(|(_, c)| c.entropy())
+            .map(|(i, _)| i)
+    }
+
+    // How many of a cell's open peers still have `val` as a candidate.
+    #[cfg(feature = "std")]
+    pub(crate) fn peers_with(&self, idx: usize, val: u8) -> usize {
+        self.constraints
+            .get_constrained_inds(idx)
+            .iter()
+            .filter(|p| self.cells[**p].entropy() > 1 && self.cells[**p].contains(val))
+            .count()
+    }
+
+    pub(crate) fn candidates_at(&self, idx: usize) -> Vec<u8> {
+        self.cells[idx].candidates()
+    }
+
+    pub(crate) fn collapse(&mut self, idx: usize, val: u8) {
+        self.set_mask(idx, 1 << val);
+    }
+
+    pub(crate) fn eliminate(&mut self, idx: usize, val: u8) -> Result<(), ConstraintError> {
+        let mut cell = self.cells[idx];
+        match cell.deny(val) {
+            Denial::Absent => Ok(()),
+            Denial::Last => Err(ConstraintError::Conflict(idx, val, None)),
+            Denial::Removed | Denial::Collapsed(_) => {
+                self.set_mask(idx, cell.mask);
+                Ok(())
+            }
+        }
+    }
+
+    // Keeps only the candidates of `idx` also in `mask`, returning whether any
+    // were removed.
+    #[cfg(feature = "std")]
+    pub(crate) fn restrict(&mut self, idx: usize, mask: u32) -> Result<bool, ConstraintError> {
+        let cell = self.cells[idx];
+        if cell.mask & mask == cell.mask {
+            return Ok(false);
+        }
+        if cell.mask & mask == 0 {
+            return Err(ConstraintError::NoCandidates(idx));
+        }
+
+        self.set_mask(idx, cell.mask & mask);
+        Ok(true)
+    }
+
+    pub fn constraints(&self) -> &Constraints {
+        &self.constraints
+    }
+
+    pub(crate) fn candidate_masks(&self) -> Vec<u32> {
+        self.cells.iter().map(|c| c.mask).collect()
+    }
+
+    // Copies the candidate masks into `masks`, reusing its allocation.
+    #[cfg(feature = "std")]
+    pub(crate) fn copy_masks_into(&self, masks: &mut Vec<u32>) {
+        masks.clear();
+        masks.extend(self.cells.iter().map(|c| c.mask));
+    }
+
+    pub(crate) fn set_masks(&mut self, masks: &[u32]) {
+        for (idx, mask) in masks.iter().enumerate() {
+            self.set_mask(idx, *mask);
+        }
+    }
+
+    // Every candidate change goes through here so the trail sees it.
+    fn set_mask(&mut self, idx: usize, mask: u32) {
+        record(&mut self.trail, &mut self.cells, idx, mask);
+    }
+
+    // Whether the givens of the two puzzles have the same canonical form. Only
+    // classic grids up to 9×9 have one, so others are never equivalent.
+    pub fn is_equivalent_to(&self, other: &State) -> bool {
+        if self.size() != other.size() {
+            return false;
+        }
+
+        match (
+            canonical::canonicalize(self),
+            canonical::canonicalize(other),
+        ) {
+            (Ok(a), Ok(b)) => a.puzzle.values() == b.puzzle.values(),
+            _ => false,
+        }
+    }
+
+    pub fn snapshot(&self) -> StateSnapshot {
+        StateSnapshot {
+            cells: self.cells.clone(),
+        }
+    }
+
+    // Puts back the candidates from a snapshot of this state.
+    pub fn restore(&mut self, snapshot: &StateSnapshot) {
+        assert_eq!(
+            snapshot.cells.len(),
+            self.cells.len(),
+            "snapshot should be of a grid the same size"
+        );
+
+        for (idx, cell) in snapshot.cells.iter().enumerate() {
+            self.set_mask(idx, cell.mask);
+        }
+    }
+
+    // Starts recording changes so that they can be undone with `undo_to`.
+    pub(crate) fn start_trail(&mut self) {
+        self.trail.get_or_insert_with(Vec::new).clear();
+    }
+
+    // A point in the trail to undo back to.
+    pub(crate) fn mark(&self) -> usize {
+        self.trail.as_ref().map_or(0, Vec::len)
+    }
+
+    pub(crate) fn undo_to(&mut self, mark: usize) {
+        if let Some(trail) = &mut self.trail {
+            for (idx, mask) in trail.drain(mark..).rev() {
+                self.cells[idx].mask = mask;
+            }
+        }
+    }
+
+    // Each cell changed since `mark`, once, as its index and its masks then
+    // and now, in the order the cells were first changed.
+    pub(crate) fn changes_since(&self, mark: usize) -> Vec<(usize, u32, u32)> {
+        let mut seen = vec![false; self.cells.len()];
+        let Some(trail) = &self.trail else {
+            return vec![];
+        };
+
+        trail[mark..]
+            .iter()
+            .filter(|(idx, _)| !core::mem::replace(&mut seen[*idx], true))
+            .map(|(idx, mask)| (*idx, *mask, self.cells[*idx].mask))
+            .collect()
+    }
+
+    pub fn filled_count(&self) -> usize {
+        self.cells.iter().filter(|c| c.entropy() == 1).count()
+    }
+}
+
+// Sets each of `inds` to its mask in `narrowed`, keeping `masks`, a copy of
+// every cell's mask, up to date. Returns whether any changed, adding those
+// left with a single candidate to `collapsed`.
+fn narrow(
+    trail: &mut Option<Vec<(usize, u32)>>,
+    cells: &mut [GridCell],
+    masks: &mut [u32],
+    inds: &[usize],
+    narrowed: Vec<u32>,
+    collapsed: &mut VecDeque<usize>,
+) -> Result<bool, ConstraintError> {
+    let mut changed = false;
+    for (idx, mask) in inds.iter().zip(narrowed) {
+        if mask == masks[*idx] {
+            continue;
+        }
+        if mask == 0 {
+            return Err(ConstraintError::NoCandidates(*idx));
+        }
+
+        record(trail, cells, *idx, mask);
+        masks[*idx] = mask;
+        if mask.is_power_of_two() {
+            collapsed.push_back(*idx);
+        }
+        changed = true;
+    }
+
+    Ok(changed)
+}
+
+// Sets a cell's mask, noting the old one on the trail if there is one. Takes
+// the fields apart so callers can hold other borrows of the state.
+fn record(trail: &mut Option<Vec<(usize, u32)>>, cells: &mut [GridCell], idx: usize, mask: u32) {
+    if cells[idx].mask == mask {
+        return;
+    }
+    if let Some(trail) = trail {
+        trail.push((idx, cells[idx].mask));
+    }
+
+    cells[idx].mask = mask;
 }
 
 impl Display for State {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let display: String = self
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let display: String = self.values().into_iter().map(digit_char).collect();
+
+        write!(f, "{}", display)
+    }
+}
+
+// Serialized form of a State: the grid size, every cell's candidates and the
+// indices of the given cells. Without `givens` every determined cell is taken
+// as given.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct StateRepr {
+    size: usize,
+    cells: Vec<Vec<u8>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    givens: Option<Vec<usize>>,
+}
+
+#[cfg(feature = "serde")]
+impl From<State> for StateRepr {
+    fn from(state: State) -> Self {
+        StateRepr {
+            size: state.size(),
+            cells: state.cells.iter().map(|c| c.candidates()).collect(),
+            givens: Some(
+                (0..state.cells.len())
+                    .filter(|idx| state.givens[*idx])
+                    .collect(),
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<StateRepr> for State {
+    type Error = String;
+
+    fn try_from(repr: StateRepr) -> Result<Self, Self::Error> {
+        if repr.size == 0 || repr.size > MAX_SIZE {
+            return Err(format!("grid size must be between 1 and {MAX_SIZE}"));
+        }
+        if repr.cells.len() != repr.size * repr.size {
+            return Err(format!(
+                "state has {} cells but a grid of size {} needs {}",
+                repr.cells.len(),
+                repr.size,
+                repr.size * repr.size
+            ));
+        }
+        if let Some(idx) = repr
             .cells
             .iter()
-            .map(|c| c.determined_value().unwrap_or(0).to_string())
-            .collect();
+            .position(|c| c.is_empty() || c.iter().any(|&v| v == 0 || v as usize > repr.size))
+        {
+            return Err(format!("cell at index {idx} has invalid candidates"));
+        }
 
-        write!(f, "{}", display)
+        let givens = match repr.givens {
+            Some(givens) => {
+                let mut mask = vec![false; repr.cells.len()];
+                for idx in givens {
+                    match repr.cells.get(idx) {
+                        Some(cell) if cell.len() == 1 => mask[idx] = true,
+                        _ => return Err(format!("given at index {idx} is not a determined cell")),
+                    }
+                }
+                mask
+            }
+            None => repr.cells.iter().map(|c| c.len() == 1).collect(),
+        };
+
+        Ok(State {
+            givens,
+            cells: repr
+                .cells
+                .into_iter()
+                .map(GridCell::try_from)
+                .collect::<Result<_, _>>()
+                .map_err(|e| format!("{e}"))?,
+            trail: None,
+            constraints: Constraints::for_size(repr.size),
+        })
+    }
+}
+
+pub(crate) fn digit_value(c: char) -> Option<u8> {
+    match c {
+        '.' => Some(0),
+        '0'..='9' => c.to_digit(10).map(|d| d as u8),
+        'a'..='z' | 'A'..='Z' => Some(c.to_ascii_uppercase() as u8 - b'A' + 10),
+        _ => None,
+    }
+}
+
+pub(crate) fn digit_char(value: u8) -> char {
+    match value {
+        0..=9 => (b'0' + value) as char,
+        _ => (b'A' + value - 10) as char,
     }
 }
 
-#[derive(Debug, PartialEq)]
-struct GridCell {
-    state: HashSet<u8>,
+// A cell's candidates, with digit n stored in bit n of the mask so bit 0 is
+// always unset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GridCell {
+    mask: u32,
+}
+
+// What removing a digit from a cell's candidates did, so callers can react to
+// a cell becoming determined without looking at every cell again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Denial {
+    // The digit wasn't a candidate, so nothing changed.
+    Absent,
+    // The digit was removed, leaving more than one candidate.
+    Removed,
+    // The digit was removed, leaving only this one.
+    Collapsed(u8),
+    // The digit was the only candidate left, so it was kept.
+    Last,
 }
 
 impl GridCell {
-    fn new() -> Self {
+    fn new(size: usize) -> Self {
         GridCell {
-            state: HashSet::from_iter(1..=9),
+            mask: ((1 << size) - 1) << 1,
         }
     }
 
     fn new_collapsed(n: u8) -> Self {
-        GridCell {
-            state: HashSet::from_iter(n..=n),
-        }
+        GridCell { mask: 1 << n }
+    }
+
+    pub(crate) fn from_mask(mask: u32) -> Self {
+        GridCell { mask }
     }
 
     #[allow(dead_code)]
     fn allow(&mut self, n: u8) -> bool {
-        self.state.insert(n)
+        let allowed = self.mask & 1 << n == 0;
+        self.mask |= 1 << n;
+        allowed
     }
 
-    fn deny(&mut self, n: u8) -> bool {
-        if self.state.len() == 1 {
-            if let Some(_) = self.state.get(&n) {
-                return false;
-            }
-            return true;
-        } else {
-            self.state.remove(&n);
-            return true;
+    fn deny(&mut self, n: u8) -> Denial {
+        if !self.contains(n) {
+            return Denial::Absent;
+        }
+        if self.mask == 1 << n {
+            return Denial::Last;
+        }
+
+        self.mask &= !(1 << n);
+        match self.determined_value() {
+            Some(value) => Denial::Collapsed(value),
+            None => Denial::Removed,
         }
     }
 
+    pub fn contains(&self, n: u8) -> bool {
+        n < u32::BITS as u8 && self.mask & 1 << n != 0
+    }
+
     fn entropy(&self) -> u8 {
-        self.state.len() as u8
+        self.mask.count_ones() as u8
+    }
+
+    pub fn is_determined(&self) -> bool {
+        self.mask.is_power_of_two()
     }
 
-    fn determined_value(&self) -> Option<u8> {
-        if self.state.len() == 1 {
-            Some(*self.state.iter().next().unwrap())
+    pub fn determined_value(&self) -> Option<u8> {
+        if self.is_determined() {
+            Some(self.mask.trailing_zeros() as u8)
         } else {
             None
         }
     }
-}
 
-impl Display for GridCell {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut out = vec![];
-        for ii in 1..=9 {
-            let str = match self.state.get(&ii) {
-                Some(i) => i.to_string(),
-                None => "·".to_string(),
-            };
-            out.push(format!("{} ", str));
-            if ii % 3 == 0 {
-                out.push("\n".to_string());
+    // The digits still possible, in increasing order.
+    pub fn candidates(&self) -> Vec<u8> {
+        (1..u32::BITS as u8).filter(|n| self.contains(*n)).collect()
+    }
+
+    // The smallest and largest digits still possible, or `None` once a cell
+    // has no candidates.
+    pub fn min_candidate(&self) -> Option<u8> {
+        (self.mask != 0).then(|| self.mask.trailing_zeros() as u8)
+    }
+
+    pub fn max_candidate(&self) -> Option<u8> {
+        (self.mask != 0).then(|| (u32::BITS - 1 - self.mask.leading_zeros()) as u8)
+    }
+
+    // The digits 1 to `size` as a block of pencil marks, one row per block
+    // width, with `·` for the digits ruled out and letters above 9.
+    pub fn marks(&self, size: usize) -> String {
+        let (box_w, _) = constraints::block_shape_for(size);
+        let mut out = String::new();
+        for digit in 1..=size as u8 {
+            out.push(match self.contains(digit) {
+                true => digit_char(digit),
+                false => '·',
+            });
+            out.push(' ');
+            if (digit as usize).is_multiple_of(box_w) {
+                out.push('\n');
+            }
+        }
+
+        out
+    }
+
+    // Keeps only the candidates within `range`, returning whether any were
+    // removed. This can leave the cell with none, which a rule built on it
+    // should treat as a contradiction.
+    pub fn retain_in_range(&mut self, range: impl RangeBounds<u8>) -> bool {
+        let before = self.mask;
+        for n in (1..u32::BITS as u8).filter(|n| !range.contains(n)) {
+            self.mask &= !(1 << n);
+        }
+
+        self.mask != before
+    }
+
+    // Every way of picking a candidate from each of `cells` so that they add
+    // up to `sum`, as the digits picked in the order of the cells. With
+    // `distinct`, as in a killer cage, no digit is picked twice. Picks that
+    // can no longer reach `sum` are cut short using the smallest and largest
+    // candidates of the cells left.
+    pub fn sum_combinations(cells: &[GridCell], sum: u32, distinct: bool) -> Vec<Vec<u8>> {
+        let bound = |pick: fn(&GridCell) -> Option<u8>| {
+            let mut bounds = vec![0; cells.len() + 1];
+            for (i, cell) in cells.iter().enumerate().rev() {
+                bounds[i] = bounds[i + 1] + pick(cell).map_or(0, u32::from);
             }
+            bounds
+        };
+        let sums = SumBounds {
+            min: bound(GridCell::min_candidate),
+            max: bound(GridCell::max_candidate),
+        };
+
+        let mut combinations = vec![];
+        if cells.iter().all(|c| c.mask != 0) {
+            add_sums(cells, sum, distinct, &sums, &mut vec![], &mut combinations);
         }
 
-        let out: String = out.into_iter().collect();
-        write!(f, "{}", out)
+        combinations
     }
 }
 
-impl From<Vec<u8>> for GridCell {
-    fn from(value: Vec<u8>) -> Self {
-        GridCell {
-            state: HashSet::from_iter(value.into_iter()),
+// The least and most the cells from each index onwards can add up to.
+struct SumBounds {
+    min: Vec<u32>,
+    max: Vec<u32>,
+}
+
+fn add_sums(
+    cells: &[GridCell],
+    remaining: u32,
+    distinct: bool,
+    sums: &SumBounds,
+    picked: &mut Vec<u8>,
+    combinations: &mut Vec<Vec<u8>>,
+) {
+    let depth = picked.len();
+    if depth == cells.len() {
+        if remaining == 0 {
+            combinations.push(picked.clone());
+        }
+        return;
+    }
+    if remaining < sums.min[depth] || remaining > sums.max[depth] {
+        return;
+    }
+
+    for n in cells[depth].candidates() {
+        if n as u32 > remaining || (distinct && picked.contains(&n)) {
+            continue;
+        }
+        picked.push(n);
+        add_sums(
+            cells,
+            remaining - n as u32,
+            distinct,
+            sums,
+            picked,
+            combinations,
+        );
+        picked.pop();
+    }
+}
+
+// A cell knows its candidates but not its grid, so it is laid out for a 9×9
+// grid unless it holds larger digits; see `GridCell::marks`.
+impl Display for GridCell {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let size = self.max_candidate().map_or(9, |max| (max as usize).max(9));
+        write!(f, "{}", self.marks(size))
+    }
+}
+
+// The cell with candidates `value`, each of which must be a digit from 1 up
+// to `MAX_SIZE` to fit in the mask.
+impl TryFrom<Vec<u8>> for GridCell {
+    type Error = SolveError;
+
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        if let Some(n) = value.iter().find(|&&n| n == 0 || n as usize > MAX_SIZE) {
+            return Err(SolveError::InvalidInput(format!(
+                "candidate {n} is not between 1 and {MAX_SIZE}"
+            )));
         }
+
+        Ok(GridCell {
+            mask: value.iter().fold(0, |mask, n| mask | 1 << n),
+        })
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::state::GridCell;
-    use crate::state::State;
+    use crate::constraints::Constraints;
+    use crate::killer::Cage;
+    use crate::state::{Denial, GridCell};
+    use crate::state::{PlacementError, SolveError, State};
+    use crate::strategies::Unit;
+
+    fn assert_valid_solution(state: &State) {
+        let values = state.values();
+        let size = state.size();
+
+        for unit in state.constraints.units() {
+            let mut digits: Vec<u8> = unit.iter().map(|i| values[*i]).collect();
+            digits.sort_unstable();
+            assert_eq!(digits, (1..=size as u8).collect::<Vec<u8>>());
+        }
+    }
+
+    #[test]
+    fn can_do_cell_arithmetic() {
+        let mut cell = GridCell::try_from(vec![2, 5, 7, 9]).unwrap();
+        assert_eq!(cell.min_candidate(), Some(2));
+        assert_eq!(cell.max_candidate(), Some(9));
+
+        assert!(cell.retain_in_range(3..=7));
+        assert_eq!(cell.candidates(), vec![5, 7]);
+        assert!(!cell.retain_in_range(..8));
+        assert!(cell.retain_in_range(8..));
+        assert_eq!(cell.min_candidate(), None);
+        assert_eq!(cell.max_candidate(), None);
+
+        let cells = [GridCell::new(9), GridCell::new(9)];
+        assert_eq!(
+            GridCell::sum_combinations(&cells, 4, true),
+            vec![vec![1, 3], vec![3, 1]]
+        );
+        assert_eq!(GridCell::sum_combinations(&cells, 4, false).len(), 3);
+        assert_eq!(
+            GridCell::sum_combinations(&cells, 18, false),
+            vec![vec![9, 9]]
+        );
+        assert!(GridCell::sum_combinations(&cells, 18, true).is_empty());
+
+        let cells = [
+            GridCell::try_from(vec![1, 2]).unwrap(),
+            GridCell::new(9),
+            GridCell::try_from(vec![9]).unwrap(),
+        ];
+        // 2, 2, 9 repeats a digit
+        assert_eq!(
+            GridCell::sum_combinations(&cells, 13, true),
+            vec![vec![1, 3, 9]]
+        );
+        assert!(GridCell::sum_combinations(&[cell], 0, true).is_empty());
+        assert_eq!(
+            GridCell::sum_combinations(&[], 0, true),
+            vec![Vec::<u8>::new()]
+        );
+    }
+
+    #[test]
+    fn can_display_gridcell() {
+        let cell = GridCell::try_from(vec![1, 5, 9]).unwrap();
+        assert_eq!(cell.to_string(), "1 · · \n· 5 · \n· · 9 \n");
+        assert_eq!(cell.marks(4), "1 · \n· · \n");
+
+        let cell = GridCell::try_from(vec![2, 10, 16]).unwrap();
+        assert_eq!(cell.to_string(), "· 2 · · \n· · · · \n· A · · \n· · · G \n");
+        assert_eq!(cell.marks(6), "· 2 · \n· · · \n");
+    }
 
     #[test]
     fn can_alter_gridcell() {
@@ -210,7 +1212,23 @@ mod test {
         gridcell.allow(6);
         gridcell.allow(8);
         gridcell.deny(6);
-        assert_eq!(gridcell, GridCell::from(vec![7, 8]));
+        assert_eq!(gridcell, GridCell::try_from(vec![7, 8]).unwrap());
+        assert!(GridCell::try_from(vec![0]).is_err());
+        assert!(GridCell::try_from(vec![1, 32]).is_err());
+
+        assert!(!gridcell.allow(8));
+        assert!(!gridcell.is_determined());
+        assert_eq!(gridcell.deny(8), Denial::Collapsed(7));
+        assert_eq!(gridcell.deny(7), Denial::Last);
+        assert!(gridcell.is_determined());
+        assert_eq!(gridcell.determined_value(), Some(7));
+        assert_eq!(gridcell.deny(3), Denial::Absent);
+        assert_eq!(gridcell.candidates(), vec![7]);
+        gridcell.allow(2);
+        gridcell.allow(3);
+        assert_eq!(gridcell.deny(2), Denial::Removed);
+        assert_eq!(GridCell::new(9).determined_value(), None);
+        assert_eq!(GridCell::new(16).entropy(), 16);
     }
 
     #[test]
@@ -269,10 +1287,277 @@ mod test {
 
         assert_eq!(
             state.solve(),
-            Err("cell at index 76 is already fully constrained as 4".to_string())
+            Err(SolveError::Contradiction {
+                row: 8,
+                col: 4,
+                value: 4,
+                unit: Some(Unit::Column(4))
+            })
         );
     }
 
+    #[test]
+    fn can_solve_with_search() {
+        let mut state = State::from(
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+        );
+
+        assert_eq!(state.solve(), Ok(()));
+        assert_eq!(
+            format!("{state}"),
+            "812753649943682175675491283154237896369845721287169534521974368438526917796318452"
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn can_read_cells() {
+        let mut state = State::from("1000000000000000");
+        state.propagate_constraints().unwrap();
+
+        assert_eq!(state.get(0, 0), Some(1));
+        assert_eq!(state.get(0, 1), None);
+        assert_eq!(state.candidates(0, 1).collect::<Vec<u8>>(), vec![2, 3, 4]);
+        assert_eq!(state.candidates(3, 3).count(), 4);
+        assert!(state.cell(0, 0).is_determined());
+        assert!(state.cell(0, 1).contains(3));
+        assert!(!state.cell(0, 1).contains(1));
+        assert_eq!(state.cell(0, 1).candidates(), vec![2, 3, 4]);
+        assert_eq!(state.filled_count(), 1);
+        assert!(!state.is_solved());
+
+        let cells: Vec<_> = state.iter_cells().take(2).collect();
+        assert_eq!(cells, vec![(0, 0, Some(1)), (0, 1, None)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "r5c1 is outside a grid of size 4")]
+    fn can_reject_cells_outside_grid() {
+        State::from("1000000000000000").get(4, 0);
+    }
+
+    #[test]
+    fn can_set_and_clear() {
+        let mut state = State::from("1000000000000000");
+        state.propagate_constraints().unwrap();
+
+        assert_eq!(state.set(0, 1, 2), Ok(()));
+        assert_eq!(state.candidates(0, 2).collect::<Vec<u8>>(), vec![3, 4]);
+        assert_eq!(state.candidates(1, 0).collect::<Vec<u8>>(), vec![3, 4]);
+
+        assert_eq!(
+            state.set(1, 1, 1),
+            Err(PlacementError::Conflict {
+                row: 0,
+                col: 0,
+                value: 1
+            })
+        );
+        assert_eq!(
+            state.set(0, 4, 1),
+            Err(PlacementError::OutOfBounds {
+                row: 0,
+                col: 4,
+                size: 4
+            })
+        );
+        assert_eq!(
+            state.set(0, 1, 5),
+            Err(PlacementError::InvalidDigit { value: 5, size: 4 })
+        );
+
+        state.set(0, 1, 3).unwrap();
+        assert_eq!(state.candidates(1, 0).collect::<Vec<u8>>(), vec![2, 4]);
+
+        state.clear(0, 1);
+        assert_eq!(state.get(0, 1), None);
+        assert_eq!(state.candidates(0, 1).collect::<Vec<u8>>(), vec![2, 3, 4]);
+        assert_eq!(state.candidates(1, 0).collect::<Vec<u8>>(), vec![2, 3, 4]);
+        assert_eq!(state.candidates(3, 3).count(), 4);
+    }
+
+    #[test]
+    fn can_share_constraints() {
+        let x = Constraints::new().with_diagonals();
+        let puzzle =
+            "301086504046521070500000001400800002080347900009050038004090200008734090007208103";
+        let a = State::from(puzzle).with_constraints(x.clone());
+        let b = State::from(&"0".repeat(81) as &str).with_constraints(x.clone());
+
+        assert!(a.constraints().shares_table(b.constraints()));
+        assert!(a.clone().constraints().shares_table(&x));
+        assert!(State::from(puzzle)
+            .with_variants(&[])
+            .constraints()
+            .shares_table(&Constraints::new()));
+    }
+
+    #[test]
+    fn can_track_givens() {
+        let mut state = State::from("1000002000030000");
+        state.solve().unwrap();
+        assert!(state.is_given(0, 0));
+        assert!(state.is_given(1, 2));
+        assert!(!state.is_given(0, 1));
+        assert_eq!(
+            state.given_values(),
+            State::from("1000002000030000").values()
+        );
+
+        state.set(0, 0, 1).unwrap();
+        assert!(state.is_given(0, 0));
+        state.clear(0, 0);
+        assert!(!state.is_given(0, 0));
+        state.set(0, 0, 1).unwrap();
+        assert!(!state.is_given(0, 0));
+    }
+
+    #[test]
+    fn can_restore_snapshot() {
+        let mut state = State::from("1000000000000000");
+        let snapshot = state.snapshot();
+
+        state.solve().unwrap();
+        assert_ne!(state.snapshot(), snapshot);
+        state.restore(&snapshot);
+        assert_eq!(format!("{state}"), "1000000000000000");
+        assert_eq!(state.candidates_at(1), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn can_undo_trail() {
+        let mut state = State::from("1000000000000000");
+        let before = state.snapshot();
+        state.start_trail();
+
+        let mark = state.mark();
+        state.propagate_constraints().unwrap();
+        state.collapse(1, 2);
+        assert!(state.mark() > mark);
+
+        state.undo_to(mark);
+        assert_eq!(state.snapshot(), before);
+        assert_eq!(state.clone().mark(), 0);
+    }
+
+    #[test]
+    fn can_count_solutions() {
+        let state = State::from(
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+        );
+        assert_eq!(state.count_solutions(10), 1);
+        assert!(state.has_unique_solution());
+
+        let state = State::from(&"0".repeat(81) as &str);
+        assert_eq!(state.count_solutions(5), 5);
+        assert_eq!(state.count_solutions(0), 0);
+        assert!(!state.has_unique_solution());
+
+        let state = State::from(
+            "000040007480960501063570820009610203350097006000005094000000005804706910001040070",
+        );
+        assert_eq!(state.count_solutions(2), 0);
+    }
+
+    #[test]
+    fn can_solve_other_sizes() {
+        let mut state = State::from("1000002000030000");
+        assert_eq!(state.size(), 4);
+        assert_eq!(state.solve(), Ok(()));
+        assert_valid_solution(&state);
+
+        let mut state = State::with_size(&"0".repeat(36), 6);
+        assert_eq!(state.solve(), Ok(()));
+        assert_valid_solution(&state);
+
+        let mut state = State::from(&"0".repeat(256) as &str);
+        assert_eq!(state.size(), 16);
+        assert_eq!(state.solve(), Ok(()));
+        assert_valid_solution(&state);
+
+        let mut state = State::from(&"0".repeat(625) as &str);
+        assert_eq!(state.size(), 25);
+        assert_eq!(state.solve(), Ok(()));
+        assert_valid_solution(&state);
+    }
+
+    #[test]
+    fn can_parse_letter_digits() {
+        let letters: String = (0..16)
+            .map(|r| {
+                (0..16)
+                    .map(|c| match (r * 4 + r / 4 + c) % 16 + 1 {
+                        v if v % 3 == 0 => '0',
+                        v => super::digit_char(v as u8),
+                    })
+                    .collect::<String>()
+            })
+            .collect();
+        let hex = letters.replace('0', ".").replace('G', "0");
+
+        let mut state = State::from(letters.as_str());
+        assert_eq!(format!("{state}"), letters);
+        assert_eq!(State::from(hex.as_str()).values(), state.values());
+
+        assert_eq!(state.solve(), Ok(()));
+        assert_valid_solution(&state);
+        assert!(format!("{state}").contains('G'));
+    }
+
+    #[test]
+    fn can_reject_invalid_input() {
+        assert!("12x4".parse::<State>().is_err());
+        assert_eq!(
+            "12345".parse::<State>().unwrap_err(),
+            SolveError::InvalidInput("puzzle length 5 is not a square number".to_string())
+        );
+        assert!("5000000000000000".parse::<State>().is_err());
+        assert!(State::parse_with_size("0000", 4).is_err());
+        assert!(State::parse_with_size("0000", 2).is_ok());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn can_serialize_state() {
+        let mut state = State::from("1000002000030000");
+        state.propagate_constraints().unwrap();
+
+        let json = serde_json::to_string(&state).unwrap();
+        assert!(json.starts_with(r#"{"size":4,"cells":[[1],[2],[3],[4],"#));
+
+        let restored: State = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.candidate_masks(), state.candidate_masks());
+        assert!(json.ends_with(r#""givens":[0,6,11]}"#));
+        assert!(restored.is_given(2, 3) && !restored.is_given(0, 1));
+
+        let old: State = serde_json::from_str(r#"{"size":1,"cells":[[1]]}"#).unwrap();
+        assert!(old.is_given(0, 0));
+        assert!(serde_json::from_str::<State>(
+            r#"{"size":2,"cells":[[1],[1,2],[2],[1]],"givens":[1]}"#
+        )
+        .is_err());
+
+        assert!(serde_json::from_str::<State>(r#"{"size":2,"cells":[[1],[2],[3],[1]]}"#).is_err());
+        assert!(serde_json::from_str::<State>(r#"{"size":1,"cells":[[40]]}"#).is_err());
+    }
+
+    #[test]
+    fn can_propagate_collapsed_cells() {
+        let mut state = State::from("1230000000000000");
+        state.propagate_constraints().unwrap();
+        assert_eq!(state.get(0, 3), Some(4));
+        assert_eq!(state.candidates(3, 3).collect::<Vec<u8>>(), vec![1, 2, 3]);
+
+        let cages = vec![Cage {
+            sum: 7,
+            cells: vec![0, 1],
+        }];
+        let mut state = State::from("3000000000000000").with_cages(cages);
+        state.propagate_constraints().unwrap();
+        assert_eq!(state.get(0, 1), Some(4));
+        assert_eq!(state.candidates(0, 2).collect::<Vec<u8>>(), vec![1, 2]);
+    }
+
     #[test]
     fn can_find_constrained_inds() {
         let state = State::from(
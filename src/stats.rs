@@ -0,0 +1,42 @@
+use crate::strategies::Technique;
+use alloc::collections::BTreeMap;
+use core::fmt::Display;
+use core::time::Duration;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// Work done solving a puzzle: the logical deductions made first, then the
+// search that finished it. Search counts are only kept by the propagation
+// backend.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SolveStats {
+    pub elapsed: Duration,
+    pub techniques: BTreeMap<Technique, usize>,
+    // searched grids that constraints were propagated through
+    pub propagations: usize,
+    pub guesses: usize,
+    // guesses that led to a contradiction
+    pub backtracks: usize,
+    pub max_depth: usize,
+}
+
+impl SolveStats {
+    pub fn deductions(&self) -> usize {
+        self.techniques.values().sum()
+    }
+}
+
+impl Display for SolveStats {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        writeln!(f, "time: {:?}", self.elapsed)?;
+        writeln!(f, "deductions: {}", self.deductions())?;
+        for (technique, count) in &self.techniques {
+            writeln!(f, "  {technique}: {count}")?;
+        }
+        writeln!(f, "propagation passes: {}", self.propagations)?;
+        writeln!(f, "guesses: {}", self.guesses)?;
+        writeln!(f, "backtracks: {}", self.backtracks)?;
+        write!(f, "max search depth: {}", self.max_depth)
+    }
+}
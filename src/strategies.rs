@@ -0,0 +1,934 @@
+use crate::constraints::Constraints;
+use crate::state::{digit_char, ConstraintError, State};
+use crate::trace::cell_name;
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{fmt::Display, str::FromStr};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
+use tracing::{debug, trace_span};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Technique {
+    NakedSingle,
+    HiddenSingle,
+    LockedCandidates,
+    NakedPair,
+    HiddenPair,
+    NakedTriple,
+    HiddenTriple,
+    XWing,
+    Swordfish,
+    XyWing,
+    XyzWing,
+    SimpleColoring,
+}
+
+impl Technique {
+    pub fn weight(&self) -> u32 {
+        match self {
+            Technique::NakedSingle => 1,
+            Technique::HiddenSingle => 2,
+            Technique::LockedCandidates => 5,
+            Technique::NakedPair => 10,
+            Technique::HiddenPair => 15,
+            Technique::NakedTriple => 20,
+            Technique::HiddenTriple => 25,
+            Technique::XWing => 40,
+            Technique::Swordfish => 60,
+            Technique::XyWing => 70,
+            Technique::XyzWing => 80,
+            Technique::SimpleColoring => 90,
+        }
+    }
+}
+
+impl FromStr for Technique {
+    type Err = String;
+
+    // Accepts the names `Display` prints, with spaces, hyphens or
+    // underscores between words.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace([' ', '-'], "_").as_str() {
+            "naked_single" => Ok(Technique::NakedSingle),
+            "hidden_single" => Ok(Technique::HiddenSingle),
+            "locked_candidates" => Ok(Technique::LockedCandidates),
+            "naked_pair" => Ok(Technique::NakedPair),
+            "hidden_pair" => Ok(Technique::HiddenPair),
+            "naked_triple" => Ok(Technique::NakedTriple),
+            "hidden_triple" => Ok(Technique::HiddenTriple),
+            "x_wing" | "xwing" => Ok(Technique::XWing),
+            "swordfish" => Ok(Technique::Swordfish),
+            "xy_wing" | "xywing" => Ok(Technique::XyWing),
+            "xyz_wing" | "xyzwing" => Ok(Technique::XyzWing),
+            "simple_coloring" | "coloring" => Ok(Technique::SimpleColoring),
+            _ => Err(format!("unknown technique: {s}")),
+        }
+    }
+}
+
+impl Display for Technique {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let name = match self {
+            Technique::NakedSingle => "naked single",
+            Technique::HiddenSingle => "hidden single",
+            Technique::LockedCandidates => "locked candidates",
+            Technique::NakedPair => "naked pair",
+            Technique::HiddenPair => "hidden pair",
+            Technique::NakedTriple => "naked triple",
+            Technique::HiddenTriple => "hidden triple",
+            Technique::XWing => "x-wing",
+            Technique::Swordfish => "swordfish",
+            Technique::XyWing => "xy-wing",
+            Technique::XyzWing => "xyz-wing",
+            Technique::SimpleColoring => "simple coloring",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+// A row, column or block of the grid, numbered from zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Row(usize),
+    Column(usize),
+    Block(usize),
+    Diagonal(usize),
+    Window(usize),
+}
+
+impl Display for Unit {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Unit::Row(i) => write!(f, "row {}", i + 1),
+            Unit::Column(i) => write!(f, "column {}", i + 1),
+            Unit::Block(i) => write!(f, "block {}", i + 1),
+            Unit::Diagonal(i) => write!(f, "diagonal {}", i + 1),
+            Unit::Window(i) => write!(f, "window {}", i + 1),
+        }
+    }
+}
+
+// Every unit of the grid, labelled by kind.
+pub(crate) fn labelled_units(constraints: &Constraints) -> impl Iterator<Item = (Unit, &[usize])> {
+    fn label(
+        kind: fn(usize) -> Unit,
+        units: &[Vec<usize>],
+    ) -> impl Iterator<Item = (Unit, &[usize])> {
+        units
+            .iter()
+            .enumerate()
+            .map(move |(i, u)| (kind(i), u.as_slice()))
+    }
+
+    label(Unit::Row, constraints.rows())
+        .chain(label(Unit::Column, constraints.cols()))
+        .chain(label(Unit::Block, constraints.blocks()))
+        .chain(label(Unit::Diagonal, constraints.diagonals()))
+        .chain(label(Unit::Window, constraints.windows()))
+}
+
+// The first unit holding both `a` and `b`, if they share one.
+pub(crate) fn shared_unit(constraints: &Constraints, a: usize, b: usize) -> Option<Unit> {
+    labelled_units(constraints)
+        .find(|(_, cells)| cells.contains(&a) && cells.contains(&b))
+        .map(|(unit, _)| unit)
+}
+
+// A single deduction: the technique that justifies it, the units it reasons
+// about and the placements and eliminations it makes as `(index, value)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub technique: Technique,
+    pub units: Vec<Unit>,
+    pub placements: Vec<(usize, u8)>,
+    pub eliminations: Vec<(usize, u8)>,
+}
+
+impl Step {
+    pub(crate) fn placement(technique: Technique, units: Vec<Unit>, idx: usize, val: u8) -> Self {
+        Step {
+            technique,
+            units,
+            placements: vec![(idx, val)],
+            eliminations: vec![],
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn elimination(
+        technique: Technique,
+        units: Vec<Unit>,
+        eliminations: Vec<(usize, u8)>,
+    ) -> Option<Self> {
+        if eliminations.is_empty() {
+            return None;
+        }
+
+        Some(Step {
+            technique,
+            units,
+            placements: vec![],
+            eliminations,
+        })
+    }
+
+    // Describes the step in words, naming cells as `r1c1` in a grid of `size`.
+    pub fn describe(&self, size: usize) -> String {
+        let mut out = self.technique.to_string();
+        if !self.units.is_empty() {
+            let units: Vec<String> = self.units.iter().map(|u| u.to_string()).collect();
+            out.push_str(&format!(" in {}", units.join(", ")));
+        }
+
+        let changes: Vec<String> = self
+            .placements
+            .iter()
+            .map(|(idx, val)| format!("{} = {}", cell_name(*idx, size), digit_char(*val)))
+            .chain(
+                self.eliminations
+                    .iter()
+                    .map(|(idx, val)| format!("{} ≠ {}", cell_name(*idx, size), digit_char(*val))),
+            )
+            .collect();
+
+        format!("{out}: {}", changes.join(", "))
+    }
+
+    pub(crate) fn apply(&self, state: &mut State) -> Result<(), ConstraintError> {
+        for (idx, val) in &self.placements {
+            state.collapse(*idx, *val);
+        }
+        for (idx, val) in &self.eliminations {
+            state.eliminate(*idx, *val)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Solves as far as possible without guessing, reporting every deduction made
+// to `on_step`. Returns whether the puzzle was completely solved.
+#[cfg(feature = "std")]
+pub(crate) fn solve_logically<F>(
+    state: &mut State,
+    on_step: &mut F,
+) -> Result<bool, ConstraintError>
+where
+    F: FnMut(&Step),
+{
+    solve_logically_upto(state, Technique::SimpleColoring, on_step)
+}
+
+// `solve_logically` using no technique harder than `max`.
+#[cfg(feature = "std")]
+pub(crate) fn solve_logically_upto<F>(
+    state: &mut State,
+    max: Technique,
+    on_step: &mut F,
+) -> Result<bool, ConstraintError>
+where
+    F: FnMut(&Step),
+{
+    loop {
+        let before = state.values();
+        state.propagate_constraints()?;
+        for (idx, val) in state.values().into_iter().enumerate() {
+            if before[idx] == 0 && val != 0 {
+                on_step(&Step::placement(Technique::NakedSingle, vec![], idx, val));
+            }
+        }
+
+        if state.is_solved() {
+            return Ok(true);
+        }
+
+        match find_step_upto(state, max) {
+            Some(step) => {
+                on_step(&step);
+                step.apply(state)?;
+            }
+            None => return Ok(false),
+        }
+    }
+}
+
+// Finds a single deduction using the easiest technique that makes progress.
+// Naked singles are left to constraint propagation.
+#[cfg(feature = "std")]
+pub(crate) fn find_step(state: &State) -> Option<Step> {
+    find_step_upto(state, Technique::SimpleColoring)
+}
+
+// `find_step` using no technique harder than `max`.
+#[cfg(feature = "std")]
+pub(crate) fn find_step_upto(state: &State, max: Technique) -> Option<Step> {
+    let grid = Grid::new(state);
+    let finders: [(Technique, &dyn Fn() -> Option<Step>); 11] = [
+        (Technique::HiddenSingle, &|| grid.hidden_single()),
+        (Technique::LockedCandidates, &|| grid.locked_candidates()),
+        (Technique::NakedPair, &|| {
+            grid.naked_subset(2, Technique::NakedPair)
+        }),
+        (Technique::HiddenPair, &|| {
+            grid.hidden_subset(2, Technique::HiddenPair)
+        }),
+        (Technique::NakedTriple, &|| {
+            grid.naked_subset(3, Technique::NakedTriple)
+        }),
+        (Technique::HiddenTriple, &|| {
+            grid.hidden_subset(3, Technique::HiddenTriple)
+        }),
+        (Technique::XWing, &|| grid.fish(2, Technique::XWing)),
+        (Technique::Swordfish, &|| grid.fish(3, Technique::Swordfish)),
+        (Technique::XyWing, &|| grid.xy_wing()),
+        (Technique::XyzWing, &|| grid.xyz_wing()),
+        (Technique::SimpleColoring, &|| grid.simple_coloring()),
+    ];
+
+    let step = finders
+        .iter()
+        .take_while(|(technique, _)| *technique <= max)
+        .find_map(|(technique, find)| {
+            let _span = trace_span!("strategy", %technique).entered();
+            find()
+        });
+    if let Some(step) = &step {
+        debug!(
+            technique = %step.technique,
+            placements = step.placements.len(),
+            eliminations = step.eliminations.len(),
+            "found step"
+        );
+    }
+
+    step
+}
+
+// Finds an unsolved cell whose peers leave it a single candidate.
+#[cfg(feature = "std")]
+pub(crate) fn naked_single(state: &State) -> Option<Step> {
+    let values = state.values();
+
+    (0..values.len())
+        .filter(|idx| values[*idx] == 0)
+        .find_map(|idx| {
+            let peers = state.constraints().get_constrained_inds(idx);
+            match state
+                .candidates_at(idx)
+                .into_iter()
+                .filter(|v| peers.iter().all(|p| values[*p] != *v))
+                .collect::<Vec<u8>>()[..]
+            {
+                [val] => Some(Step::placement(Technique::NakedSingle, vec![], idx, val)),
+                _ => None,
+            }
+        })
+}
+
+// Places every hidden single until none remain, propagating after each one.
+pub(crate) fn place_hidden_singles(state: &mut State) -> Result<(), ConstraintError> {
+    while let Some(step) = Grid::new(state).hidden_single() {
+        step.apply(state)?;
+        state.propagate_constraints()?;
+    }
+
+    Ok(())
+}
+
+struct Grid<'a> {
+    size: u8,
+    masks: Vec<u32>,
+    #[cfg(feature = "std")]
+    rows: &'a [Vec<usize>],
+    #[cfg(feature = "std")]
+    cols: &'a [Vec<usize>],
+    #[cfg(feature = "std")]
+    blocks: &'a [Vec<usize>],
+    constraints: &'a Constraints,
+}
+
+impl<'a> Grid<'a> {
+    fn new(state: &'a State) -> Self {
+        let constraints = state.constraints();
+
+        Grid {
+            size: state.size() as u8,
+            masks: state.candidate_masks(),
+            #[cfg(feature = "std")]
+            rows: constraints.rows(),
+            #[cfg(feature = "std")]
+            cols: constraints.cols(),
+            #[cfg(feature = "std")]
+            blocks: constraints.blocks(),
+            constraints,
+        }
+    }
+
+    fn units(&self) -> impl Iterator<Item = (Unit, &'a [usize])> {
+        labelled_units(self.constraints)
+    }
+
+    fn is_solved(&self, idx: usize) -> bool {
+        self.masks[idx].count_ones() == 1
+    }
+
+    fn has(&self, idx: usize, val: u8) -> bool {
+        self.masks[idx] & 1 << val != 0
+    }
+
+    fn cells_with(&self, unit: &[usize], val: u8) -> Vec<usize> {
+        unit.iter().copied().filter(|i| self.has(*i, val)).collect()
+    }
+
+    fn hidden_single(&self) -> Option<Step> {
+        for (label, unit) in self.units() {
+            for val in 1..=self.size {
+                if let [idx] = self.cells_with(unit, val)[..] {
+                    if !self.is_solved(idx) {
+                        return Some(Step::placement(
+                            Technique::HiddenSingle,
+                            vec![label],
+                            idx,
+                            val,
+                        ));
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
+
+// The techniques beyond hidden singles, which only the solver and ratings use.
+#[cfg(feature = "std")]
+impl<'a> Grid<'a> {
+    fn lines(&self) -> impl Iterator<Item = (Unit, &[usize])> {
+        let rows = self.rows.iter().enumerate().map(|(i, u)| (Unit::Row(i), u));
+        let cols = self
+            .cols
+            .iter()
+            .enumerate()
+            .map(|(i, u)| (Unit::Column(i), u));
+
+        rows.chain(cols).map(|(unit, u)| (unit, u.as_slice()))
+    }
+
+    fn locked_candidates(&self) -> Option<Step> {
+        let lines: Vec<(Unit, &[usize])> = self.lines().collect();
+
+        for val in 1..=self.size {
+            // pointing: a block's candidates for val all lie on one line
+            for (b, block) in self.blocks.iter().enumerate() {
+                let cells = self.cells_with(block, val);
+                if cells.len() < 2 {
+                    continue;
+                }
+
+                for (label, line) in &lines {
+                    if cells.iter().all(|i| line.contains(i)) {
+                        let eliminations = self.eliminate_outside(line, block, val);
+                        if let Some(step) = Step::elimination(
+                            Technique::LockedCandidates,
+                            vec![Unit::Block(b), *label],
+                            eliminations,
+                        ) {
+                            return Some(step);
+                        }
+                    }
+                }
+            }
+
+            // claiming: a line's candidates for val all lie in one block
+            for (label, line) in &lines {
+                let cells = self.cells_with(line, val);
+                if cells.len() < 2 {
+                    continue;
+                }
+
+                for (b, block) in self.blocks.iter().enumerate() {
+                    if cells.iter().all(|i| block.contains(i)) {
+                        let eliminations = self.eliminate_outside(block, line, val);
+                        if let Some(step) = Step::elimination(
+                            Technique::LockedCandidates,
+                            vec![*label, Unit::Block(b)],
+                            eliminations,
+                        ) {
+                            return Some(step);
+                        }
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    fn eliminate_outside(&self, unit: &[usize], keep: &[usize], val: u8) -> Vec<(usize, u8)> {
+        unit.iter()
+            .copied()
+            .filter(|i| !keep.contains(i) && self.has(*i, val))
+            .map(|i| (i, val))
+            .collect()
+    }
+
+    fn naked_subset(&self, size: usize, technique: Technique) -> Option<Step> {
+        for (label, unit) in self.units() {
+            let open: Vec<usize> = unit
+                .iter()
+                .copied()
+                .filter(|i| !self.is_solved(*i))
+                .collect();
+
+            for subset in combinations(&open, size) {
+                let mask = subset.iter().fold(0, |m, i| m | self.masks[*i]);
+                if mask.count_ones() as usize != size {
+                    continue;
+                }
+
+                let eliminations = open
+                    .iter()
+                    .filter(|i| !subset.contains(i))
+                    .flat_map(|i| {
+                        (1..=self.size)
+                            .filter(move |v| mask & 1 << v != 0 && self.has(*i, *v))
+                            .map(move |v| (*i, v))
+                    })
+                    .collect();
+
+                if let Some(step) = Step::elimination(technique, vec![label], eliminations) {
+                    return Some(step);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn hidden_subset(&self, size: usize, technique: Technique) -> Option<Step> {
+        for (label, unit) in self.units() {
+            let open_vals: Vec<u8> = (1..=self.size)
+                .filter(|v| {
+                    let cells = self.cells_with(unit, *v);
+                    cells.len() > 1 || cells.iter().any(|i| !self.is_solved(*i))
+                })
+                .collect();
+
+            for vals in combinations(&open_vals, size) {
+                let mask = vals.iter().fold(0u32, |m, v| m | 1 << v);
+                let cells: Vec<usize> = unit
+                    .iter()
+                    .copied()
+                    .filter(|i| self.masks[*i] & mask != 0)
+                    .collect();
+                if cells.len() != size {
+                    continue;
+                }
+
+                let eliminations = cells
+                    .iter()
+                    .flat_map(|i| {
+                        (1..=self.size)
+                            .filter(move |v| mask & 1 << v == 0 && self.has(*i, *v))
+                            .map(move |v| (*i, v))
+                    })
+                    .collect();
+
+                if let Some(step) = Step::elimination(technique, vec![label], eliminations) {
+                    return Some(step);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn fish(&self, size: usize, technique: Technique) -> Option<Step> {
+        let row = Unit::Row as fn(usize) -> Unit;
+        let col = Unit::Column as fn(usize) -> Unit;
+
+        for (base, cover, label) in [(self.rows, self.cols, row), (self.cols, self.rows, col)] {
+            for val in 1..=self.size {
+                let lines: Vec<(usize, Vec<usize>)> = base
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(b, line)| {
+                        let covers: Vec<usize> = self
+                            .cells_with(line, val)
+                            .iter()
+                            .map(|i| cover.iter().position(|c| c.contains(i)).unwrap())
+                            .collect();
+                        (2..=size).contains(&covers.len()).then_some((b, covers))
+                    })
+                    .collect();
+
+                for subset in combinations(&lines, size) {
+                    let mut covers: Vec<usize> =
+                        subset.iter().flat_map(|(_, c)| c.clone()).collect();
+                    covers.sort_unstable();
+                    covers.dedup();
+                    if covers.len() != size {
+                        continue;
+                    }
+
+                    let base_cells: Vec<usize> =
+                        subset.iter().flat_map(|(b, _)| base[*b].clone()).collect();
+                    let eliminations = covers
+                        .iter()
+                        .flat_map(|c| self.eliminate_outside(&cover[*c], &base_cells, val))
+                        .collect();
+                    let units = subset.iter().map(|(b, _)| label(*b)).collect();
+
+                    if let Some(step) = Step::elimination(technique, units, eliminations) {
+                        return Some(step);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    fn sees(&self, a: usize, b: usize) -> bool {
+        self.constraints.get_constrained_inds(a).contains(&b)
+    }
+
+    // Eliminates `val` from the open cells outside `cells` that see all of
+    // them.
+    fn eliminate_seen_by(&self, cells: &[usize], val: u8) -> Vec<(usize, u8)> {
+        self.constraints
+            .get_constrained_inds(cells[0])
+            .iter()
+            .copied()
+            .filter(|i| !cells.contains(i) && self.has(*i, val) && !self.is_solved(*i))
+            .filter(|i| cells[1..].iter().all(|c| self.sees(*i, *c)))
+            .map(|i| (i, val))
+            .collect()
+    }
+
+    fn bivalue_peers(&self, idx: usize) -> Vec<usize> {
+        self.constraints
+            .get_constrained_inds(idx)
+            .iter()
+            .copied()
+            .filter(|i| self.masks[*i].count_ones() == 2)
+            .collect()
+    }
+
+    // A pivot with candidates xy sees pincers with xz and yz. Whichever
+    // value the pivot takes, one pincer is z, so no cell seeing both
+    // pincers can be.
+    fn xy_wing(&self) -> Option<Step> {
+        for pivot in 0..self.masks.len() {
+            let mask = self.masks[pivot];
+            if mask.count_ones() != 2 {
+                continue;
+            }
+
+            let pincers: Vec<usize> = self
+                .bivalue_peers(pivot)
+                .into_iter()
+                .filter(|i| (self.masks[*i] & mask).count_ones() == 1)
+                .collect();
+            for (n, a) in pincers.iter().enumerate() {
+                for b in &pincers[n + 1..] {
+                    let (ma, mb) = (self.masks[*a], self.masks[*b]);
+                    let z = ma & mb & !mask;
+                    if z == 0 || ma & mask == mb & mask {
+                        continue;
+                    }
+
+                    let val = z.trailing_zeros() as u8;
+                    let eliminations = self.eliminate_seen_by(&[*a, *b], val);
+                    if let Some(step) = Step::elimination(Technique::XyWing, vec![], eliminations) {
+                        return Some(step);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    // Like an xy-wing, but the pivot may also be z, so only cells seeing the
+    // pivot as well as both pincers lose z.
+    fn xyz_wing(&self) -> Option<Step> {
+        for pivot in 0..self.masks.len() {
+            let mask = self.masks[pivot];
+            if mask.count_ones() != 3 {
+                continue;
+            }
+
+            let pincers: Vec<usize> = self
+                .bivalue_peers(pivot)
+                .into_iter()
+                .filter(|i| self.masks[*i] & !mask == 0)
+                .collect();
+            for (n, a) in pincers.iter().enumerate() {
+                for b in &pincers[n + 1..] {
+                    let (ma, mb) = (self.masks[*a], self.masks[*b]);
+                    let z = ma & mb;
+                    if ma == mb || z.count_ones() != 1 {
+                        continue;
+                    }
+
+                    let val = z.trailing_zeros() as u8;
+                    let eliminations = self.eliminate_seen_by(&[pivot, *a, *b], val);
+                    if let Some(step) = Step::elimination(Technique::XyzWing, vec![], eliminations)
+                    {
+                        return Some(step);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    // Chains the cells of units where a value has exactly two places, and
+    // colours them alternately: one colour holds the value, the other does
+    // not. A colour that sees itself is false everywhere, and a cell that
+    // sees both colours cannot hold the value.
+    fn simple_coloring(&self) -> Option<Step> {
+        let cells = self.masks.len();
+
+        for val in 1..=self.size {
+            let mut links = vec![vec![]; cells];
+            for (_, unit) in self.units() {
+                if let [a, b] = self.cells_with(unit, val)[..] {
+                    if !self.is_solved(a) && !self.is_solved(b) {
+                        links[a].push(b);
+                        links[b].push(a);
+                    }
+                }
+            }
+
+            let mut colour = vec![None; cells];
+            for start in 0..cells {
+                if links[start].is_empty() || colour[start].is_some() {
+                    continue;
+                }
+
+                let mut chain = vec![start];
+                colour[start] = Some(false);
+                let mut n = 0;
+                while n < chain.len() {
+                    let idx = chain[n];
+                    for next in &links[idx] {
+                        if colour[*next].is_none() {
+                            colour[*next] = colour[idx].map(|c| !c);
+                            chain.push(*next);
+                        }
+                    }
+                    n += 1;
+                }
+
+                let eliminations = self.colour_eliminations(&chain, &colour, val);
+                if let Some(step) =
+                    Step::elimination(Technique::SimpleColoring, vec![], eliminations)
+                {
+                    return Some(step);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn colour_eliminations(
+        &self,
+        chain: &[usize],
+        colour: &[Option<bool>],
+        val: u8,
+    ) -> Vec<(usize, u8)> {
+        for side in [false, true] {
+            let cells: Vec<usize> = chain
+                .iter()
+                .copied()
+                .filter(|i| colour[*i] == Some(side))
+                .collect();
+            let wraps = cells
+                .iter()
+                .enumerate()
+                .any(|(n, a)| cells[n + 1..].iter().any(|b| self.sees(*a, *b)));
+            if wraps {
+                return cells.into_iter().map(|i| (i, val)).collect();
+            }
+        }
+
+        (0..self.masks.len())
+            .filter(|i| !chain.contains(i) && self.has(*i, val) && !self.is_solved(*i))
+            .filter(|i| {
+                let seen: Vec<bool> = chain
+                    .iter()
+                    .filter(|c| self.sees(*i, **c))
+                    .map(|c| colour[*c] == Some(true))
+                    .collect();
+                seen.contains(&true) && seen.contains(&false)
+            })
+            .map(|i| (i, val))
+            .collect()
+    }
+}
+
+#[cfg(feature = "std")]
+fn combinations<T: Clone>(items: &[T], size: usize) -> Vec<Vec<T>> {
+    if size == 0 {
+        return vec![vec![]];
+    }
+    if items.len() < size {
+        return vec![];
+    }
+
+    let mut out: Vec<Vec<T>> = combinations(&items[1..], size - 1)
+        .into_iter()
+        .map(|mut rest| {
+            rest.insert(0, items[0].clone());
+            rest
+        })
+        .collect();
+    out.extend(combinations(&items[1..], size));
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::{combinations, find_step, naked_single, solve_logically, Step, Technique, Unit};
+    use crate::state::State;
+
+    #[test]
+    fn can_enumerate_combinations() {
+        assert_eq!(
+            combinations(&[1, 2, 3, 4], 2),
+            vec![
+                vec![1, 2],
+                vec![1, 3],
+                vec![1, 4],
+                vec![2, 3],
+                vec![2, 4],
+                vec![3, 4]
+            ]
+        );
+        assert_eq!(combinations(&[1, 2], 3), Vec::<Vec<i32>>::new());
+    }
+
+    #[test]
+    fn steps_are_sound() {
+        let puzzles = [
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+            "000000010400000000020000000000050407008000300001090000300400200050100000000806000",
+            "100007090030020008009600500005300900010080002600004000300000010040000007007000300",
+            "900040000000600031020000090000700020002935600070002000060000073510009000000080009",
+            "000704005020010070000080002090006250600070008053200010400090000030060090200407000",
+            "200041006400602010016090004300129640142060590069504001584216379920408165601950482",
+        ];
+
+        for puzzle in puzzles {
+            let mut state = State::from(puzzle);
+            let mut solution = state.clone();
+            solution.solve().unwrap();
+            let solution = solution.values();
+
+            state.propagate_constraints().unwrap();
+            while let Some(step) = find_step(&state) {
+                for (idx, val) in &step.placements {
+                    assert_eq!(solution[*idx], *val, "{:?}", step.technique);
+                }
+                for (idx, val) in &step.eliminations {
+                    assert_ne!(solution[*idx], *val, "{:?}", step.technique);
+                }
+
+                step.apply(&mut state).unwrap();
+                state.propagate_constraints().unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn can_solve_logically() {
+        let mut state = State::from(
+            "000030007480960501063570820009610203350097006000005094000000005804706910001040070",
+        );
+        let mut techniques = vec![];
+
+        assert_eq!(
+            solve_logically(&mut state, &mut |step| techniques.push(step.technique)),
+            Ok(true)
+        );
+        assert_eq!(techniques.len(), 81 - 37);
+        assert!(techniques.iter().all(|t| *t <= Technique::HiddenSingle));
+    }
+
+    #[test]
+    fn can_solve_with_wings_and_coloring() {
+        let mut state = State::from(
+            "200041006400602010016090004300129640142060590069504001584216379920408165601950482",
+        );
+        let mut techniques = vec![];
+
+        assert_eq!(
+            solve_logically(&mut state, &mut |step| techniques.push(step.technique)),
+            Ok(true)
+        );
+        for technique in [
+            Technique::XyWing,
+            Technique::XyzWing,
+            Technique::SimpleColoring,
+        ] {
+            assert!(techniques.contains(&technique), "{technique}");
+        }
+    }
+
+    #[test]
+    fn can_find_naked_single() {
+        let state = State::from("1230000000000000");
+
+        assert_eq!(
+            naked_single(&state),
+            Some(Step::placement(Technique::NakedSingle, vec![], 3, 4))
+        );
+        assert_eq!(naked_single(&State::from("1000000000000000")), None);
+    }
+
+    #[test]
+    fn can_describe_step() {
+        let step = Step {
+            technique: Technique::LockedCandidates,
+            units: vec![Unit::Block(0), Unit::Row(2)],
+            placements: vec![],
+            eliminations: vec![(21, 4), (24, 4)],
+        };
+
+        assert_eq!(
+            step.describe(9),
+            "locked candidates in block 1, row 3: r3c4 ≠ 4, r3c7 ≠ 4"
+        );
+        assert_eq!(
+            Step::placement(Technique::NakedSingle, vec![], 80, 9).describe(9),
+            "naked single: r9c9 = 9"
+        );
+    }
+
+    #[test]
+    fn can_parse_techniques() {
+        assert_eq!("x-wing".parse(), Ok(Technique::XWing));
+        assert_eq!("Hidden Pair".parse(), Ok(Technique::HiddenPair));
+        assert_eq!("locked_candidates".parse(), Ok(Technique::LockedCandidates));
+        assert_eq!("XY-Wing".parse(), Ok(Technique::XyWing));
+        assert_eq!("coloring".parse(), Ok(Technique::SimpleColoring));
+        assert!("guessing".parse::<Technique>().is_err());
+    }
+}
@@ -0,0 +1,239 @@
+use crate::format::OutputFormat;
+use crate::state::State;
+use crate::strategies::Step;
+#[cfg(any(feature = "server", feature = "wasm"))]
+use crate::strategies::Technique;
+use alloc::{format, string::String, vec::Vec};
+use core::fmt::Display;
+#[cfg(any(feature = "server", feature = "wasm"))]
+use serde::Serialize;
+
+// Names a cell by its one-based row and column, e.g. `r3c5`.
+pub fn cell_name(idx: usize, size: usize) -> String {
+    format!("r{}c{}", idx / size + 1, idx % size + 1)
+}
+
+// Every deduction made while solving a puzzle, in order. If logic alone
+// could not finish the puzzle, `searched` counts the cells that were left
+// to the solver's search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolveTrace {
+    pub size: usize,
+    pub steps: Vec<Step>,
+    pub searched: usize,
+}
+
+impl Display for SolveTrace {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write_steps(f, &self.steps, self.size)?;
+
+        if self.searched > 0 {
+            writeln!(
+                f,
+                "no further deductions, {} cells found by search",
+                self.searched
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+fn write_steps(f: &mut core::fmt::Formatter<'_>, steps: &[Step], size: usize) -> core::fmt::Result {
+    for (i, step) in steps.iter().enumerate() {
+        writeln!(f, "{:>3}. {}", i + 1, step.describe(size))?;
+    }
+
+    Ok(())
+}
+
+// How far a puzzle got when solved by logic alone: every deduction made, and
+// the cells still open when no allowed technique applied any more.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogicalSolve {
+    pub size: usize,
+    pub steps: Vec<Step>,
+    pub unsolved: usize,
+}
+
+impl LogicalSolve {
+    pub fn is_solved(&self) -> bool {
+        self.unsolved == 0
+    }
+}
+
+impl Display for LogicalSolve {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write_steps(f, &self.steps, self.size)?;
+
+        if self.unsolved > 0 {
+            writeln!(f, "stuck with {} cells open", self.unsolved)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl SolveTrace {
+    // Describes the steps like `Display`, following each with the grid of
+    // candidates left once it has been replayed on `puzzle`, the puzzle
+    // this trace was made from.
+    pub fn with_candidates(&self, puzzle: &State) -> String {
+        let size = self.size;
+        let mut state = puzzle.clone();
+        let place = |state: &mut State, idx: usize, val: u8| {
+            state
+                .set(idx / size, idx % size, val)
+                .expect("steps in a trace replay on its puzzle");
+        };
+        for (idx, val) in puzzle.values().into_iter().enumerate() {
+            if val != 0 {
+                place(&mut state, idx, val);
+            }
+        }
+
+        let mut out = String::new();
+        for (i, step) in self.steps.iter().enumerate() {
+            out.push_str(&format!("{:>3}. {}\n", i + 1, step.describe(size)));
+            for (idx, val) in &step.placements {
+                place(&mut state, *idx, *val);
+            }
+            for (idx, val) in &step.eliminations {
+                state
+                    .eliminate(*idx, *val)
+                    .expect("steps in a trace replay on its puzzle");
+            }
+            out.push_str(&state.format(OutputFormat::Candidates));
+        }
+
+        if self.searched > 0 {
+            out.push_str(&format!(
+                "no further deductions, {} cells found by search\n",
+                self.searched
+            ));
+        }
+
+        out
+    }
+}
+
+// A change the solver makes to the grid, as reported to the observer given
+// to `Solver::solve_with_observer`. Rows and columns count from zero, and a
+// guess's `depth` counts the guesses in force including itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolveEvent {
+    Placed {
+        row: usize,
+        col: usize,
+        value: u8,
+    },
+    Eliminated {
+        row: usize,
+        col: usize,
+        value: u8,
+    },
+    Guessed {
+        row: usize,
+        col: usize,
+        value: u8,
+        depth: usize,
+    },
+    // The guess at `depth` was wrong, and every change made since it was
+    // placed is undone.
+    Backtracked {
+        depth: usize,
+    },
+}
+
+// The next deduction available in a grid of `size`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hint {
+    pub step: Step,
+    pub size: usize,
+}
+
+// A hint as JSON, with cells as indices into the puzzle string.
+#[cfg(any(feature = "server", feature = "wasm"))]
+#[derive(Serialize)]
+pub(crate) struct HintJson {
+    technique: Technique,
+    description: String,
+    placements: Vec<(usize, u8)>,
+    eliminations: Vec<(usize, u8)>,
+}
+
+#[cfg(any(feature = "server", feature = "wasm"))]
+impl From<Hint> for HintJson {
+    fn from(hint: Hint) -> Self {
+        HintJson {
+            technique: hint.step.technique,
+            description: hint.to_string(),
+            placements: hint.step.placements,
+            eliminations: hint.step.eliminations,
+        }
+    }
+}
+
+impl Display for Hint {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.step.describe(self.size))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::solver::Solver;
+    use crate::state::State;
+
+    #[test]
+    fn can_trace_solve() {
+        let mut state = State::from(
+            "301086504046521070500000001400800002080347900009050038004090200008734090007208103",
+        );
+        let trace = Solver::new().solve_with_trace(&mut state).unwrap();
+
+        assert!(state.is_solved());
+        assert_eq!(trace.searched, 0);
+        assert_eq!(
+            trace
+                .steps
+                .iter()
+                .map(|s| s.placements.len())
+                .sum::<usize>(),
+            42
+        );
+        assert!(trace
+            .to_string()
+            .lines()
+            .next()
+            .unwrap()
+            .starts_with("  1. "));
+    }
+
+    #[test]
+    fn can_trace_search_fallback() {
+        let mut state = State::from(
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+        );
+        let trace = Solver::new().solve_with_trace(&mut state).unwrap();
+
+        assert!(state.is_solved());
+        assert!(trace.searched > 0);
+        assert!(trace.to_string().ends_with("cells found by search\n"));
+    }
+
+    #[test]
+    fn can_trace_with_candidates() {
+        let puzzle = State::from("1000002000030000");
+        let trace = Solver::new().solve_with_trace(&mut puzzle.clone()).unwrap();
+        let out = trace.with_candidates(&puzzle);
+        let lines: Vec<&str> = out.lines().collect();
+
+        // each step is followed by a 13 line grid
+        assert_eq!(lines.len(), trace.steps.len() * 14 + 1);
+        assert_eq!(lines[0], "  1. naked single: r1c2 = 2");
+        assert_eq!(lines[2], "│ 1 ·   · 2 │ · ·   · · │");
+        assert_eq!(lines[3], "│ · ·   · · │ 3 4   · 4 │");
+        assert!(out.ends_with("cells found by search\n"));
+    }
+}
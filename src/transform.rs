@@ -0,0 +1,249 @@
+use crate::shapes::Shape;
+use crate::state::State;
+use alloc::collections::BTreeSet;
+use alloc::{vec, vec::Vec};
+use thiserror::Error;
+
+// Why a puzzle could not be transformed.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum TransformError {
+    #[error("the transformation does not map the puzzle's rules onto themselves")]
+    BreaksRules,
+    #[error("rows {} and {} are not in the same band", .0 + 1, .1 + 1)]
+    DifferentBands(usize, usize),
+    #[error("digit map should hold every digit from 1 to {0} once")]
+    InvalidDigitMap(usize),
+}
+
+// Transformations that turn a puzzle into an equivalent one, with the same
+// number of solutions. Each checks that the puzzle's rules are unchanged by
+// it, e.g. transposing a 6×6 grid would turn its 3×2 blocks into 2×3 ones.
+impl State {
+    // Turns the grid a quarter turn clockwise.
+    pub fn rotate90(&self) -> Result<State, TransformError> {
+        let size = self.size();
+
+        self.move_cells(|idx| idx % size * size + size - 1 - idx / size)
+    }
+
+    // Mirrors the grid in its main diagonal, swapping rows and columns.
+    pub fn transpose(&self) -> Result<State, TransformError> {
+        let size = self.size();
+
+        self.move_cells(|idx| idx % size * size + idx / size)
+    }
+
+    // Swaps two rows of the same band, counting from zero.
+    pub fn swap_rows_within_band(&self, a: usize, b: usize) -> Result<State, TransformError> {
+        let size = self.size();
+        let (_, box_h) = self.constraints().block_shape();
+        if a >= size || b >= size || a / box_h != b / box_h {
+            return Err(TransformError::DifferentBands(a, b));
+        }
+
+        self.move_cells(|idx| match idx / size {
+            r if r == a => b * size + idx % size,
+            r if r == b => a * size + idx % size,
+            _ => idx,
+        })
+    }
+
+    // Relabels every digit `d` as `map[d - 1]`. Cage sums and shapes would
+    // change, so killer, thermometer and arrow puzzles can only be given the
+    // identity.
+    pub fn permute_digits(&self, map: &[u8]) -> Result<State, TransformError> {
+        let size = self.size();
+        let mut sorted = map.to_vec();
+        sorted.sort_unstable();
+        if !sorted.iter().copied().eq(1..=size as u8) {
+            return Err(TransformError::InvalidDigitMap(size));
+        }
+        let constraints = self.constraints();
+        let fixed = constraints.cages().is_empty() && constraints.shapes().is_empty();
+        if !fixed && !map.iter().copied().eq(1..=size as u8) {
+            return Err(TransformError::BreaksRules);
+        }
+
+        let masks: Vec<u32> = self
+            .candidate_masks()
+            .into_iter()
+            .map(|mask| {
+                (1..=size)
+                    .filter(|d| mask & 1 << d != 0)
+                    .fold(0, |moved, d| moved | 1 << map[d - 1])
+            })
+            .collect();
+
+        let mut state = self.clone();
+        state.set_masks(&masks);
+        Ok(state)
+    }
+
+    // Moves the candidates of each cell `idx` to `map(idx)`, if every unit,
+    // peer, cage and shape lands on one of the same kind.
+    fn move_cells(&self, map: impl Fn(usize) -> usize) -> Result<State, TransformError> {
+        let constraints = self.constraints();
+        let moved = |cells: &[usize]| sorted(cells.iter().map(|i| map(*i)));
+
+        let units: BTreeSet<Vec<usize>> = constraints
+            .units()
+            .map(|u| sorted(u.iter().copied()))
+            .collect();
+        let cages: BTreeSet<(u32, Vec<usize>)> = constraints
+            .cages()
+            .iter()
+            .map(|c| (c.sum, sorted(c.cells.iter().copied())))
+            .collect();
+        // arrow cells are compared in sorted order by moving them in place
+        let shapes: Vec<Shape> = constraints
+            .shapes()
+            .iter()
+            .map(|s| s.moved(|i| i))
+            .collect();
+        let keeps_rules = constraints.units().all(|u| units.contains(&moved(u)))
+            && constraints
+                .cages()
+                .iter()
+                .all(|c| cages.contains(&(c.sum, moved(&c.cells))))
+            && constraints
+                .shapes()
+                .iter()
+                .all(|s| shapes.contains(&s.moved(&map)))
+            && (0..self.size() * self.size()).all(|idx| {
+                moved(constraints.get_constrained_inds(idx))
+                    == constraints.get_constrained_inds(map(idx))
+            });
+        if !keeps_rules {
+            return Err(TransformError::BreaksRules);
+        }
+
+        let masks = self.candidate_masks();
+        let mut moved_masks = vec![0; masks.len()];
+        for (idx, mask) in masks.iter().enumerate() {
+            moved_masks[map(idx)] = *mask;
+        }
+
+        let mut state = self.clone();
+        state.set_masks(&moved_masks);
+        state.move_givens(map);
+        Ok(state)
+    }
+}
+
+fn sorted(cells: impl Iterator<Item = usize>) -> Vec<usize> {
+    let mut cells: Vec<usize> = cells.collect();
+    cells.sort_unstable();
+    cells
+}
+
+#[cfg(test)]
+mod test {
+    use super::TransformError;
+    use crate::shapes::Shape;
+    use crate::state::State;
+    use crate::variant::Variant;
+
+    const PUZZLE: &str =
+        "301086504046521070500000001400800002080347900009050038004090200008734090007208103";
+
+    fn solved(state: &State) -> String {
+        let mut state = state.clone();
+        state.solve().unwrap();
+        format!("{state}")
+    }
+
+    #[test]
+    fn can_rotate_and_transpose() {
+        let state = State::from("1200340000000000");
+        let rotated = state.rotate90().unwrap();
+        assert_eq!(format!("{rotated}"), "0031004200000000");
+
+        assert!(rotated.is_given(0, 3) && !rotated.is_given(0, 0));
+
+        let back = (0..3).fold(rotated, |s, _| s.rotate90().unwrap());
+        assert_eq!(format!("{back}"), "1200340000000000");
+        assert_eq!(
+            format!("{}", state.transpose().unwrap()),
+            "1300240000000000"
+        );
+
+        let state = State::from(PUZZLE);
+        assert_eq!(
+            solved(&state.rotate90().unwrap()),
+            format!(
+                "{}",
+                State::from(solved(&state).as_str()).rotate90().unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn can_swap_rows_and_digits() {
+        let state = State::from("1200340000000000");
+
+        assert_eq!(
+            format!("{}", state.swap_rows_within_band(0, 1).unwrap()),
+            "3400120000000000"
+        );
+        assert_eq!(
+            state.swap_rows_within_band(1, 2).unwrap_err(),
+            TransformError::DifferentBands(1, 2)
+        );
+        assert_eq!(
+            format!("{}", state.permute_digits(&[4, 3, 2, 1]).unwrap()),
+            "4300210000000000"
+        );
+        assert_eq!(
+            state.permute_digits(&[1, 1, 2, 3]).unwrap_err(),
+            TransformError::InvalidDigitMap(4)
+        );
+    }
+
+    #[test]
+    fn can_reject_transformations_breaking_rules() {
+        let six = State::from(&"0".repeat(36) as &str);
+        assert_eq!(six.transpose().unwrap_err(), TransformError::BreaksRules);
+        assert!(six.rotate90().is_err());
+
+        let x = State::from(PUZZLE).with_variant(Variant::X);
+        assert!(x.rotate90().is_ok());
+        assert_eq!(
+            x.swap_rows_within_band(0, 1).unwrap_err(),
+            TransformError::BreaksRules
+        );
+
+        let jigsaw = State::from("0000000000000000")
+            .with_regions("1112 1322 3342 3444")
+            .unwrap();
+        assert_eq!(jigsaw.transpose().unwrap_err(), TransformError::BreaksRules);
+
+        // swapping rows reorders the first column, so its sandwich changes
+        let sandwich = State::from("1000200040003000").with_shapes(vec![Shape::Sandwich {
+            cells: vec![0, 4, 8, 12],
+            sum: 2,
+        }]);
+        assert_eq!(
+            sandwich.swap_rows_within_band(0, 1).unwrap_err(),
+            TransformError::BreaksRules
+        );
+
+        // turned, rows become columns and some read backwards, so clues of
+        // 5 on the outer lines and 0 on the inner ones stay in place
+        let lines = State::from("0000000000000000").with_shapes(
+            (0..4)
+                .flat_map(|i| [(i * 4..i * 4 + 4).collect(), (i..16).step_by(4).collect()])
+                .map(|cells: Vec<usize>| Shape::Sandwich {
+                    sum: if cells.contains(&5) || cells.contains(&10) {
+                        0
+                    } else {
+                        5
+                    },
+                    cells,
+                })
+                .collect(),
+        );
+        let turned = lines.rotate90().unwrap();
+        assert_eq!(turned.count_solutions(100), 4);
+        assert_eq!(turned.count_solutions(100), lines.count_solutions(100));
+    }
+}
@@ -0,0 +1,526 @@
+use crate::constraints::Constraints;
+use crate::shapes::Shape;
+use crate::state::{digit_char, SolveError, State};
+use crate::strategies::{self, Unit};
+use crate::trace::cell_name;
+use alloc::{collections::BTreeSet, format, string::String, vec, vec::Vec};
+use core::fmt::Display;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Solutions {
+    None,
+    Unique,
+    Multiple,
+}
+
+// A digit given more than once in the same unit, with the offending cells.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Duplicate {
+    pub unit: Unit,
+    pub value: u8,
+    pub cells: Vec<usize>,
+}
+
+// Two givens holding the same digit that see each other through a rule other
+// than a unit, such as a knight's move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clash {
+    pub value: u8,
+    pub cells: [usize; 2],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Validation {
+    pub size: usize,
+    pub duplicates: Vec<Duplicate>,
+    pub clashes: Vec<Clash>,
+    pub empty_cells: Vec<usize>,
+    // Cells that all see each other under the combined rules, more of them
+    // than there are digits, so no grid can follow the rules at all.
+    pub clique: Option<Vec<usize>>,
+    // Shapes, such as sandwich clues, that no digits allowed by the givens
+    // complete.
+    pub broken_shapes: Vec<Shape>,
+    // Where filling in the cells the givens force first goes wrong, when
+    // nothing above already explains it.
+    pub contradiction: Option<SolveError>,
+    pub solutions: Solutions,
+}
+
+impl Validation {
+    pub fn is_valid(&self) -> bool {
+        self.duplicates.is_empty()
+            && self.clashes.is_empty()
+            && self.empty_cells.is_empty()
+            && self.clique.is_none()
+            && self.broken_shapes.is_empty()
+            && self.contradiction.is_none()
+            && self.solutions == Solutions::Unique
+    }
+}
+
+impl Display for Validation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let names = |cells: &[usize]| {
+            cells
+                .iter()
+                .map(|idx| cell_name(*idx, self.size))
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+
+        for duplicate in &self.duplicates {
+            writeln!(
+                f,
+                "{} is given more than once in {}: {}",
+                digit_char(duplicate.value),
+                duplicate.unit,
+                names(&duplicate.cells)
+            )?;
+        }
+        for clash in &self.clashes {
+            writeln!(
+                f,
+                "{} and {} both hold {} but see each other",
+                cell_name(clash.cells[0], self.size),
+                cell_name(clash.cells[1], self.size),
+                digit_char(clash.value)
+            )?;
+        }
+        if !self.empty_cells.is_empty() {
+            writeln!(f, "no candidates left in {}", names(&self.empty_cells))?;
+        }
+        if let Some(clique) = &self.clique {
+            writeln!(
+                f,
+                "the rules can't be met: {} all see each other, but there are only {} digits",
+                names(clique),
+                self.size
+            )?;
+        }
+        for shape in &self.broken_shapes {
+            writeln!(
+                f,
+                "the {} can't be completed from the givens",
+                describe(shape, self.size)
+            )?;
+        }
+        if let Some(contradiction) = &self.contradiction {
+            writeln!(f, "the givens lead to a contradiction: {contradiction}")?;
+        }
+
+        let solutions = match self.solutions {
+            Solutions::None => "no solution",
+            Solutions::Unique => "a unique solution",
+            Solutions::Multiple => "multiple solutions",
+        };
+        write!(f, "puzzle has {solutions}")
+    }
+}
+
+// Why a grid is not a solution to its puzzle, found by `verify_solution`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum SolutionError {
+    #[error("r{}c{} is empty", .row + 1, .col + 1)]
+    Empty { row: usize, col: usize },
+    #[error("{unit} is missing {}", digit_char(*.value))]
+    Missing { unit: Unit, value: u8 },
+    #[error("r{}c{} and r{}c{} both hold {}", .cells[0].0 + 1, .cells[0].1 + 1, .cells[1].0 + 1, .cells[1].1 + 1, digit_char(*.value))]
+    Repeated {
+        cells: [(usize, usize); 2],
+        value: u8,
+    },
+    #[error("cage {} does not add up to {sum}", .cage + 1)]
+    Cage { cage: usize, sum: u32 },
+    #[error("shape {} is broken", .shape + 1)]
+    Shape { shape: usize },
+}
+
+impl State {
+    // Checks the givens for duplicates within a unit or between other cells
+    // that see each other, for cells left with no candidates and the rules
+    // for more cells seeing each other than there are digits, then counts
+    // solutions up to two.
+    pub fn validate(&self) -> Validation {
+        let constraints = self.constraints();
+        let values = self.values();
+
+        let mut duplicates = vec![];
+        for (unit, cells) in strategies::labelled_units(constraints) {
+            for value in 1..=self.size() as u8 {
+                let cells: Vec<usize> = cells
+                    .iter()
+                    .copied()
+                    .filter(|i| values[*i] == value)
+                    .collect();
+                if cells.len() > 1 {
+                    duplicates.push(Duplicate { unit, value, cells });
+                }
+            }
+        }
+
+        let mut clashes = vec![];
+        for (a, value) in values.iter().enumerate().filter(|(_, v)| **v != 0) {
+            for b in constraints.get_constrained_inds(a) {
+                let shared_unit = duplicates
+                    .iter()
+                    .any(|d| d.cells.contains(&a) && d.cells.contains(b));
+                if a < *b && values[*b] == *value && !shared_unit {
+                    clashes.push(Clash {
+                        value: *value,
+                        cells: [a, *b],
+                    });
+                }
+            }
+        }
+
+        let empty_cells: Vec<usize> = (0..values.len())
+            .filter(|idx| values[*idx] == 0)
+            .filter(|idx| {
+                let peers = constraints.get_constrained_inds(*idx);
+                self.candidates_at(*idx)
+                    .iter()
+                    .all(|v| peers.iter().any(|p| values[*p] == *v))
+            })
+            .collect();
+
+        let clique = large_clique(constraints);
+
+        let masks = self.candidate_masks();
+        let broken_shapes: Vec<Shape> = constraints
+            .shapes()
+            .iter()
+            .filter(|shape| shape.supported(&masks).contains(&0))
+            .cloned()
+            .collect();
+
+        let explained = !duplicates.is_empty()
+            || !clashes.is_empty()
+            || !empty_cells.is_empty()
+            || clique.is_some()
+            || !broken_shapes.is_empty();
+        let contradiction = match explained {
+            true => None,
+            false => self
+                .clone()
+                .propagate_constraints()
+                .map_err(|e| e.in_grid(constraints))
+                .err(),
+        };
+
+        let solutions = if explained || contradiction.is_some() {
+            Solutions::None
+        } else {
+            match self.count_solutions(2) {
+                0 => Solutions::None,
+                1 => Solutions::Unique,
+                _ => Solutions::Multiple,
+            }
+        };
+
+        Validation {
+            size: self.size(),
+            duplicates,
+            clashes,
+            empty_cells,
+            clique,
+            broken_shapes,
+            contradiction,
+            solutions,
+        }
+    }
+
+    // Checks that the grid is a solution to its rules from the digits alone,
+    // without trusting the candidates or the peers worked out from the units:
+    // every cell is filled, every unit holds each digit, cages add up with
+    // distinct digits, shapes hold, and no two cells that see each other, as
+    // in anti-knight puzzles, share a digit.
+    pub fn verify_solution(&self) -> Result<(), SolutionError> {
+        let size = self.size();
+        let constraints = self.constraints();
+        let values = self.values();
+
+        if let Some(idx) = values.iter().position(|v| *v == 0) {
+            return Err(SolutionError::Empty {
+                row: idx / size,
+                col: idx % size,
+            });
+        }
+
+        for (unit, cells) in strategies::labelled_units(constraints) {
+            for value in 1..=size as u8 {
+                if !cells.iter().any(|i| values[*i] == value) {
+                    return Err(SolutionError::Missing { unit, value });
+                }
+            }
+        }
+
+        for (i, cage) in constraints.cages().iter().enumerate() {
+            let digits: BTreeSet<u8> = cage.cells.iter().map(|c| values[*c]).collect();
+            let sum: u32 = digits.iter().map(|v| *v as u32).sum();
+            if digits.len() != cage.cells.len() || sum != cage.sum {
+                return Err(SolutionError::Cage {
+                    cage: i,
+                    sum: cage.sum,
+                });
+            }
+        }
+
+        if let Some(shape) = constraints
+            .shapes()
+            .iter()
+            .position(|shape| shape.error(&values) != 0)
+        {
+            return Err(SolutionError::Shape { shape });
+        }
+
+        for idx in 0..values.len() {
+            if let Some(peer) = constraints
+                .get_constrained_inds(idx)
+                .iter()
+                .find(|p| values[**p] == values[idx])
+            {
+                return Err(SolutionError::Repeated {
+                    cells: [(idx / size, idx % size), (peer / size, peer % size)],
+                    value: values[idx],
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Names a shape by where it starts on the grid.
+fn describe(shape: &Shape, size: usize) -> String {
+    match shape {
+        Shape::Thermometer(cells) => format!("thermometer from {}", cell_name(cells[0], size)),
+        Shape::Arrow { circle, .. } => format!("arrow from {}", cell_name(*circle, size)),
+        Shape::Pair { cells, .. } => format!(
+            "marker between {} and {}",
+            cell_name(cells[0], size),
+            cell_name(cells[1], size)
+        ),
+        Shape::Sandwich { cells, sum } => {
+            let line = match cells.get(1).map(|next| next - cells[0]) {
+                Some(1) => Unit::Row(cells[0] / size),
+                _ => Unit::Column(cells[0] % size),
+            };
+            format!("sandwich of {sum} in {line}")
+        }
+    }
+}
+
+// Looks for more cells than there are digits that all see each other, growing
+// a group from each cell by adding the peer that sees the most of the
+// remaining candidates. Units alone never make one, so classic grids are
+// skipped.
+fn large_clique(constraints: &Constraints) -> Option<Vec<usize>> {
+    if constraints.is_classic() {
+        return None;
+    }
+
+    let cells = constraints.size() * constraints.size();
+    let mut sees = vec![false; cells * cells];
+    for idx in 0..cells {
+        for peer in constraints.get_constrained_inds(idx) {
+            sees[idx * cells + peer] = true;
+        }
+    }
+
+    (0..cells).find_map(|start| {
+        let mut clique = vec![start];
+        let mut candidates = constraints.get_constrained_inds(start).to_vec();
+        while let Some(next) = candidates.iter().copied().max_by_key(|c| {
+            candidates
+                .iter()
+                .filter(|other| sees[c * cells + **other])
+                .count()
+        }) {
+            clique.push(next);
+            candidates.retain(|other| sees[next * cells + other]);
+        }
+
+        clique.sort_unstable();
+        (clique.len() > constraints.size()).then_some(clique)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Clash, SolutionError, Solutions};
+    use crate::killer::Cage;
+    use crate::shapes::Shape;
+    use crate::state::{SolveError, State};
+    use crate::strategies::Unit;
+    use crate::variant::Variant;
+
+    #[test]
+    fn can_validate() {
+        let validation = State::from(
+            "301086504046521070500000001400800002080347900009050038004090200008734090007208103",
+        )
+        .validate();
+        assert!(validation.is_valid());
+        assert_eq!(validation.to_string(), "puzzle has a unique solution");
+
+        let validation = State::from(&"0".repeat(16) as &str).validate();
+        assert_eq!(validation.solutions, Solutions::Multiple);
+        assert!(!validation.is_valid());
+    }
+
+    #[test]
+    fn can_find_duplicates() {
+        let validation = State::from("1100000000000000").validate();
+
+        assert_eq!(validation.duplicates.len(), 2);
+        assert_eq!(validation.duplicates[0].unit, Unit::Row(0));
+        assert_eq!(validation.duplicates[0].cells, vec![0, 1]);
+        assert_eq!(validation.solutions, Solutions::None);
+        assert!(validation
+            .to_string()
+            .starts_with("1 is given more than once in row 1: r1c1, r1c2\n"));
+    }
+
+    #[test]
+    fn can_find_empty_cells() {
+        let validation = State::from("1200000003000400").validate();
+
+        assert!(validation.duplicates.is_empty());
+        assert_eq!(validation.empty_cells, vec![5]);
+        assert_eq!(validation.solutions, Solutions::None);
+    }
+
+    #[test]
+    fn can_find_contradictions() {
+        let validation = State::from(
+            "000000500040021000500000001400000009080047000009000038000090200008030090007208103",
+        )
+        .validate();
+
+        // nothing clashes directly, but the digits the givens force do
+        assert!(validation.duplicates.is_empty() && validation.empty_cells.is_empty());
+        assert_eq!(
+            validation.contradiction,
+            Some(SolveError::Contradiction {
+                row: 7,
+                col: 6,
+                value: 4,
+                unit: Some(Unit::Column(6))
+            })
+        );
+        assert_eq!(validation.solutions, Solutions::None);
+        assert!(validation.to_string().starts_with(
+            "the givens lead to a contradiction: cell r8c7 can only hold 4, which is already in column 7\n"
+        ));
+    }
+
+    #[test]
+    fn can_find_broken_shapes() {
+        let sandwich = Shape::Sandwich {
+            cells: vec![0, 1, 2, 3],
+            sum: 5,
+        };
+        let validation = State::from("1400000000000000")
+            .with_shapes(vec![sandwich.clone()])
+            .validate();
+
+        assert_eq!(validation.broken_shapes, vec![sandwich]);
+        assert_eq!(validation.solutions, Solutions::None);
+        assert!(validation
+            .to_string()
+            .starts_with("the sandwich of 5 in row 1 can't be completed from the givens\n"));
+    }
+
+    #[test]
+    fn can_find_clashes_between_variant_peers() {
+        let mut puzzle = "0".repeat(81);
+        puzzle.replace_range(2..3, "5");
+        puzzle.replace_range(13..14, "5");
+        let validation = State::from(puzzle.as_str())
+            .with_variant(Variant::AntiKnight)
+            .validate();
+
+        assert!(validation.duplicates.is_empty());
+        assert_eq!(
+            validation.clashes,
+            vec![Clash {
+                value: 5,
+                cells: [2, 13]
+            }]
+        );
+        assert_eq!(validation.solutions, Solutions::None);
+        assert!(validation
+            .to_string()
+            .starts_with("r1c3 and r2c5 both hold 5 but see each other\n"));
+    }
+
+    #[test]
+    fn can_find_infeasible_rules() {
+        let empty = State::from(&"0".repeat(16) as &str);
+        let validation = empty.clone().with_variant(Variant::AntiKing).validate();
+        let clique = validation.clique.clone().unwrap();
+        let constraints = empty.clone().with_variant(Variant::AntiKing);
+        assert!(clique.len() > 4);
+        for a in &clique {
+            for b in clique.iter().filter(|b| *b != a) {
+                assert!(constraints
+                    .constraints()
+                    .get_constrained_inds(*a)
+                    .contains(b));
+            }
+        }
+        assert_eq!(validation.solutions, Solutions::None);
+        assert!(validation.to_string().contains("the rules can't be met"));
+
+        let nine = State::from(&"0".repeat(81) as &str);
+        assert_eq!(nine.clone().validate().clique, None);
+        assert_eq!(
+            nine.with_variants(&[Variant::X, Variant::AntiKing])
+                .validate()
+                .clique,
+            None
+        );
+    }
+
+    #[test]
+    fn can_verify_solution() {
+        let solution = "1234341221434321";
+        assert_eq!(State::from(solution).verify_solution(), Ok(()));
+
+        let error = State::from("1234341221434320").verify_solution();
+        assert_eq!(error, Err(SolutionError::Empty { row: 3, col: 3 }));
+        assert_eq!(error.unwrap_err().to_string(), "r4c4 is empty");
+
+        // the first two digits swapped
+        assert_eq!(
+            State::from("2134341221434321").verify_solution(),
+            Err(SolutionError::Missing {
+                unit: Unit::Column(0),
+                value: 1
+            })
+        );
+
+        let caged = State::from(solution).with_cages(vec![Cage {
+            sum: 4,
+            cells: vec![0, 1],
+        }]);
+        assert_eq!(
+            caged.verify_solution(),
+            Err(SolutionError::Cage { cage: 0, sum: 4 })
+        );
+
+        let thermometer = State::from(solution).with_shapes(vec![Shape::Thermometer(vec![1, 0])]);
+        assert_eq!(
+            thermometer.verify_solution(),
+            Err(SolutionError::Shape { shape: 0 })
+        );
+
+        let anti_king = State::from(solution).with_variant(Variant::AntiKing);
+        assert_eq!(
+            anti_king.verify_solution().unwrap_err().to_string(),
+            "r2c2 and r3c3 both hold 4"
+        );
+    }
+}
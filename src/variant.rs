@@ -0,0 +1,188 @@
+use crate::constraints::Constraints;
+use crate::shapes::{self, Relation};
+use crate::state::State;
+use alloc::{format, string::String};
+use core::{fmt::Display, str::FromStr};
+
+// Extra rules layered on top of the classic row, column and block units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    // Both main diagonals must also contain every digit once.
+    X,
+    // Four extra windows must also contain every digit once.
+    Hyper,
+    // Cells a chess knight's move apart may not hold the same digit.
+    AntiKnight,
+    // Cells a chess king's move apart may not hold the same digit.
+    AntiKing,
+    // Orthogonally adjacent cells may not hold consecutive digits.
+    NonConsecutive,
+}
+
+impl Variant {
+    pub(crate) fn apply(self, constraints: Constraints) -> Constraints {
+        match self {
+            Variant::X => constraints.with_diagonals(),
+            Variant::Hyper => constraints.with_windows(),
+            Variant::AntiKnight => constraints.with_moves(&[(1, 2), (2, 1), (1, -2), (2, -1)]),
+            Variant::AntiKing => constraints.with_moves(&[(1, 1), (1, -1)]),
+            Variant::NonConsecutive => {
+                let pairs = shapes::adjacent_pairs(constraints.size(), Relation::NonConsecutive);
+                constraints.with_shapes(pairs)
+            }
+        }
+    }
+}
+
+impl FromStr for Variant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "x" | "diagonal" => Ok(Variant::X),
+            "hyper" | "windoku" => Ok(Variant::Hyper),
+            "anti-knight" => Ok(Variant::AntiKnight),
+            "anti-king" => Ok(Variant::AntiKing),
+            "non-consecutive" => Ok(Variant::NonConsecutive),
+            _ => Err(format!("unknown variant: {s}")),
+        }
+    }
+}
+
+impl Display for Variant {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let name = match self {
+            Variant::X => "x",
+            Variant::Hyper => "hyper",
+            Variant::AntiKnight => "anti-knight",
+            Variant::AntiKing => "anti-king",
+            Variant::NonConsecutive => "non-consecutive",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+// Combines the rules of every variant in `variants`, adding each only once
+// however often it is repeated.
+pub(crate) fn apply_all(variants: &[Variant], constraints: Constraints) -> Constraints {
+    variants
+        .iter()
+        .enumerate()
+        .filter(|(i, v)| !variants[..*i].contains(v))
+        .fold(constraints, |constraints, (_, v)| v.apply(constraints))
+}
+
+impl State {
+    pub fn with_variant(self, variant: Variant) -> Self {
+        self.with_variants(&[variant])
+    }
+
+    pub fn with_variants(self, variants: &[Variant]) -> Self {
+        let constraints = apply_all(variants, self.constraints().clone());
+        self.with_constraints(constraints)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Variant;
+    use crate::solver::{Backend, Solver};
+    use crate::state::State;
+
+    #[test]
+    fn can_solve_x_sudoku() {
+        for backend in [Backend::Propagation, Backend::Dlx] {
+            let mut state = State::from(&"0".repeat(81) as &str).with_variant(Variant::X);
+            Solver::with_backend(backend).solve(&mut state).unwrap();
+            let values = state.values();
+
+            for diagonal in state.constraints().diagonals() {
+                let mut digits: Vec<u8> = diagonal.iter().map(|i| values[*i]).collect();
+                digits.sort_unstable();
+                assert_eq!(digits, (1..=9).collect::<Vec<u8>>());
+            }
+        }
+    }
+
+    #[test]
+    fn can_solve_hyper_sudoku() {
+        let mut state = State::from(&"0".repeat(81) as &str).with_variant(Variant::Hyper);
+        Solver::new().solve(&mut state).unwrap();
+        let values = state.values();
+
+        assert_eq!(state.constraints().windows().len(), 4);
+        for window in state.constraints().windows() {
+            let mut digits: Vec<u8> = window.iter().map(|i| values[*i]).collect();
+            digits.sort_unstable();
+            assert_eq!(digits, (1..=9).collect::<Vec<u8>>());
+        }
+    }
+
+    #[test]
+    fn can_solve_anti_knight_and_king() {
+        for backend in [Backend::Propagation, Backend::Dlx] {
+            let mut state = State::from(&"0".repeat(81) as &str)
+                .with_variant(Variant::AntiKnight)
+                .with_variant(Variant::AntiKing);
+            Solver::with_backend(backend).solve(&mut state).unwrap();
+            let values = state.values();
+
+            for idx in 0..81 {
+                let (row, col) = (idx / 9, idx % 9);
+                for (r, c) in [(row + 1, col + 2), (row + 2, col + 1), (row + 1, col + 1)] {
+                    if r < 9 && c < 9 {
+                        assert_ne!(values[idx], values[r * 9 + c]);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn can_solve_non_consecutive() {
+        let mut state = State::from(&"0".repeat(81) as &str).with_variant(Variant::NonConsecutive);
+        Solver::new().solve(&mut state).unwrap();
+        let values = state.values();
+
+        assert!(state.satisfies_rules());
+        for idx in 0..81 {
+            for other in [idx + 1, idx + 9] {
+                if other < 81 && (other == idx + 9 || idx % 9 < 8) {
+                    assert_ne!(values[idx].abs_diff(values[other]), 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn can_combine_variants() {
+        let empty = || State::from(&"0".repeat(81) as &str);
+        let once = empty().with_variants(&[Variant::NonConsecutive, Variant::AntiKnight]);
+        let twice = empty().with_variants(&[
+            Variant::NonConsecutive,
+            Variant::AntiKnight,
+            Variant::NonConsecutive,
+        ]);
+        assert_eq!(
+            twice.constraints().shapes().len(),
+            once.constraints().shapes().len()
+        );
+
+        let mut state = empty().with_variants(&[Variant::X, Variant::AntiKing]);
+        Solver::new().solve(&mut state).unwrap();
+        assert!(state.verify_solution().is_ok());
+        assert_eq!(state.constraints().diagonals().len(), 2);
+    }
+
+    #[test]
+    fn can_parse_variant() {
+        assert_eq!("X".parse::<Variant>(), Ok(Variant::X));
+        assert_eq!("anti-knight".parse::<Variant>(), Ok(Variant::AntiKnight));
+        assert_eq!(
+            "non-consecutive".parse::<Variant>(),
+            Ok(Variant::NonConsecutive)
+        );
+        assert!("killer".parse::<Variant>().is_err());
+    }
+}
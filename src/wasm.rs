@@ -0,0 +1,107 @@
+use crate::generator::{self, Symmetry};
+use crate::rating::Difficulty;
+use crate::solver::Solver;
+use crate::state::{SolveError, State};
+use crate::trace::HintJson;
+use wasm_bindgen::prelude::*;
+
+// Bindings for JavaScript. Puzzles are passed as strings in any format
+// `State::from_str` accepts, and errors are thrown as JS `Error`s rather than
+// panicking, so the work is done by plain functions that return `Result`.
+
+// Parses a puzzle into `{"size": 9, "cells": [[candidates], ...], "givens":
+// [indices]}`.
+#[wasm_bindgen]
+pub fn parse(puzzle: &str) -> Result<String, JsError> {
+    parse_json(puzzle).map_err(|e| JsError::new(&e.to_string()))
+}
+
+// Solves a puzzle, returning the solution as one line of digits.
+#[wasm_bindgen]
+pub fn solve(puzzle: &str) -> Result<String, JsError> {
+    solve_line(puzzle).map_err(|e| JsError::new(&e.to_string()))
+}
+
+// The next logical step for a puzzle as JSON, or `undefined` if there is none.
+#[wasm_bindgen]
+pub fn hint(puzzle: &str) -> Result<Option<String>, JsError> {
+    hint_json(puzzle).map_err(|e| JsError::new(&e.to_string()))
+}
+
+// Generates a puzzle of the given difficulty and symmetry, e.g. "medium" and
+// "rotational", returning it as one line of digits.
+#[wasm_bindgen]
+pub fn generate(difficulty: &str, symmetry: &str) -> Result<String, JsError> {
+    generate_line(difficulty, symmetry).map_err(|e| JsError::new(&e))
+}
+
+fn parse_json(puzzle: &str) -> Result<String, SolveError> {
+    let state: State = puzzle.parse()?;
+
+    serde_json::to_string(&state).map_err(|e| SolveError::InvalidInput(e.to_string()))
+}
+
+fn solve_line(puzzle: &str) -> Result<String, SolveError> {
+    let mut state: State = puzzle.parse()?;
+    state.solve()?;
+
+    Ok(state.to_string())
+}
+
+fn hint_json(puzzle: &str) -> Result<Option<String>, SolveError> {
+    let state: State = puzzle.parse()?;
+
+    Solver::new()
+        .next_hint(&state)
+        .map(|hint| {
+            serde_json::to_string(&HintJson::from(hint))
+                .map_err(|e| SolveError::InvalidInput(e.to_string()))
+        })
+        .transpose()
+}
+
+fn generate_line(difficulty: &str, symmetry: &str) -> Result<String, String> {
+    let difficulty: Difficulty = difficulty.parse()?;
+    let symmetry: Symmetry = symmetry.parse()?;
+
+    Ok(generator::generate(difficulty, symmetry).to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{generate_line, hint_json, parse_json, solve_line};
+    use crate::state::State;
+
+    const PUZZLE: &str =
+        "000030007480960501063570820009610203350097006000005094000000005804706910001040070";
+
+    #[test]
+    fn can_parse_to_json() {
+        let json = parse_json("1200340000430021").unwrap();
+
+        assert!(json.starts_with(r#"{"size":4,"cells":[[1],[2],[1,2,3,4],"#));
+        assert_eq!(
+            parse_json("12").unwrap_err().to_string(),
+            "puzzle length 2 is not a square number"
+        );
+    }
+
+    #[test]
+    fn can_solve_and_hint() {
+        let solution = solve_line(PUZZLE).unwrap();
+        assert_eq!(solution.len(), 81);
+        assert!(!solution.contains('0'));
+
+        let hint = hint_json(PUZZLE).unwrap().unwrap();
+        assert!(hint.starts_with(r#"{"technique":"naked_single","description":"naked single: "#));
+        assert_eq!(hint_json(&solution).unwrap(), None);
+    }
+
+    #[test]
+    fn can_generate() {
+        let puzzle = generate_line("easy", "none").unwrap();
+        assert!(State::from(puzzle.as_str()).has_unique_solution());
+
+        assert!(generate_line("trivial", "none").is_err());
+    }
+}